@@ -0,0 +1,90 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use specs::prelude::*;
+
+use lib::{coordinate::Coordinate, entity_components::*, game_clock::GameClock, map::Map, map_memory::MapMemory, map_tile::MapTile};
+use lib::status_effects::StatusEffects;
+use lib::visibility_system::VisibilitySystem;
+
+const MAP_WIDTH: usize = 150;
+const MAP_HEIGHT: usize = 100;
+const MAP_DEPTH: usize = 2;
+const VIEWSHED_COUNT: usize = 120;
+const BENCHMARK_SEED: u64 = 2023;
+
+/// A full size station with a crowd of viewers scattered through its rooms
+fn build_world() -> World
+{
+    let mut entity_system = World::new();
+
+    entity_system.register::<Coordinate>();
+    entity_system.register::<Viewshed>();
+    entity_system.register::<MapMemory>();
+    entity_system.register::<StatusEffects>();
+
+    let mut rng = StdRng::seed_from_u64(BENCHMARK_SEED);
+    let (map, rooms) = Map::rooms_and_corridors_map(20, 5, 10, Coordinate::new(MAP_WIDTH, MAP_HEIGHT, MAP_DEPTH), &mut rng);
+
+    for i in 0..VIEWSHED_COUNT
+    {
+        let room = rooms[i % rooms.len()];
+        let coordinate = Coordinate::new(rng.gen_range(room.corner_one.x + 1..=room.corner_two.x),
+                                         rng.gen_range(room.corner_one.y + 1..=room.corner_two.y),
+                                         room.corner_one.z);
+
+        entity_system.create_entity()
+                     .with(coordinate)
+                     .with(Viewshed { visible_tiles: Vec::new(), range: 18, dirty: true })
+                     .with(MapMemory::new())
+                     .build();
+    }
+
+    entity_system.insert(map);
+    entity_system.insert(GameClock::default());
+
+    let mut visibility_system = VisibilitySystem{};
+    visibility_system.run_now(&entity_system);
+
+    entity_system
+}
+
+fn mark_all_dirty(entity_system: &World)
+{
+    for viewshed in (&mut entity_system.write_storage::<Viewshed>()).join()
+    {
+        viewshed.dirty = true;
+    }
+}
+
+fn visibility_benchmarks(criterion: &mut Criterion)
+{
+    let entity_system = build_world();
+    let mut visibility_system = VisibilitySystem{};
+
+    criterion.bench_function("visibility_all_dirty", |bencher| bencher.iter(||
+    {
+        mark_all_dirty(&entity_system);
+        visibility_system.run_now(&entity_system);
+    }));
+
+    criterion.bench_function("visibility_idle", |bencher| bencher.iter(||
+    {
+        visibility_system.run_now(&entity_system);
+    }));
+
+    // Toggling one wall should only recompute the viewsheds that can see it
+    let changed_coordinate = entity_system.read_storage::<Coordinate>().join().next().copied().unwrap();
+    let mut toggle = false;
+
+    criterion.bench_function("visibility_single_tile_change", |bencher| bencher.iter(||
+    {
+        toggle = !toggle;
+        let tile = if toggle { MapTile::wall() } else { MapTile::floor() };
+        entity_system.fetch_mut::<Map>().set_tile(Coordinate::new(changed_coordinate.x + 1, changed_coordinate.y, changed_coordinate.z), tile);
+        visibility_system.run_now(&entity_system);
+    }));
+}
+
+criterion_group!(benches, visibility_benchmarks);
+criterion_main!(benches);
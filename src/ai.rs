@@ -0,0 +1,338 @@
+use std::fs;
+
+use specs_derive::Component;
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+
+
+pub const DEFAULT_BEHAVIOURS_PATH: &str = "resources/behaviours.cfg";
+/// The profiles shipped with the game, used when the behaviours file can't be found
+const BUILT_IN_BEHAVIOURS: &str = include_str!("../resources/behaviours.cfg");
+
+/// Something an NPC can spend its turn doing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Behaviour
+{
+    Attack,
+    Flee,
+    SeekMedbay,
+    Repair,
+    Investigate,
+    Search,
+    Patrol,
+    Wander,
+}
+
+impl Behaviour
+{
+    pub const ALL: [Behaviour; 8] =
+    [
+        Behaviour::Attack, Behaviour::Flee, Behaviour::SeekMedbay, Behaviour::Repair, Behaviour::Investigate, Behaviour::Search, Behaviour::Patrol,
+        Behaviour::Wander,
+    ];
+
+    /// Name used in the behaviours file
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Behaviour::Attack => "attack",
+            Behaviour::Flee => "flee",
+            Behaviour::SeekMedbay => "seek_medbay",
+            Behaviour::Repair => "repair",
+            Behaviour::Investigate => "investigate",
+            Behaviour::Search => "search",
+            Behaviour::Patrol => "patrol",
+            Behaviour::Wander => "wander",
+        }
+    }
+
+    /// What an NPC doing this is described as when examined
+    pub fn description(&self) -> &'static str
+    {
+        match self
+        {
+            Behaviour::Attack => "attacking",
+            Behaviour::Flee => "fleeing",
+            Behaviour::SeekMedbay => "looking for treatment",
+            Behaviour::Repair => "making repairs",
+            Behaviour::Investigate => "investigating a noise",
+            Behaviour::Search => "searching for someone",
+            Behaviour::Patrol => "on patrol",
+            Behaviour::Wander => "wandering",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Behaviour>
+    {
+        Behaviour::ALL.iter().copied().find(|behaviour| behaviour.name() == name.trim())
+    }
+
+    /// How much the situation calls for the behaviour, from 0 for not at all to 1, before the profile's weight
+    pub fn score(&self, situation: &Situation) -> f32
+    {
+        let wounds = 1.0 - situation.health;
+
+        match self
+        {
+            Behaviour::Attack if situation.hostile.is_some() => 0.5 + 0.5 * situation.health,
+            Behaviour::Flee if situation.hostile.is_some() => 0.4 + 0.6 * wounds,
+            Behaviour::SeekMedbay if situation.hostile.is_none() && situation.medbay.is_some() => wounds,
+            Behaviour::Repair if situation.damage.is_some() => 0.7,
+            Behaviour::Investigate if situation.heard.is_some() => 0.6,
+            Behaviour::Search if situation.hostile.is_none() && situation.last_seen.is_some() => 0.5,
+            Behaviour::Patrol if situation.patrol.is_some() => 0.3,
+            Behaviour::Wander => 0.1,
+            _ => 0.0,
+        }
+    }
+}
+
+/// What an NPC knows about its surroundings when it decides what to do
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Situation
+{
+    /// Share of hit points left, from 0 to 1
+    pub health: f32,
+    /// The nearest enemy in view and where it stands
+    pub hostile: Option<(Entity, Coordinate)>,
+    /// The nearest medical bay tile on the deck
+    pub medbay: Option<Coordinate>,
+    /// Wreckage in view that could be patched
+    pub damage: Option<Coordinate>,
+    /// Where the last noise it heard came from
+    pub heard: Option<Coordinate>,
+    /// Where it last saw an enemy now out of view
+    pub last_seen: Option<Coordinate>,
+    /// The next stop on its patrol route
+    pub patrol: Option<Coordinate>,
+}
+
+/// A set of behaviours with the weight each is given, as read from the behaviours file
+#[derive(Clone, Debug, PartialEq)]
+pub struct BehaviourProfile
+{
+    pub id: String,
+    pub considerations: Vec<(Behaviour, f32)>,
+}
+
+impl BehaviourProfile
+{
+    /// The behaviour that scores highest for the situation, the first considered winning a tie.
+    /// Gives nothing when no behaviour is called for at all.
+    pub fn choose(&self, situation: &Situation) -> Option<Behaviour>
+    {
+        let mut best: Option<(Behaviour, f32)> = None;
+
+        for (behaviour, weight) in self.considerations.iter()
+        {
+            let score = behaviour.score(situation) * weight;
+
+            if score > 0.0 && best.map(|(_best, best_score)| score > best_score).unwrap_or(true)
+            {
+                best = Some((*behaviour, score));
+            }
+        }
+
+        best.map(|(behaviour, _score)| behaviour)
+    }
+}
+
+/// Every behaviour profile NPCs can be given
+#[derive(Clone, Debug, Default)]
+pub struct BehaviourProfiles
+{
+    pub profiles: Vec<BehaviourProfile>,
+}
+
+impl BehaviourProfiles
+{
+    /// Reads the behaviours file, falling back to the built-in profiles when there isn't one
+    pub fn load_all(path: &str) -> Result<BehaviourProfiles, String>
+    {
+        let contents = match fs::read_to_string(path)
+        {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(BehaviourProfiles::built_in()),
+            Err(error) => return Err(format!("could not read behaviours '{}': {}", path, error)),
+        };
+
+        BehaviourProfiles::parse_all(&contents).map_err(|(line_number, line)| format!("{}:{}: bad behaviour line '{}'", path, line_number, line))
+    }
+
+    pub fn built_in() -> BehaviourProfiles
+    {
+        BehaviourProfiles::parse_all(BUILT_IN_BEHAVIOURS).expect("Built-in behaviours don't parse")
+    }
+
+    /// Parses behaviours file contents, giving back the line number and text of the first bad line
+    pub fn parse_all(contents: &str) -> Result<BehaviourProfiles, (usize, String)>
+    {
+        let mut profiles: Vec<BehaviourProfile> = Vec::new();
+
+        for (line_number, line) in contents.lines().enumerate()
+        {
+            let line_text = line.split('#').next().unwrap_or("").trim();
+            let bad_line = || (line_number + 1, line.to_string());
+
+            match line_text.split_whitespace().collect::<Vec<&str>>().as_slice()
+            {
+                [] => {}
+                ["profile", id] => profiles.push(BehaviourProfile { id: id.to_string(), considerations: Vec::new() }),
+                ["consider", name, weight] =>
+                {
+                    let behaviour = Behaviour::from_name(name).ok_or_else(bad_line)?;
+                    let weight: f32 = weight.parse().ok().filter(|weight: &f32| *weight >= 0.0).ok_or_else(bad_line)?;
+                    profiles.last_mut().ok_or_else(bad_line)?.considerations.push((behaviour, weight));
+                }
+                _ => return Err(bad_line()),
+            }
+        }
+
+        Ok(BehaviourProfiles { profiles })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&BehaviourProfile>
+    {
+        self.profiles.iter().find(|profile| profile.id == id)
+    }
+}
+
+//Brain
+/// Lets an NPC decide for itself what to do each turn, going by its behaviour profile
+#[derive(Component, Clone, Debug)]
+pub struct Brain
+{
+    /// Id of the profile in the behaviours file
+    pub profile: String,
+    /// Room centres walked between in turn while patrolling
+    pub patrol_route: Vec<Coordinate>,
+    pub patrol_stop: usize,
+    /// Where the last noise it heard and hasn't looked into yet came from
+    pub heard: Option<Coordinate>,
+    /// Where it last searched for an enemy and found nobody
+    pub searched: Option<Coordinate>,
+    /// What it did last turn
+    pub behaviour: Option<Behaviour>,
+    /// Where it is headed and the steps left to get there
+    pub goal: Option<Coordinate>,
+    pub path: Vec<Coordinate>,
+}
+
+impl Brain
+{
+    pub fn new(profile: &str) -> Brain
+    {
+        Brain { profile: profile.to_string(), patrol_route: Vec::new(), patrol_stop: 0, heard: None, searched: None, behaviour: None, goal: None, path: Vec::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn profile(considerations: &[(Behaviour, f32)]) -> BehaviourProfile
+    {
+        BehaviourProfile { id: "test".to_string(), considerations: considerations.to_vec() }
+    }
+
+    fn hostile() -> Option<(Entity, Coordinate)>
+    {
+        let mut world = World::new();
+        Some((world.create_entity().build(), Coordinate::new(1, 1, 0)))
+    }
+
+    #[test]
+    fn behaviours_score_nothing_without_a_reason()
+    {
+        let situation = Situation { health: 1.0, ..Default::default() };
+
+        for behaviour in [Behaviour::Attack, Behaviour::Flee, Behaviour::SeekMedbay, Behaviour::Repair, Behaviour::Investigate, Behaviour::Search, Behaviour::Patrol]
+        {
+            assert_eq!(behaviour.score(&situation), 0.0, "{:?}", behaviour);
+        }
+        assert!(Behaviour::Wander.score(&situation) > 0.0);
+    }
+
+    #[test]
+    fn wounds_make_fleeing_and_treatment_more_pressing()
+    {
+        let healthy = Situation { health: 1.0, hostile: hostile(), medbay: Some(Coordinate::new(2, 2, 0)), ..Default::default() };
+        let wounded = Situation { health: 0.2, ..healthy };
+
+        assert!(Behaviour::Flee.score(&wounded) > Behaviour::Flee.score(&healthy));
+        assert!(Behaviour::Attack.score(&wounded) < Behaviour::Attack.score(&healthy));
+        assert_eq!(Behaviour::SeekMedbay.score(&wounded), 0.0, "no treatment while a hostile is in view");
+        assert!(Behaviour::SeekMedbay.score(&Situation { hostile: None, ..wounded }) > 0.0);
+    }
+
+    #[test]
+    fn search_only_once_the_enemy_is_out_of_view()
+    {
+        let lost = Situation { health: 1.0, last_seen: Some(Coordinate::new(3, 3, 0)), ..Default::default() };
+
+        assert!(Behaviour::Search.score(&lost) > 0.0);
+        assert_eq!(Behaviour::Search.score(&Situation { hostile: hostile(), ..lost }), 0.0);
+    }
+
+    #[test]
+    fn choose_weighs_scores_by_the_profile()
+    {
+        let situation = Situation { health: 1.0, hostile: hostile(), ..Default::default() };
+
+        assert_eq!(profile(&[(Behaviour::Attack, 1.0), (Behaviour::Flee, 1.0)]).choose(&situation), Some(Behaviour::Attack));
+        assert_eq!(profile(&[(Behaviour::Attack, 0.1), (Behaviour::Flee, 1.0)]).choose(&situation), Some(Behaviour::Flee));
+    }
+
+    #[test]
+    fn choose_prefers_the_first_considered_on_a_tie()
+    {
+        let situation = Situation { health: 1.0, ..Default::default() };
+
+        assert_eq!(profile(&[(Behaviour::Wander, 1.0), (Behaviour::Wander, 1.0)]).choose(&situation), Some(Behaviour::Wander));
+        assert_eq!(profile(&[(Behaviour::Patrol, 1.0), (Behaviour::Wander, 3.0)]).choose(&Situation { patrol: Some(Coordinate::new(1, 1, 0)), ..situation }),
+                   Some(Behaviour::Patrol));
+    }
+
+    #[test]
+    fn choose_gives_nothing_when_nothing_is_called_for()
+    {
+        let situation = Situation { health: 1.0, ..Default::default() };
+
+        assert_eq!(profile(&[]).choose(&situation), None);
+        assert_eq!(profile(&[(Behaviour::Attack, 1.0), (Behaviour::Wander, 0.0)]).choose(&situation), None);
+    }
+
+    #[test]
+    fn built_in_behaviours_parse()
+    {
+        let profiles = BehaviourProfiles::built_in();
+
+        for id in ["crew", "security", "pirate", "drone", "scavenger"]
+        {
+            assert!(profiles.get(id).is_some(), "missing profile {}", id);
+        }
+    }
+
+    #[test]
+    fn parse_reads_profiles_and_skips_comments()
+    {
+        let profiles = BehaviourProfiles::parse_all("# comment\n\nprofile guard\nconsider attack 2 # hits hard\nconsider wander 0.5\nprofile idle\n").unwrap();
+
+        assert_eq!(profiles.profiles.len(), 2);
+        assert_eq!(profiles.get("guard").unwrap().considerations, vec![(Behaviour::Attack, 2.0), (Behaviour::Wander, 0.5)]);
+        assert!(profiles.get("idle").unwrap().considerations.is_empty());
+    }
+
+    #[test]
+    fn parse_reports_the_first_bad_line()
+    {
+        assert_eq!(BehaviourProfiles::parse_all("profile a\nconsider dance 1").unwrap_err(), (2, "consider dance 1".to_string()));
+        assert_eq!(BehaviourProfiles::parse_all("profile a\nconsider attack -1").unwrap_err().0, 2);
+        assert_eq!(BehaviourProfiles::parse_all("profile a\nconsider attack lots").unwrap_err().0, 2);
+        assert_eq!(BehaviourProfiles::parse_all("consider attack 1").unwrap_err().0, 1, "consider before any profile");
+        assert_eq!(BehaviourProfiles::parse_all("profile\n").unwrap_err().0, 1);
+    }
+}
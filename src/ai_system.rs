@@ -0,0 +1,545 @@
+use std::collections::HashSet;
+
+use rand::Rng;
+use rand::rngs::StdRng;
+use specs::prelude::*;
+
+use super::ai::{Behaviour, BehaviourProfiles, Brain, Situation};
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::factions::{is_hostile, FactionMember, Relations};
+use super::game_clock::GameClock;
+use super::game_log::GameLog;
+use super::map::Map;
+use super::map_memory::MapMemory;
+use super::map_tile::Door;
+use super::navigation::station_path;
+use super::noise::{hidden_in_shadow, Sneaking};
+use super::physics::{has_handhold, Velocity};
+use super::status_effects::StatusEffects;
+
+
+/// Hit points an NPC gets back for each turn it rests in a medical bay
+const MEDBAY_HEALING: i32 = 1;
+/// Turns an NPC keeps looking for an enemy it has lost sight of
+const SEARCH_TURNS: u64 = 50;
+
+/// How a step along a route went
+enum Step
+{
+    Moved(Coordinate),
+    /// Spent the turn opening a door or waiting for the way to clear
+    Waited,
+    NoRoute,
+}
+
+/// Has every NPC with a brain weigh up its situation and carry out whichever behaviour its profile scores highest
+pub struct AiSystem {}
+
+impl<'a> System<'a> for AiSystem
+{
+    type SystemData = (WriteExpect<'a, Map>,
+                       WriteExpect<'a, StdRng>,
+                       WriteExpect<'a, GameLog>,
+                       ReadExpect<'a, BehaviourProfiles>,
+                       ReadExpect<'a, Relations>,
+                       ReadExpect<'a, GameClock>,
+                       Entities<'a>,
+                       WriteStorage<'a, Coordinate>,
+                       WriteStorage<'a, Brain>,
+                       ReadStorage<'a, FactionMember>,
+                       ReadStorage<'a, Player>,
+                       WriteStorage<'a, Viewshed>,
+                       WriteStorage<'a, CombatStats>,
+                       WriteStorage<'a, SufferDamage>,
+                       ReadStorage<'a, MeleeAttack>,
+                       ReadStorage<'a, StatusEffects>,
+                       ReadStorage<'a, Sneaking>,
+                       ReadStorage<'a, Name>,
+                       ReadStorage<'a, MapMemory>,
+                       WriteStorage<'a, Velocity>);
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let (mut map, mut rng, mut log, profiles, relations, game_clock, entities, mut coordinates, mut brains, members, players, mut viewsheds,
+             mut combat_stats, mut suffering, melee_attacks, status_effects, sneaking, names, memories, mut velocities) = data;
+
+        let thinkers: Vec<Entity> = (&entities, &brains, &coordinates).join().map(|(entity, _brain, _coordinate)| entity).collect();
+        let player_view: HashSet<Coordinate> = (&players, &viewsheds).join().flat_map(|(_player, viewshed)| viewshed.visible_tiles.iter().copied()).collect();
+        let mut occupied: HashSet<Coordinate> = (&coordinates, &combat_stats).join().map(|(coordinate, _stats)| *coordinate).collect();
+        let medbays: Vec<Coordinate> = map.medbay_tiles().iter().copied().filter(|tile| map.get(*tile).map(|tile| tile.medical).unwrap_or(false)).collect();
+
+        for entity in thinkers
+        {
+            let position = match coordinates.get(entity)
+            {
+                Some(position) => *position,
+                None => continue,
+            };
+            let health = combat_stats.get(entity).map(|stats| stats.hp as f32 / stats.max_hp.max(1) as f32).unwrap_or(1.0);
+
+            // Anything drifting helplessly through zero-g can do nothing until it hits something
+            if health <= 0.0 || status_effects.get(entity).map(|effects| !effects.can_move()).unwrap_or(false) || velocities.get(entity).is_some()
+            {
+                continue;
+            }
+
+            let name = names.get(entity).map(|name| name.name.clone()).unwrap_or_else(|| "figure".to_string());
+            let visible_tiles: &[Coordinate] = viewsheds.get(entity).map(|viewshed| viewshed.visible_tiles.as_slice()).unwrap_or(&[]);
+            let brain = brains.get_mut(entity).expect("Thinker without a brain");
+
+            // Look around, missing anyone sneaking in the shadows unless they come close
+            let hostile = members.get(entity).and_then(|member|
+            {
+                (&entities, &coordinates, &members, &combat_stats).join()
+                    .filter(|(other, coordinate, other_member, stats)|
+                    {
+                        *other != entity && stats.hp > 0 && coordinate.z == position.z && visible_tiles.contains(coordinate) && !hidden_in_shadow(&map, sneaking.get(*other).is_some(), **coordinate, position)
+                        && is_hostile(&relations, (member.faction, false), (other_member.faction, players.get(*other).is_some()))
+                    })
+                    .min_by_key(|(_other, coordinate, _member, _stats)| distance(position, **coordinate))
+                    .map(|(other, coordinate, _member, _stats)| (other, *coordinate))
+            });
+            // With no enemy in view, it remembers where it last saw one, or where the others it shares a map with did
+            if hostile.is_some()
+            {
+                brain.searched = None;
+            }
+            let last_seen = match (hostile, members.get(entity), memories.get(entity))
+            {
+                (None, Some(member), Some(memory)) =>
+                {
+                    memory.entities.iter()
+                        .filter(|(other, remembered)|
+                        {
+                            remembered.coordinate.z == position.z && game_clock.turn.saturating_sub(remembered.turn) <= SEARCH_TURNS && brain.searched != Some(remembered.coordinate)
+                            && entities.is_alive(**other)
+                            && members.get(**other).map(|other_member| is_hostile(&relations, (member.faction, false), (other_member.faction, players.get(**other).is_some()))).unwrap_or(false)
+                        })
+                        .max_by_key(|(other, remembered)| (remembered.turn, other.id()))
+                        .map(|(_other, remembered)| remembered.coordinate)
+                }
+                _ => None,
+            };
+            let damage = visible_tiles.iter()
+                                      .filter(|tile| tile.z == position.z && !occupied.contains(tile) && map.get(**tile).map(|tile| tile.needs_repair()).unwrap_or(false))
+                                      .min_by_key(|tile| distance(position, **tile))
+                                      .copied();
+            let medbay = medbays.iter().filter(|tile| tile.z == position.z).min_by_key(|tile| distance(position, **tile)).copied();
+            let situation = Situation { health, hostile, medbay, damage, heard: brain.heard, last_seen, patrol: brain.patrol_route.get(brain.patrol_stop).copied() };
+
+            let behaviour = profiles.get(&brain.profile).and_then(|profile| profile.choose(&situation)).unwrap_or(Behaviour::Wander);
+
+            if brain.behaviour != Some(behaviour)
+            {
+                brain.behaviour = Some(behaviour);
+                brain.goal = None;
+                brain.path.clear();
+            }
+
+            // Then act on it
+            let step = match (behaviour, situation)
+            {
+                (Behaviour::Attack, Situation { hostile: Some((target, target_position)), .. }) if distance(position, target_position) <= 1 =>
+                {
+                    let damage = melee_attacks.get(entity).map(|attack| attack.damage).unwrap_or(1);
+                    SufferDamage::new_damage(&mut suffering, target, damage);
+
+                    if players.get(target).is_some()
+                    {
+                        log.add(format!("The {} hits you for {} damage.", name, damage));
+                    }
+                    else if player_view.contains(&position)
+                    {
+                        let target_name = names.get(target).map(|name| name.name.as_str()).unwrap_or("figure");
+                        log.add(format!("The {} attacks the {}.", name, target_name));
+                    }
+                    Step::Waited
+                }
+                (Behaviour::Attack, Situation { hostile: Some((_target, target_position)), .. }) => step_towards(&mut map, &occupied, brain, position, target_position),
+                (Behaviour::Flee, Situation { hostile: Some((_target, threat)), .. }) =>
+                {
+                    match flee_step(&map, &occupied, position, threat)
+                    {
+                        Some(next) => Step::Moved(next),
+                        None => Step::Waited,
+                    }
+                }
+                (Behaviour::SeekMedbay, Situation { medbay: Some(medbay), .. }) if map.get(position).map(|tile| tile.medical).unwrap_or(false) || medbay == position =>
+                {
+                    if let Some(stats) = combat_stats.get_mut(entity)
+                    {
+                        stats.hp = (stats.hp + MEDBAY_HEALING).min(stats.max_hp);
+                    }
+                    Step::Waited
+                }
+                (Behaviour::SeekMedbay, Situation { medbay: Some(medbay), .. }) => step_towards(&mut map, &occupied, brain, position, medbay),
+                (Behaviour::Repair, Situation { damage: Some(damaged), .. }) if distance(position, damaged) <= 1 =>
+                {
+                    if player_view.contains(&damaged)
+                    {
+                        let tile_name = map.get(damaged).map(|tile| tile.name).unwrap_or("wreckage");
+                        log.add(format!("The {} patches up the {}.", name, tile_name));
+                    }
+                    map.repair_tile(damaged);
+                    Step::Waited
+                }
+                (Behaviour::Repair, Situation { damage: Some(damaged), .. }) => step_towards(&mut map, &occupied, brain, position, damaged),
+                (Behaviour::Investigate, Situation { heard: Some(heard), .. }) =>
+                {
+                    let step = match distance(position, heard) <= 1
+                    {
+                        true => Step::NoRoute,
+                        false => step_towards(&mut map, &occupied, brain, position, heard),
+                    };
+                    // Once there, or once it is clear there is no getting there, the noise is forgotten
+                    if let Step::NoRoute = step
+                    {
+                        brain.heard = None;
+                    }
+                    step
+                }
+                (Behaviour::Search, Situation { last_seen: Some(spot), .. }) =>
+                {
+                    let step = match distance(position, spot) <= 1
+                    {
+                        true => Step::NoRoute,
+                        false => step_towards(&mut map, &occupied, brain, position, spot),
+                    };
+                    // Finding nobody there, or no way there, it gives up on the spot
+                    if let Step::NoRoute = step
+                    {
+                        brain.searched = Some(spot);
+                    }
+                    step
+                }
+                (Behaviour::Patrol, Situation { patrol: Some(stop), .. }) =>
+                {
+                    let step = match distance(position, stop) <= 1
+                    {
+                        true => Step::NoRoute,
+                        false => step_towards(&mut map, &occupied, brain, position, stop),
+                    };
+                    if let Step::NoRoute = step
+                    {
+                        brain.patrol_stop = (brain.patrol_stop + 1) % brain.patrol_route.len();
+                    }
+                    step
+                }
+                _ => Step::NoRoute,
+            };
+
+            // Anything that can't get where it wants to go mills about instead
+            let next = match step
+            {
+                Step::Moved(next) => Some(next),
+                Step::Waited => None,
+                Step::NoRoute => wander_step(&map, &occupied, &mut rng, position),
+            };
+
+            if let Some(next) = next
+            {
+                occupied.remove(&position);
+                occupied.insert(next);
+
+                if let Some(coordinate) = coordinates.get_mut(entity)
+                {
+                    *coordinate = next;
+                }
+                if let Some(viewshed) = viewsheds.get_mut(entity)
+                {
+                    viewshed.dirty = true;
+                }
+
+                // A step into zero-g with nothing in reach to hold on to carries on as a drift
+                if !map.has_gravity(next) && !has_handhold(&map, next)
+                {
+                    let velocity = Velocity { x: next.x as i32 - position.x as i32, y: next.y as i32 - position.y as i32 };
+                    velocities.insert(entity, velocity).expect("Unable to set velocity");
+                }
+            }
+        }
+    }
+}
+
+/// Tiles apart in a straight or diagonal line, ignoring decks
+fn distance(from: Coordinate, to: Coordinate) -> i32
+{
+    (from.x as i32 - to.x as i32).abs().max((from.y as i32 - to.y as i32).abs())
+}
+
+/// The tiles around a position that can be stepped onto this turn
+fn open_neighbours(map: &Map, occupied: &HashSet<Coordinate>, position: Coordinate) -> Vec<Coordinate>
+{
+    let mut neighbours: Vec<Coordinate> = Vec::new();
+
+    for (delta_x, delta_y) in [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)]
+    {
+        let (x, y) = (position.x as i32 + delta_x, position.y as i32 + delta_y);
+
+        if x < 0 || y < 0 || x >= map.map_size.x as i32 || y >= map.map_size.y as i32
+        {
+            continue;
+        }
+
+        let neighbour = Coordinate::new(x as usize, y as usize, position.z);
+
+        if map.get(neighbour).map(|tile| tile.passable).unwrap_or(false) && !occupied.contains(&neighbour)
+        {
+            neighbours.push(neighbour);
+        }
+    }
+
+    neighbours
+}
+
+/// Takes the next step of the route to the goal, planning a new route when the goal has moved or the old one
+/// was left. Doors on the way are opened, which takes the turn, and locked ones end the route. A goal found to be
+/// out of reach is not planned for again until a different one is set.
+fn step_towards(map: &mut Map, occupied: &HashSet<Coordinate>, brain: &mut Brain, position: Coordinate, goal: Coordinate) -> Step
+{
+    let off_route = brain.path.first().map(|next| distance(position, *next) != 1).unwrap_or(false);
+
+    if brain.goal != Some(goal) || off_route
+    {
+        brain.goal = Some(goal);
+        brain.path = station_path(map, position, goal).unwrap_or_default();
+    }
+
+    let next = match brain.path.first()
+    {
+        Some(next) => *next,
+        None => return Step::NoRoute,
+    };
+    let tile = match map.get(next)
+    {
+        Some(tile) => tile,
+        None => return Step::NoRoute,
+    };
+
+    match tile.door
+    {
+        Some(Door::Closed) =>
+        {
+            map.open_door(next);
+            return Step::Waited;
+        }
+        Some(Door::Locked) =>
+        {
+            brain.path.clear();
+            return Step::NoRoute;
+        }
+        _ => {}
+    }
+
+    if !tile.passable || occupied.contains(&next)
+    {
+        return Step::Waited;
+    }
+
+    brain.path.remove(0);
+    Step::Moved(next)
+}
+
+/// The open tile next to the position furthest from the threat, if any is further than standing still
+fn flee_step(map: &Map, occupied: &HashSet<Coordinate>, position: Coordinate, threat: Coordinate) -> Option<Coordinate>
+{
+    let squared_distance = |coordinate: Coordinate| (coordinate.x as i32 - threat.x as i32).pow(2) + (coordinate.y as i32 - threat.y as i32).pow(2);
+
+    open_neighbours(map, occupied, position).into_iter()
+                                             .filter(|neighbour| squared_distance(*neighbour) > squared_distance(position))
+                                             .max_by_key(|neighbour| squared_distance(*neighbour))
+}
+
+/// A random open tile next to the position, or nothing for staying put
+fn wander_step(map: &Map, occupied: &HashSet<Coordinate>, rng: &mut StdRng, position: Coordinate) -> Option<Coordinate>
+{
+    let neighbours = open_neighbours(map, occupied, position);
+
+    match neighbours.is_empty()
+    {
+        true => None,
+        false => Some(neighbours[rng.gen_range(0..neighbours.len())]),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use rand::SeedableRng;
+
+    use super::*;
+    use super::super::factions::Faction;
+    use super::super::map_tile::MapTile;
+
+    /// A bare room with bulkheads around a floor of the given size
+    fn room_map(width: usize, height: usize) -> Map
+    {
+        let mut map = Map::empty(Coordinate::new(width + 2, height + 2, 1));
+
+        for x in 0..width + 2
+        {
+            for y in 0..height + 2
+            {
+                let tile = match x == 0 || y == 0 || x == width + 1 || y == height + 1
+                {
+                    true => MapTile::wall(),
+                    false => MapTile::floor(),
+                };
+                map.set_tile(Coordinate::new(x, y, 0), tile);
+            }
+        }
+
+        map
+    }
+
+    #[test]
+    fn step_towards_follows_a_route_to_the_goal()
+    {
+        let mut map = room_map(5, 1);
+        let mut brain = Brain::new("test");
+        let mut position = Coordinate::new(1, 1, 0);
+        let goal = Coordinate::new(5, 1, 0);
+
+        for _ in 0..4
+        {
+            position = match step_towards(&mut map, &HashSet::new(), &mut brain, position, goal)
+            {
+                Step::Moved(next) => next,
+                _ => panic!("expected to move"),
+            };
+        }
+
+        assert_eq!(position, goal);
+        assert!(matches!(step_towards(&mut map, &HashSet::new(), &mut brain, position, goal), Step::NoRoute));
+    }
+
+    #[test]
+    fn step_towards_opens_doors_and_stops_at_locked_ones()
+    {
+        let mut map = room_map(3, 1);
+        let door = Coordinate::new(2, 1, 0);
+        let mut brain = Brain::new("test");
+
+        map.set_tile(door, MapTile::closed_door());
+        assert!(matches!(step_towards(&mut map, &HashSet::new(), &mut brain, Coordinate::new(1, 1, 0), Coordinate::new(3, 1, 0)), Step::Waited));
+        assert_eq!(map.get(door).unwrap().door, Some(Door::Open));
+
+        map.set_tile(door, MapTile::locked_door());
+        let mut brain = Brain::new("test");
+        assert!(matches!(step_towards(&mut map, &HashSet::new(), &mut brain, Coordinate::new(1, 1, 0), Coordinate::new(3, 1, 0)), Step::NoRoute));
+    }
+
+    #[test]
+    fn step_towards_waits_for_the_way_to_clear()
+    {
+        let mut map = room_map(3, 1);
+        let mut brain = Brain::new("test");
+        let occupied: HashSet<Coordinate> = [Coordinate::new(2, 1, 0)].into_iter().collect();
+
+        assert!(matches!(step_towards(&mut map, &occupied, &mut brain, Coordinate::new(1, 1, 0), Coordinate::new(3, 1, 0)), Step::Waited));
+    }
+
+    #[test]
+    fn flee_step_moves_away_from_the_threat()
+    {
+        let map = room_map(5, 5);
+        let threat = Coordinate::new(1, 1, 0);
+        let position = Coordinate::new(3, 3, 0);
+        let next = flee_step(&map, &HashSet::new(), position, threat).expect("room to flee into");
+
+        assert_eq!(next, Coordinate::new(4, 4, 0));
+    }
+
+    #[test]
+    fn flee_step_stays_put_when_cornered()
+    {
+        let map = room_map(5, 5);
+
+        assert_eq!(flee_step(&map, &HashSet::new(), Coordinate::new(5, 5, 0), Coordinate::new(4, 4, 0)), None);
+    }
+
+    #[test]
+    fn search_heads_for_where_an_enemy_was_last_seen()
+    {
+        let mut world = World::new();
+        let mut ai_system = AiSystem {};
+        let start = Coordinate::new(1, 1, 0);
+        let mut clock = GameClock::default();
+
+        System::setup(&mut ai_system, &mut world);
+        world.register::<Player>();
+        world.insert(room_map(6, 3));
+        world.insert(StdRng::seed_from_u64(1));
+        world.insert(GameLog::default());
+        world.insert(BehaviourProfiles::parse_all("profile hunter\nconsider search 1").unwrap());
+        world.insert(Relations::default());
+
+        let player = world.create_entity()
+                          .with(Player{})
+                          .with(Coordinate::new(6, 3, 0))
+                          .with(FactionMember { faction: Faction::Crew })
+                          .with(CombatStats { max_hp: 10, hp: 10 })
+                          .build();
+        let mut memory = MapMemory::new();
+        memory.remember_entity(player, Coordinate::new(6, 1, 0), 0);
+        clock.turn = 5;
+        world.insert(clock);
+
+        let hunter = world.create_entity()
+                          .with(start)
+                          .with(Brain::new("hunter"))
+                          .with(FactionMember { faction: Faction::Pirates })
+                          .with(CombatStats { max_hp: 10, hp: 10 })
+                          .with(Viewshed { visible_tiles: vec![start], range: 8, dirty: false })
+                          .with(memory)
+                          .build();
+
+        ai_system.run_now(&world);
+
+        assert_eq!(world.read_storage::<Brain>().get(hunter).unwrap().behaviour, Some(Behaviour::Search));
+        assert_eq!(world.read_storage::<Coordinate>().get(hunter).copied(), Some(Coordinate::new(2, 1, 0)));
+    }
+
+    /// Has a lone NPC with a repair-only profile stand next to the wrecked tile for a turn
+    fn repair_next_to(map: Map, position: Coordinate, damaged: Coordinate) -> Map
+    {
+        let mut world = World::new();
+        let mut ai_system = AiSystem {};
+
+        System::setup(&mut ai_system, &mut world);
+        world.insert(map);
+        world.insert(StdRng::seed_from_u64(1));
+        world.insert(GameLog::default());
+        world.insert(BehaviourProfiles::parse_all("profile fixer\nconsider repair 1").unwrap());
+        world.insert(Relations::default());
+        world.insert(GameClock::default());
+        world.create_entity()
+             .with(position)
+             .with(Brain::new("fixer"))
+             .with(CombatStats { max_hp: 10, hp: 10 })
+             .with(Viewshed { visible_tiles: vec![position, damaged], range: 8, dirty: false })
+             .build();
+
+        ai_system.run_now(&world);
+        world.remove::<Map>().unwrap()
+    }
+
+    #[test]
+    fn repair_restores_what_was_wrecked()
+    {
+        let mut map = room_map(3, 3);
+        let door = Coordinate::new(2, 0, 0);
+        let floor = Coordinate::new(3, 3, 0);
+
+        map.set_tile(door, MapTile::locked_door());
+        map.set_tile(door, MapTile::rubble());
+        map.set_tile(floor, MapTile::rubble());
+
+        let map = repair_next_to(map, Coordinate::new(2, 1, 0), door);
+        assert_eq!(map.get(door).unwrap().door, Some(Door::Locked));
+
+        let map = repair_next_to(map, Coordinate::new(2, 2, 0), floor);
+        assert_eq!(map.get(floor).unwrap().name, MapTile::floor().name);
+    }
+}
@@ -0,0 +1,89 @@
+/// Partial pressure of oxygen in standard station air, in kilopascals
+pub const STANDARD_OXYGEN: f32 = 21.2;
+/// Partial pressure of nitrogen in standard station air, in kilopascals
+pub const STANDARD_NITROGEN: f32 = 80.1;
+/// Partial pressure of carbon dioxide in standard station air, in kilopascals
+pub const STANDARD_CARBON_DIOXIDE: f32 = 0.04;
+/// Temperature the station's air is kept at, in degrees Celsius
+pub const STANDARD_TEMPERATURE: f32 = 21.0;
+/// Temperature of open space, which anything open to it cools towards
+pub const SPACE_TEMPERATURE: f32 = -270.0;
+/// Carbon dioxide partial pressure above which the air is too foul to breathe, in kilopascals
+pub const HYPERCAPNIA_CARBON_DIOXIDE: f32 = 4.0;
+
+/// Smoke density at which a tile can no longer be seen through
+pub const OPAQUE_SMOKE: f32 = 2.0;
+
+/// The gas filling one tile, as partial pressures in kilopascals, the smoke hanging in it and how warm it is in
+/// degrees Celsius
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Atmosphere
+{
+    pub oxygen: f32,
+    pub nitrogen: f32,
+    pub carbon_dioxide: f32,
+    pub smoke: f32,
+    pub temperature: f32,
+}
+
+impl Atmosphere
+{
+    /// Breathable air at sea level pressure, what every sealed room starts with
+    pub fn standard() -> Atmosphere
+    {
+        Atmosphere { oxygen: STANDARD_OXYGEN, nitrogen: STANDARD_NITROGEN, carbon_dioxide: STANDARD_CARBON_DIOXIDE, smoke: 0.0,
+                     temperature: STANDARD_TEMPERATURE }
+    }
+
+    pub fn vacuum() -> Atmosphere
+    {
+        Atmosphere { temperature: SPACE_TEMPERATURE, ..Atmosphere::default() }
+    }
+
+    pub fn pressure(&self) -> f32
+    {
+        self.oxygen + self.nitrogen + self.carbon_dioxide
+    }
+
+    /// Share of the gas that is oxygen, from 0 to 1
+    pub fn oxygen_fraction(&self) -> f32
+    {
+        match self.pressure() > 0.0
+        {
+            true => self.oxygen / self.pressure(),
+            false => 0.0,
+        }
+    }
+
+    /// Whether there is oxygen enough to breathe and little enough carbon dioxide to keep it down
+    pub fn is_breathable(&self, minimum_oxygen: f32) -> bool
+    {
+        self.oxygen >= minimum_oxygen && self.carbon_dioxide < HYPERCAPNIA_CARBON_DIOXIDE
+    }
+
+    pub fn is_smoky(&self) -> bool
+    {
+        self.smoke >= OPAQUE_SMOKE
+    }
+
+    /// What a handheld gas analyser would show for the tile
+    pub fn reading(&self) -> String
+    {
+        let reading = match self.pressure() < 0.1
+        {
+            true => "Vacuum".to_string(),
+            false => format!("Pressure {:.1} kPa, oxygen {:.0}%, {:.0}°C", self.pressure(), self.oxygen_fraction() * 100.0, self.temperature),
+        };
+        let reading = match self.carbon_dioxide >= 1.0
+        {
+            true => format!("{}, CO2 {:.1} kPa", reading, self.carbon_dioxide),
+            false => reading,
+        };
+
+        match self.smoke >= 0.1
+        {
+            true => format!("{}, smoke {:.1}", reading, self.smoke),
+            false => reading,
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use specs::prelude::*;
+
+use super::map::Map;
+
+
+/// Moves a turn's worth of gas around the station
+pub struct AtmosphereSystem {}
+
+impl<'a> System<'a> for AtmosphereSystem
+{
+    type SystemData = WriteExpect<'a, Map>;
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let mut map = data;
+        map.diffuse_atmosphere();
+    }
+}
@@ -1,156 +1,145 @@
 extern crate lib;
-use lib::{entity_components::*, visibility_system::VisibilitySystem, coordinate::Coordinate};
+use lib::{coordinate::Coordinate, game_state::State, headless::*, launch_options::*};
+use lib::input_recording::{InputRecorder, Recording};
+use lib::key_bindings::{KeyBindings, DEFAULT_KEY_BINDINGS_PATH};
+use lib::character::{Background, Character, DEFAULT_BACKGROUNDS_PATH};
+use lib::ai::{BehaviourProfiles, DEFAULT_BEHAVIOURS_PATH};
+#[cfg(not(feature = "terminal"))]
+use lib::gui::HUD_HEIGHT;
 
-use lib::map::Map;
+use rand::Rng;
 
-use rltk::{GameState, Rltk, RGB, VirtualKeyCode};
-use specs::prelude::*;
-use std::cmp::{max, min};
 
-
-struct State 
+fn main() -> rltk::BError 
 {
-    entity_system: World,
-    map_size: Coordinate, 
-}
+    use rltk::RltkBuilder;
 
-impl State
-{
-    fn run_systems(&mut self)
-    {
-        let mut visibility_system = VisibilitySystem{};
+    const MAP_WIDTH: usize = 150;
+    const MAP_HEIGHT: usize = 100;
+    const MAP_DEPTH: usize = 2;
 
-        visibility_system.run_now(&self.entity_system);
-        self.entity_system.maintain();
-    }
-}
+    // A terminal rarely fits the whole deck, the camera follows the player instead
+    #[cfg(feature = "terminal")]
+    let default_options = LaunchOptions::new(80, 45);
+    #[cfg(not(feature = "terminal"))]
+    let default_options = LaunchOptions::new(MAP_WIDTH, MAP_HEIGHT + HUD_HEIGHT);
 
-impl GameState for State 
-{
-    fn tick(&mut self, context : &mut Rltk) 
+    let options = match default_options.from_args(std::env::args().skip(1))
     {
-        context.cls();
-        self.run_systems();
+        Ok(options) => options,
+        Err(message) =>
+        {
+            eprintln!("{}\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    };
 
-        player_input(self, context);
+    let map_size = Coordinate{ x: MAP_WIDTH, y: MAP_HEIGHT, z: MAP_DEPTH };
 
-        let coordinates = self.entity_system.write_storage::<Coordinate>();
-        let renderables = self.entity_system.read_storage::<Renderable>(); 
-    
-        let players = self.entity_system.write_storage::<Player>();
-        let mut camera_z: usize = 0;
+    let backgrounds_path = options.backgrounds_path.clone().unwrap_or_else(|| DEFAULT_BACKGROUNDS_PATH.to_string());
+    let backgrounds = match Background::load_all(&backgrounds_path)
+    {
+        Ok(backgrounds) => backgrounds,
+        Err(message) =>
+        {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
 
-        for (_player, coordinate) in (&players, &coordinates).join() 
+    let behaviours = match BehaviourProfiles::load_all(DEFAULT_BEHAVIOURS_PATH)
+    {
+        Ok(behaviours) => behaviours,
+        Err(message) =>
         {
-            camera_z = coordinate.z;
+            eprintln!("{}", message);
+            std::process::exit(2);
         }
-        drop(players);
+    };
 
-        let map = self.entity_system.fetch::<Map>();
-        map.draw(context, camera_z);
+    if let Some(replay_path) = &options.replay_path
+    {
+        let recording = match Recording::load(replay_path)
+        {
+            Ok(recording) => recording,
+            Err(message) =>
+            {
+                eprintln!("{}", message);
+                std::process::exit(2);
+            }
+        };
 
-        for (coordinate, renderable) in (&coordinates, &renderables).join()
+        let mut game_state = State::new(map_size, options.screen_width, options.screen_height, recording.seed);
+        game_state.entity_system.insert(behaviours);
+        let (name, background) = match &recording.character
         {
-            context.set(coordinate.x, coordinate.y, renderable.foreground_color, renderable.background_color, renderable.glyph);
+            Some((background, name)) => (Some(name.as_str()), Some(background.as_str())),
+            None => (None, None),
+        };
+        match Character::from_choices(&backgrounds, name, background)
+        {
+            Ok(character) => game_state.create_character(character),
+            Err(message) =>
+            {
+                eprintln!("{}", message);
+                std::process::exit(2);
+            }
         }
-    }
-}
-
-fn main() -> rltk::BError 
-{
-    use rltk::RltkBuilder;
 
-    const MAP_WIDTH: usize = 150;
-    const MAP_HEIGHT: usize = 100;
-    const MAP_DEPTH: usize = 2;
+        let matched = run_replay(&mut game_state, &recording, &mut std::io::stdout())?;
+        std::process::exit(if matched { 0 } else { 1 });
+    }
 
-    let context = RltkBuilder::simple(MAP_WIDTH, MAP_HEIGHT)
-    .unwrap()
-    .with_title("Roguelike Tutorial")
-    .with_font("vga8x16.png", 8, 16)
-    .with_sparse_console(MAP_WIDTH, MAP_HEIGHT, "vga8x16.png")
-    .with_vsync(false)
-    .build()?;
+    let seed = options.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let mut game_state = State::new(map_size, options.screen_width, options.screen_height, seed);
+    game_state.entity_system.insert(behaviours);
 
-    let mut game_state = State
-    { 
-        entity_system: World::new(),
-        map_size: Coordinate{ x: MAP_WIDTH, y: MAP_HEIGHT, z: MAP_DEPTH },
+    let key_bindings_path = options.key_bindings_path.clone().unwrap_or_else(|| DEFAULT_KEY_BINDINGS_PATH.to_string());
+    game_state.key_bindings = match KeyBindings::load(&key_bindings_path)
+    {
+        Ok(key_bindings) => key_bindings,
+        Err(message) =>
+        {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
     };
-    
-    register_components(&mut game_state);
-
-    let (map, rooms) = Map::rooms_and_corridors_map(20,5, 10, game_state.map_size);
-    let player_start_coordinate = rooms[0].center();
-
-    game_state.entity_system.insert(map);
-    //Test player
-    game_state.entity_system.create_entity()
-                            .with(Player{})
-                            .with(player_start_coordinate)
-                            .with(Renderable { glyph: rltk::to_cp437('@'), 
-                                                foreground_color: RGB::named(rltk::YELLOW), 
-                                                background_color: RGB::named(rltk::BLACK)})
-                            .with(Viewshed { visible_tiles: Vec::new(), range: 18, dirty: true})
-                            .build();
-
-                        
-    rltk::main_loop(context, game_state)
-}
+    game_state.key_bindings_path = key_bindings_path;
 
-fn try_move_player(delta_x: i32, delta_y: i32, game_state: &mut State)
-{
-    
-    let mut coordinates = game_state.entity_system.write_storage::<Coordinate>();
-    let mut players = game_state.entity_system.write_storage::<Player>();
-    let mut viewsheds = game_state.entity_system.write_storage::<Viewshed>();
-    let map = game_state.entity_system.fetch::<Map>();
-    
-    for (_player, coordinate, viewshed) in (&mut players, &mut coordinates, &mut viewsheds).join() 
+    if let Some(record_path) = &options.record_path
     {
-        let target_coordinate = Coordinate { x: (coordinate.x as i32 + delta_x) as usize, y: (coordinate.y as i32 + delta_y) as usize, z : coordinate.z};
-        match map.get(target_coordinate)
+        game_state.recorder = Some(InputRecorder::create(record_path, seed)?);
+    }
+
+    // Without a screen to make one on, or when one was asked for on the command line, the character is made straight away
+    game_state.backgrounds = backgrounds;
+    if options.headless || options.character_name.is_some() || options.background.is_some()
+    {
+        match Character::from_choices(&game_state.backgrounds, options.character_name.as_deref(), options.background.as_deref())
         {
-            Some(tile) => if tile.passable
-            {
-                coordinate.x = min(game_state.map_size.x - 1 , max(0, target_coordinate.x));
-                coordinate.y = min(game_state.map_size.y - 1, max(0, target_coordinate.y));
-                viewshed.dirty = true;
-                
-            }
-            None => 
+            Ok(character) => game_state.create_character(character),
+            Err(message) =>
             {
-                coordinate.x = min(game_state.map_size.x - 1 , max(0, target_coordinate.x));
-                coordinate.y = min(game_state.map_size.y - 1, max(0, target_coordinate.y));
-                viewshed.dirty = true;
+                eprintln!("{}", message);
+                std::process::exit(2);
             }
         }
     }
-}
 
-fn player_input(game_state: &mut State, context: &mut Rltk) 
-{
-    // Player movement
-    match context.key {
-        None => {} // Nothing happened
-        Some(key) => match key {
-            VirtualKeyCode::Left => try_move_player(-1, 0, game_state),
-            VirtualKeyCode::Right => try_move_player(1, 0, game_state),
-            VirtualKeyCode::Up => try_move_player(0, -1, game_state),
-            VirtualKeyCode::Down => try_move_player(0, 1, game_state),
-            VirtualKeyCode::Home => try_move_player(-1, -1, game_state),
-            VirtualKeyCode::PageUp => try_move_player(1, -1, game_state),
-            VirtualKeyCode::PageDown => try_move_player(1, 1, game_state),
-            VirtualKeyCode::End => try_move_player(-1, 1, game_state),
-            _ => {}
-        },
+    if options.headless
+    {
+        let stdin = std::io::stdin();
+        run_headless(&mut game_state, stdin.lock(), &mut std::io::stdout())?;
+        return Ok(());
     }
-}
 
-fn register_components(game_state: &mut State)
-{
-    game_state.entity_system.register::<Coordinate>();
-    game_state.entity_system.register::<Renderable>();
-    game_state.entity_system.register::<Player>();
-    game_state.entity_system.register::<Viewshed>();
+    let context = RltkBuilder::simple(options.screen_width, options.screen_height)
+    .unwrap()
+    .with_title("Roguelike Tutorial")
+    .with_font("vga8x16.png", 8, 16)
+    .with_sparse_console(options.screen_width, options.screen_height, "vga8x16.png")
+    .with_vsync(false)
+    .build()?;
+
+    rltk::main_loop(context, game_state)
 }
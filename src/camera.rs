@@ -0,0 +1,53 @@
+use std::cmp::min;
+
+use super::coordinate::Coordinate;
+
+
+/// The part of a deck shown on screen, kept centred on its target wherever the map edges allow
+#[derive(Clone, Copy, Debug)]
+pub struct Camera
+{
+    /// Map coordinate drawn in the top left corner of the screen
+    pub origin: Coordinate,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Camera
+{
+    pub fn centered_on(target: Coordinate, width: usize, height: usize, map_size: Coordinate) -> Camera
+    {
+        let x = target.x.saturating_sub(width / 2).min(map_size.x.saturating_sub(width));
+        let y = target.y.saturating_sub(height / 2).min(map_size.y.saturating_sub(height));
+
+        Camera { origin: Coordinate::new(x, y, target.z), width: min(width, map_size.x), height: min(height, map_size.y) }
+    }
+
+    /// Screen position of a map coordinate on the camera's deck, if it is in view
+    pub fn to_screen(&self, coordinate: Coordinate) -> Option<(usize, usize)>
+    {
+        if coordinate.z == self.origin.z
+            && coordinate.x >= self.origin.x && coordinate.x < self.origin.x + self.width
+            && coordinate.y >= self.origin.y && coordinate.y < self.origin.y + self.height
+        {
+            Some((coordinate.x - self.origin.x, coordinate.y - self.origin.y))
+        }
+        else
+        {
+            None
+        }
+    }
+
+    /// Map coordinate under a screen position, if the position is inside the camera
+    pub fn to_map(&self, screen_x: i32, screen_y: i32) -> Option<Coordinate>
+    {
+        if screen_x >= 0 && screen_y >= 0 && (screen_x as usize) < self.width && (screen_y as usize) < self.height
+        {
+            Some(Coordinate::new(self.origin.x + screen_x as usize, self.origin.y + screen_y as usize, self.origin.z))
+        }
+        else
+        {
+            None
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs;
+
+use specs_derive::Component;
+use specs::prelude::*;
+
+use super::entity_components::Player;
+use super::factions::Faction;
+use super::skills::{Skill, Skills, MAX_SKILL_LEVEL};
+use super::spawner::KIT_ITEMS;
+
+
+pub const DEFAULT_BACKGROUNDS_PATH: &str = "resources/backgrounds.cfg";
+/// The backgrounds shipped with the game, used when the backgrounds file can't be found
+const BUILT_IN_BACKGROUNDS: &str = include_str!("../resources/backgrounds.cfg");
+
+/// Name given to a character nobody named
+pub const DEFAULT_NAME: &str = "Player";
+/// Longest name the character creation screen accepts
+pub const MAX_NAME_LENGTH: usize = 20;
+
+//Attributes
+/// What a character is born with, each from 1 to 10 with 5 as average
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Attributes
+{
+    pub strength: i32,
+    pub agility: i32,
+    pub endurance: i32,
+    pub intellect: i32,
+}
+
+impl Default for Attributes
+{
+    fn default() -> Attributes
+    {
+        Attributes { strength: 5, agility: 5, endurance: 5, intellect: 5 }
+    }
+}
+
+impl Attributes
+{
+    pub const NAMES: [&'static str; 4] = ["strength", "agility", "endurance", "intellect"];
+
+    pub fn get(&self, name: &str) -> Option<i32>
+    {
+        match name
+        {
+            "strength" => Some(self.strength),
+            "agility" => Some(self.agility),
+            "endurance" => Some(self.endurance),
+            "intellect" => Some(self.intellect),
+            _ => None,
+        }
+    }
+
+    fn set(&mut self, name: &str, value: i32) -> Option<()>
+    {
+        match name
+        {
+            "strength" => self.strength = value,
+            "agility" => self.agility = value,
+            "endurance" => self.endurance = value,
+            "intellect" => self.intellect = value,
+            _ => return None,
+        }
+        Some(())
+    }
+
+    pub fn max_hp(&self) -> i32
+    {
+        10 + self.endurance * 2
+    }
+
+    /// Percent added to the chance of hitting with a shot, negative for clumsy shooters
+    pub fn hit_bonus(&self) -> i32
+    {
+        (self.agility - 5) * 3
+    }
+
+    /// Damage dealt hitting someone with whatever is to hand
+    pub fn melee_damage(&self) -> i32
+    {
+        (self.strength / 2).max(1)
+    }
+
+    /// Tiles added to how far things can be thrown
+    pub fn throw_bonus(&self) -> i32
+    {
+        (self.strength - 5) / 2
+    }
+}
+
+//CharacterBackground
+/// Which background a character was made with, for the character sheet
+#[derive(Component, Clone, Debug)]
+pub struct CharacterBackground
+{
+    pub title: String,
+}
+
+/// A background a new character can be given, as read from the backgrounds file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Background
+{
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub attributes: Attributes,
+    /// Skill levels the background starts with, untrained in everything else
+    pub skills: Skills,
+    /// Items carried from the start, by the ids the spawner knows them by
+    pub kit: Vec<String>,
+    /// Faction the character belongs to, setting how every other faction regards them at the start
+    pub faction: Faction,
+}
+
+impl Background
+{
+    /// Reads the backgrounds file, falling back to the built-in backgrounds when there isn't one
+    pub fn load_all(path: &str) -> Result<Vec<Background>, String>
+    {
+        let contents = match fs::read_to_string(path)
+        {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Background::built_in()),
+            Err(error) => return Err(format!("could not read backgrounds '{}': {}", path, error)),
+        };
+
+        Background::parse_all(&contents).map_err(|(line_number, line)| format!("{}:{}: bad background line '{}'", path, line_number, line))
+    }
+
+    pub fn built_in() -> Vec<Background>
+    {
+        Background::parse_all(BUILT_IN_BACKGROUNDS).expect("Built-in backgrounds don't parse")
+    }
+
+    /// Parses backgrounds file contents, giving back the line number and text of the first bad line
+    pub fn parse_all(contents: &str) -> Result<Vec<Background>, (usize, String)>
+    {
+        let mut backgrounds: Vec<Background> = Vec::new();
+
+        for (line_number, line) in contents.lines().enumerate()
+        {
+            let line_text = line.split('#').next().unwrap_or("").trim();
+            let (keyword, rest) = line_text.split_once(' ').map(|(keyword, rest)| (keyword, rest.trim())).unwrap_or((line_text, ""));
+            let bad_line = || (line_number + 1, line.to_string());
+
+            if keyword.is_empty()
+            {
+                continue;
+            }
+
+            if keyword == "background"
+            {
+                let (id, title) = rest.split_once(' ').ok_or_else(bad_line)?;
+                backgrounds.push(Background
+                {
+                    id: id.to_string(),
+                    title: title.trim().to_string(),
+                    description: String::new(),
+                    attributes: Attributes::default(),
+                    skills: Skills::default(),
+                    kit: Vec::new(),
+                    faction: Faction::Crew,
+                });
+                continue;
+            }
+
+            let background = backgrounds.last_mut().ok_or_else(bad_line)?;
+
+            match (keyword, rest.split_whitespace().collect::<Vec<&str>>().as_slice())
+            {
+                ("description", _) => background.description = rest.to_string(),
+                ("attribute", [name, value]) =>
+                {
+                    let value: i32 = value.parse().ok().filter(|value| (1..=10).contains(value)).ok_or_else(bad_line)?;
+                    background.attributes.set(name, value).ok_or_else(bad_line)?;
+                }
+                ("skill", [name, level]) =>
+                {
+                    let level: i32 = level.parse().ok().filter(|level| (0..=MAX_SKILL_LEVEL).contains(level)).ok_or_else(bad_line)?;
+                    background.skills.set_level(Skill::from_name(name).ok_or_else(bad_line)?, level);
+                }
+                ("faction", [name]) => background.faction = Faction::from_name(name).ok_or_else(bad_line)?,
+                ("kit", [item]) if KIT_ITEMS.contains(item) => background.kit.push(item.to_string()),
+                _ => return Err(bad_line()),
+            }
+        }
+
+        match backgrounds.is_empty()
+        {
+            true => Err((0, "no backgrounds".to_string())),
+            false => Ok(backgrounds),
+        }
+    }
+}
+
+/// Everything picked on the character creation screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct Character
+{
+    pub name: String,
+    pub background: Background,
+}
+
+impl Character
+{
+    /// A character made without the creation screen, from a background id and a name, each falling back to a default
+    pub fn from_choices(backgrounds: &[Background], name: Option<&str>, background_id: Option<&str>) -> Result<Character, String>
+    {
+        let background = match background_id
+        {
+            Some(id) => backgrounds.iter().find(|background| background.id == id).ok_or(format!("unknown background '{}'", id))?,
+            None => backgrounds.first().ok_or("there are no backgrounds to pick from")?,
+        };
+        let name = name.map(|name| name.trim()).filter(|name| !name.is_empty()).unwrap_or(DEFAULT_NAME);
+
+        Ok(Character { name: name.chars().take(MAX_NAME_LENGTH).collect(), background: background.clone() })
+    }
+}
+
+/// The attributes of the player, or average ones if the player has none
+pub fn player_attributes(entity_system: &World) -> Attributes
+{
+    let players = entity_system.read_storage::<Player>();
+    let attributes = entity_system.read_storage::<Attributes>();
+
+    (&players, &attributes).join().map(|(_player, attributes)| *attributes).next().unwrap_or_default()
+}
@@ -3,7 +3,8 @@ use specs::prelude::*;
 
 
 //Position
-#[derive(Component, Clone, Copy, Debug)]
+/// Decks are stacked downwards, so deck z + 1 is directly below deck z
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Coordinate
 {
     pub x: usize,
@@ -17,4 +18,4 @@ impl Coordinate
     {
         Coordinate { x, y, z }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,100 @@
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::explosions::explode;
+use super::game_log::GameLog;
+use super::noise::{make_noise, BLAST_NOISE};
+use super::status_effects::StatusEffects;
+
+
+pub struct DamageSystem {}
+
+impl<'a> System<'a> for DamageSystem
+{
+    type SystemData = (WriteStorage<'a, CombatStats>,
+                       WriteStorage<'a, SufferDamage>,
+                       ReadStorage<'a, StatusEffects>);
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let (mut combat_stats, mut suffering, status_effects) = data;
+
+        for (stats, damage, effects) in (&mut combat_stats, &suffering, status_effects.maybe()).join()
+        {
+            // Status effects can make each hit worse than it would be
+            stats.hp -= damage.amounts.iter().map(|amount| effects.map(|effects| effects.damage_taken(*amount)).unwrap_or(*amount)).sum::<i32>();
+        }
+
+        suffering.clear();
+    }
+}
+
+/// Removes everything whose hit points have run out, except the player, who is left for the game over screen.
+/// Anything explosive goes off as it is destroyed, and whatever its blast destroys goes off in turn.
+/// Only deaths the player sees are logged. Returns whether the player is dead.
+pub fn delete_the_dead(entity_system: &mut World) -> bool
+{
+    let mut dead: Vec<Entity> = Vec::new();
+    let mut blasts: Vec<(Coordinate, i32, i32)> = Vec::new();
+    let mut player_dead = false;
+
+    {
+        let entities = entity_system.entities();
+        let combat_stats = entity_system.read_storage::<CombatStats>();
+        let players = entity_system.read_storage::<Player>();
+        let names = entity_system.read_storage::<Name>();
+        let coordinates = entity_system.read_storage::<Coordinate>();
+        let viewsheds = entity_system.read_storage::<Viewshed>();
+        let explosives = entity_system.read_storage::<Explosive>();
+        let mut log = entity_system.fetch_mut::<GameLog>();
+        let in_view = |entity: Entity| coordinates.get(entity).map(|coordinate| (&players, &viewsheds).join().any(|(_player, viewshed)| viewshed.visible_tiles.contains(coordinate))).unwrap_or(false);
+
+        for (entity, stats) in (&entities, &combat_stats).join()
+        {
+            if stats.hp > 0
+            {
+                continue;
+            }
+
+            match players.get(entity)
+            {
+                Some(_player) => player_dead = true,
+                None =>
+                {
+                    let blast = explosives.get(entity).zip(coordinates.get(entity)).map(|(explosive, coordinate)| (*coordinate, explosive.radius, explosive.damage));
+
+                    if let Some(name) = names.get(entity).filter(|_name| in_view(entity))
+                    {
+                        match blast
+                        {
+                            Some(_blast) => log.add(format!("The {} explodes!", name.name)),
+                            None => log.add(format!("The {} is destroyed.", name.name)),
+                        }
+                    }
+                    blasts.extend(blast);
+                    dead.push(entity);
+                }
+            }
+        }
+    }
+
+    for victim in dead
+    {
+        entity_system.delete_entity(victim).expect("Unable to delete");
+    }
+
+    if blasts.is_empty()
+    {
+        return player_dead;
+    }
+
+    for (centre, radius, damage) in blasts
+    {
+        explode(entity_system, centre, radius, damage);
+        make_noise(entity_system, centre, BLAST_NOISE);
+    }
+
+    DamageSystem{}.run_now(entity_system);
+    delete_the_dead(entity_system) || player_dead
+}
@@ -1,8 +1,10 @@
 
-use rltk::{RGB, Point};
+use rltk::RGB;
 use specs_derive::Component;
 use specs::prelude::*;
 
+use super::coordinate::Coordinate;
+
 
 //Renderable
 #[derive(Component)]
@@ -11,6 +13,8 @@ pub struct Renderable
     pub glyph: rltk::FontCharType,
     pub foreground_color: RGB,
     pub background_color: RGB,
+    /// Entities with a lower order are drawn over those with a higher one, so actors stand on top of items
+    pub render_order: i32,
 }
 
 //Player
@@ -20,9 +24,108 @@ pub struct Player {}
 #[derive(Component)]
 pub struct Viewshed
 {
-    pub visible_tiles: Vec<Point>,
+    pub visible_tiles: Vec<Coordinate>,
     pub range: i32,
     pub dirty: bool
 }
 
+//MeleeAttack
+/// Damage dealt to whatever it strikes when standing next to it
+#[derive(Component)]
+pub struct MeleeAttack
+{
+    pub damage: i32,
+}
+
+//Name
+#[derive(Component)]
+pub struct Name
+{
+    pub name: String,
+}
+
+//Item
+#[derive(Component)]
+pub struct Item {}
 
+//InBackpack
+#[derive(Component)]
+pub struct InBackpack
+{
+    pub owner: Entity,
+}
+
+//CombatStats
+#[derive(Component)]
+pub struct CombatStats
+{
+    pub max_hp: i32,
+    pub hp: i32,
+}
+
+//SufferDamage
+/// Damage taken this turn, applied all at once by the damage system
+#[derive(Component)]
+pub struct SufferDamage
+{
+    pub amounts: Vec<i32>,
+}
+
+impl SufferDamage
+{
+    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32)
+    {
+        if let Some(suffering) = store.get_mut(victim)
+        {
+            suffering.amounts.push(amount);
+        }
+        else
+        {
+            store.insert(victim, SufferDamage { amounts: vec![amount] }).expect("Unable to insert damage");
+        }
+    }
+}
+
+//RangedWeapon
+/// Fires one round of ammunition from the backpack per shot
+#[derive(Component)]
+pub struct RangedWeapon
+{
+    pub range: i32,
+    pub damage: i32,
+}
+
+//Ammunition
+#[derive(Component)]
+pub struct Ammunition
+{
+    pub rounds: i32,
+}
+
+//Explosive
+/// Blows up when thrown, or when destroyed if it stands on the map, hurting everything within the radius and tearing
+/// open bulkheads close to the blast
+#[derive(Component)]
+pub struct Explosive
+{
+    pub radius: i32,
+    pub damage: i32,
+}
+
+//Flammable
+/// Burns up when fire reaches its tile
+#[derive(Component)]
+pub struct Flammable {}
+
+//Extinguisher
+/// Smothers fires with foam, one charge per spray
+#[derive(Component)]
+pub struct Extinguisher
+{
+    pub charges: i32,
+}
+
+//Breathes
+/// Needs oxygen to live, and bleeds when hurt
+#[derive(Component)]
+pub struct Breathes {}
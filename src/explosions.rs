@@ -0,0 +1,194 @@
+use rltk::{field_of_view, DistanceAlg, Point};
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::factions::hold_player_to_account;
+use super::noise::{make_noise, BLAST_NOISE};
+use super::game_log::GameLog;
+use super::map::{DeckView, Map};
+use super::map_tile::MapTile;
+use super::character::{player_attributes, Attributes};
+use super::ranged_combat::projectile_path;
+use super::status_effects::{StatusEffects, StatusKind};
+
+
+/// Blast damage a bulkhead can take before it gives way
+const BULKHEAD_STRENGTH: i32 = 6;
+/// Blast damage that leaves whoever took it stunned
+const STUNNING_BLAST_DAMAGE: i32 = 4;
+const STUNNED_TURNS: i32 = 2;
+/// How far an average thrower can throw something
+pub const THROW_RANGE: i32 = 6;
+
+/// Blast damage at a distance from the centre, falling off evenly to nothing just past the radius
+pub fn blast_damage(damage: i32, radius: i32, distance: f32) -> i32
+{
+    (damage as f32 * (1.0 - distance / (radius + 1) as f32)).round().max(0.0) as i32
+}
+
+/// Every tile the blast reaches, which is what can be seen from its centre within the radius, so walls shield
+/// what is behind them but take the blast themselves
+pub fn blast_area(map: &Map, centre: Coordinate, radius: i32) -> Vec<Coordinate>
+{
+    let deck = DeckView::new(map, centre.z);
+
+    field_of_view(Point::new(centre.x, centre.y), radius, &deck).into_iter()
+                                                                .filter(|point| point.x >= 0 && point.y >= 0 && (point.x as usize) < map.map_size.x && (point.y as usize) < map.map_size.y)
+                                                                .map(|point| Coordinate::new(point.x as usize, point.y as usize, centre.z))
+                                                                .collect()
+}
+
+/// Sets off an explosion, damaging everything caught in it with falloff from the centre and setting light to
+/// whatever will burn. Bulkheads near enough to the centre are torn apart, into rubble inside the station and into
+/// breaches where they are hull.
+/// Gives back the tiles the blast covered.
+pub fn explode(entity_system: &mut World, centre: Coordinate, radius: i32, damage: i32) -> Vec<Coordinate>
+{
+    let entities = entity_system.entities();
+    let coordinates = entity_system.read_storage::<Coordinate>();
+    let combat_stats = entity_system.read_storage::<CombatStats>();
+    let names = entity_system.read_storage::<Name>();
+    let players = entity_system.read_storage::<Player>();
+    let mut suffering = entity_system.write_storage::<SufferDamage>();
+    let mut status_effects = entity_system.write_storage::<StatusEffects>();
+    let mut map = entity_system.fetch_mut::<Map>();
+    let mut log = entity_system.fetch_mut::<GameLog>();
+    let centre_point = Point::new(centre.x, centre.y);
+    let area = blast_area(&map, centre, radius);
+
+    for coordinate in area.iter()
+    {
+        let distance = DistanceAlg::Pythagoras.distance2d(centre_point, Point::new(coordinate.x, coordinate.y));
+        let tile_damage = blast_damage(damage, radius, distance);
+
+        if tile_damage <= 0
+        {
+            continue;
+        }
+
+        for (entity, _stats, _coordinate) in (&entities, &combat_stats, &coordinates).join().filter(|(_entity, _stats, victim)| *victim == coordinate)
+        {
+            SufferDamage::new_damage(&mut suffering, entity, tile_damage);
+
+            if tile_damage >= STUNNING_BLAST_DAMAGE
+            {
+                StatusEffects::apply_to(&mut status_effects, entity, StatusKind::Stunned, STUNNED_TURNS);
+            }
+
+            match (players.get(entity), names.get(entity))
+            {
+                (Some(_player), _) => log.add(format!("The blast hits you for {} damage.", tile_damage)),
+                (None, Some(name)) => log.add(format!("The blast hits the {} for {} damage.", name.name, tile_damage)),
+                (None, None) => {}
+            }
+        }
+
+        map.ignite(*coordinate);
+
+        if let Some(tile) = map.get(*coordinate)
+        {
+            if !tile.holds_atmosphere() && tile_damage >= BULKHEAD_STRENGTH
+            {
+                let wreck = if map.is_hull(*coordinate) { MapTile::hull_breach() } else { MapTile::rubble() };
+                map.set_tile(*coordinate, wreck);
+            }
+        }
+    }
+
+    area
+}
+
+/// Where something thrown at the target comes down, at the target or at the end of its range, short of the
+/// first solid tile in the way
+pub fn landing_point(map: &Map, from: Coordinate, target: Coordinate, range: i32) -> Coordinate
+{
+    let mut landing = from;
+    let distance = (target.x as i32 - from.x as i32).abs().max((target.y as i32 - from.y as i32).abs());
+
+    for step in projectile_path(from, target, distance.min(range), map.map_size)
+    {
+        match map.get(step)
+        {
+            Some(tile) if tile.holds_atmosphere() && !tile.open_to_space => landing = step,
+            _ => break,
+        }
+    }
+
+    landing
+}
+
+/// The first explosive the player carries, if any
+pub fn player_explosive(entity_system: &World) -> Option<Entity>
+{
+    let entities = entity_system.entities();
+    let players = entity_system.read_storage::<Player>();
+    let backpacks = entity_system.read_storage::<InBackpack>();
+    let explosives = entity_system.read_storage::<Explosive>();
+
+    let player = (&entities, &players).join().map(|(entity, _player)| entity).next()?;
+
+    (&entities, &explosives, &backpacks).join()
+                                        .find(|(_entity, _explosive, backpack)| backpack.owner == player)
+                                        .map(|(entity, _explosive, _backpack)| entity)
+}
+
+/// How far someone with the given attributes can throw something
+pub fn throw_range(attributes: &Attributes) -> i32
+{
+    (THROW_RANGE + attributes.throw_bonus()).max(1)
+}
+
+/// Throws the player's first explosive towards the target, where it goes off as it lands.
+/// Gives back the tiles it flew over and the tiles the blast covered, both empty if nothing was thrown.
+pub fn player_throw(entity_system: &mut World, target: Coordinate) -> (Vec<Coordinate>, Vec<Coordinate>)
+{
+    let explosive = match player_explosive(entity_system)
+    {
+        Some(explosive) => explosive,
+        None =>
+        {
+            entity_system.fetch_mut::<GameLog>().add("You have nothing to throw.");
+            return (Vec::new(), Vec::new());
+        }
+    };
+
+    let from =
+    {
+        let players = entity_system.read_storage::<Player>();
+        let coordinates = entity_system.read_storage::<Coordinate>();
+
+        match (&players, &coordinates).join().map(|(_player, coordinate)| *coordinate).next()
+        {
+            Some(from) if from != target => from,
+            _ => return (Vec::new(), Vec::new()),
+        }
+    };
+
+    let (landing, flight) =
+    {
+        let map = entity_system.fetch::<Map>();
+        let range = throw_range(&player_attributes(entity_system));
+        let landing = landing_point(&map, from, target, range);
+        let flight: Vec<Coordinate> = projectile_path(from, landing, range, map.map_size).into_iter()
+                                                                                         .take_while(|step| *step != landing)
+                                                                                         .chain(std::iter::once(landing))
+                                                                                         .collect();
+        (landing, flight)
+    };
+
+    let (radius, damage) =
+    {
+        let explosives = entity_system.read_storage::<Explosive>();
+        let stats = explosives.get(explosive).expect("Explosive without stats");
+        (stats.radius, stats.damage)
+    };
+
+    entity_system.delete_entity(explosive).expect("Unable to delete");
+    entity_system.fetch_mut::<GameLog>().add("The charge explodes!");
+    let blast = explode(entity_system, landing, radius, damage);
+    hold_player_to_account(entity_system);
+    make_noise(entity_system, landing, BLAST_NOISE);
+
+    (flight, blast)
+}
@@ -0,0 +1,229 @@
+use specs_derive::Component;
+use specs::prelude::*;
+
+use super::entity_components::{CombatStats, Player, SufferDamage};
+use super::game_log::GameLog;
+
+
+/// Standing at or below which members of one faction attack the other on sight
+pub const HOSTILE_STANDING: i32 = -25;
+/// Standing at or above which factions look out for each other
+pub const FRIENDLY_STANDING: i32 = 25;
+pub const MIN_STANDING: i32 = -100;
+pub const MAX_STANDING: i32 = 100;
+
+/// Reputation a faction loses with the player for each of its members shot or blasted, and for each one killed
+const ATTACK_PENALTY: i32 = 10;
+const KILL_PENALTY: i32 = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Faction
+{
+    Crew,
+    Security,
+    Pirates,
+    Drones,
+    Scavengers,
+}
+
+impl Faction
+{
+    pub const ALL: [Faction; 5] = [Faction::Crew, Faction::Security, Faction::Pirates, Faction::Drones, Faction::Scavengers];
+
+    /// Name used in the backgrounds file
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Faction::Crew => "crew",
+            Faction::Security => "security",
+            Faction::Pirates => "pirates",
+            Faction::Drones => "drones",
+            Faction::Scavengers => "scavengers",
+        }
+    }
+
+    pub fn title(&self) -> &'static str
+    {
+        match self
+        {
+            Faction::Crew => "Crew",
+            Faction::Security => "Security",
+            Faction::Pirates => "Pirates",
+            Faction::Drones => "Rogue drones",
+            Faction::Scavengers => "Scavengers",
+        }
+    }
+
+    /// Colour its members are drawn in
+    pub fn color(&self) -> rltk::RGB
+    {
+        match self
+        {
+            Faction::Crew => rltk::RGB::named(rltk::GREEN),
+            Faction::Security => rltk::RGB::named(rltk::LIGHT_BLUE),
+            Faction::Pirates => rltk::RGB::named(rltk::RED),
+            Faction::Drones => rltk::RGB::named(rltk::MAGENTA),
+            Faction::Scavengers => rltk::RGB::named(rltk::ORANGE),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Faction>
+    {
+        Faction::ALL.iter().copied().find(|faction| faction.name() == name.trim())
+    }
+
+    fn index(&self) -> usize
+    {
+        Faction::ALL.iter().position(|faction| faction == self).unwrap_or(0)
+    }
+}
+
+//FactionMember
+/// Which faction an actor sides with, deciding who it fights and who it leaves alone
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FactionMember
+{
+    pub faction: Faction,
+}
+
+/// How every faction regards every other, and how each regards the player in particular.
+/// The player starts out with the standing of the faction they joined and earns their own from there.
+pub struct Relations
+{
+    /// Standing of the row faction towards the column faction, in the order of `Faction::ALL`
+    pub standings: [[i32; 5]; 5],
+    /// Standing of each faction towards the player
+    pub reputation: [i32; 5],
+}
+
+impl Default for Relations
+{
+    fn default() -> Relations
+    {
+        // Crew, Security, Pirates, Drones, Scavengers
+        let standings =
+        [
+            [  50,   40,  -60, -100,  -10],
+            [  40,   50,  -80, -100,  -40],
+            [ -60,  -80,   50, -100,  -30],
+            [-100, -100, -100,  100, -100],
+            [ -10,  -40,  -30, -100,   50],
+        ];
+        let mut relations = Relations { standings, reputation: [0; 5] };
+
+        relations.join(Faction::Crew);
+        relations
+    }
+}
+
+impl Relations
+{
+    pub fn standing(&self, of: Faction, towards: Faction) -> i32
+    {
+        self.standings[of.index()][towards.index()]
+    }
+
+    pub fn reputation(&self, faction: Faction) -> i32
+    {
+        self.reputation[faction.index()]
+    }
+
+    pub fn hostile(&self, of: Faction, towards: Faction) -> bool
+    {
+        self.standing(of, towards) <= HOSTILE_STANDING
+    }
+
+    pub fn hostile_to_player(&self, faction: Faction) -> bool
+    {
+        self.reputation(faction) <= HOSTILE_STANDING
+    }
+
+    pub fn friendly_to_player(&self, faction: Faction) -> bool
+    {
+        self.reputation(faction) >= FRIENDLY_STANDING
+    }
+
+    /// Resets the player's reputation to how each faction regards the one the player belongs to
+    pub fn join(&mut self, faction: Faction)
+    {
+        for other in Faction::ALL.iter()
+        {
+            self.reputation[other.index()] = self.standing(*other, faction);
+        }
+    }
+
+    /// Word for how a faction regards the player
+    pub fn reputation_label(&self, faction: Faction) -> &'static str
+    {
+        match self.reputation(faction)
+        {
+            _ if self.hostile_to_player(faction) => "Hostile",
+            _ if self.friendly_to_player(faction) => "Friendly",
+            _ => "Neutral",
+        }
+    }
+
+    /// Adjusts reputation after the player hurts a member of a faction. The faction itself and its friends think
+    /// worse of the player, its enemies a little better. Gives back every faction that turned hostile over it.
+    pub fn player_attacked(&mut self, victim: Faction, killed: bool) -> Vec<Faction>
+    {
+        let penalty = if killed { KILL_PENALTY } else { ATTACK_PENALTY };
+        let mut turned: Vec<Faction> = Vec::new();
+
+        for faction in Faction::ALL.iter()
+        {
+            let change = match self.standing(*faction, victim)
+            {
+                _ if *faction == victim => -penalty,
+                standing if standing >= FRIENDLY_STANDING => -penalty / 3,
+                standing if standing <= HOSTILE_STANDING => penalty / 6,
+                _ => 0,
+            };
+            let was_hostile = self.hostile_to_player(*faction);
+
+            self.reputation[faction.index()] = (self.reputation(*faction) + change).clamp(MIN_STANDING, MAX_STANDING);
+
+            if !was_hostile && self.hostile_to_player(*faction)
+            {
+                turned.push(*faction);
+            }
+        }
+
+        turned
+    }
+}
+
+/// Whether an actor of one faction would attack an actor of the other, going by the player's own reputation when
+/// either of them is the player
+pub fn is_hostile(relations: &Relations, attacker: (Faction, bool), target: (Faction, bool)) -> bool
+{
+    match (attacker, target)
+    {
+        ((_, true), (_, true)) => false,
+        ((_, true), (faction, false)) | ((faction, false), (_, true)) => relations.hostile_to_player(faction),
+        ((attacker, false), (target, false)) => relations.hostile(attacker, target),
+    }
+}
+
+/// Changes reputation for every faction member about to take damage, for use straight after the player has shot
+/// or blasted something, before the damage is dealt. Factions that turn hostile over it are named in the log.
+pub fn hold_player_to_account(entity_system: &World)
+{
+    let members = entity_system.read_storage::<FactionMember>();
+    let players = entity_system.read_storage::<Player>();
+    let suffering = entity_system.read_storage::<SufferDamage>();
+    let combat_stats = entity_system.read_storage::<CombatStats>();
+    let mut relations = entity_system.fetch_mut::<Relations>();
+    let mut log = entity_system.fetch_mut::<GameLog>();
+
+    for (member, damage, stats, _not_player) in (&members, &suffering, &combat_stats, !&players).join()
+    {
+        let killed = damage.amounts.iter().sum::<i32>() >= stats.hp;
+
+        for faction in relations.player_attacked(member.faction, killed)
+        {
+            log.add(format!("The {} now treat you as an enemy.", faction.title().to_lowercase()));
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use rand::Rng;
+use rand::rngs::StdRng;
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::game_log::GameLog;
+use super::map::Map;
+use super::explosions::landing_point;
+use super::ranged_combat::projectile_path;
+use super::status_effects::{StatusEffects, StatusKind};
+
+
+/// How far an extinguisher reaches
+pub const SPRAY_RANGE: i32 = 4;
+
+/// Percent chance per turn a fire spreads to each flammable neighbour
+const SPREAD_CHANCE: i32 = 25;
+/// Turns a body keeps burning after leaving the flames
+const BURNING_TURNS: i32 = 3;
+
+/// Burns fires for a turn, spreading them to flammable neighbours, setting light to what stands in them and
+/// destroying flammable items
+pub struct FireSystem {}
+
+impl<'a> System<'a> for FireSystem
+{
+    type SystemData = (WriteExpect<'a, Map>,
+                       WriteExpect<'a, StdRng>,
+                       WriteExpect<'a, GameLog>,
+                       Entities<'a>,
+                       ReadStorage<'a, Coordinate>,
+                       ReadStorage<'a, CombatStats>,
+                       ReadStorage<'a, Flammable>,
+                       ReadStorage<'a, Player>,
+                       WriteStorage<'a, StatusEffects>);
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let (mut map, mut rng, mut log, entities, coordinates, combat_stats, flammables, players, mut status_effects) = data;
+
+        let burning = map.burning_tiles();
+
+        for coordinate in burning.iter()
+        {
+            for (delta_x, delta_y) in [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            {
+                let (x, y) = (coordinate.x as i32 + delta_x, coordinate.y as i32 + delta_y);
+
+                if x >= 0 && y >= 0 && rng.gen_range(0..100) < SPREAD_CHANCE
+                {
+                    map.ignite(Coordinate::new(x as usize, y as usize, coordinate.z));
+                }
+            }
+        }
+
+        for (entity, coordinate) in (&entities, &coordinates).join()
+        {
+            if !burning.contains(coordinate)
+            {
+                continue;
+            }
+
+            if combat_stats.get(entity).is_some()
+            {
+                let already_burning = status_effects.get(entity).map(|effects| effects.has(StatusKind::Burning)).unwrap_or(false);
+                StatusEffects::apply_to(&mut status_effects, entity, StatusKind::Burning, BURNING_TURNS);
+
+                if players.get(entity).is_some() && !already_burning
+                {
+                    log.add("You catch fire!");
+                }
+            }
+            else if flammables.get(entity).is_some()
+            {
+                entities.delete(entity).expect("Unable to delete");
+            }
+        }
+
+        map.burn();
+    }
+}
+
+/// The first extinguisher the player carries with charges left, if any
+pub fn player_extinguisher(entity_system: &World) -> Option<Entity>
+{
+    let entities = entity_system.entities();
+    let players = entity_system.read_storage::<Player>();
+    let backpacks = entity_system.read_storage::<InBackpack>();
+    let extinguishers = entity_system.read_storage::<Extinguisher>();
+
+    let player = (&entities, &players).join().map(|(entity, _player)| entity).next()?;
+
+    (&entities, &extinguishers, &backpacks).join()
+                                           .find(|(_entity, extinguisher, backpack)| backpack.owner == player && extinguisher.charges > 0)
+                                           .map(|(entity, _extinguisher, _backpack)| entity)
+}
+
+/// Every tile a spray landing on the centre smothers
+pub fn spray_area(centre: Coordinate) -> Vec<Coordinate>
+{
+    let mut area: Vec<Coordinate> = Vec::new();
+
+    for x in centre.x.saturating_sub(1)..=centre.x + 1
+    {
+        for y in centre.y.saturating_sub(1)..=centre.y + 1
+        {
+            area.push(Coordinate::new(x, y, centre.z));
+        }
+    }
+
+    area
+}
+
+/// Sprays the player's extinguisher towards the target, putting out every fire around where the foam lands.
+/// Gives back the tiles the foam flew over and the tiles it covered, both empty if nothing was sprayed.
+pub fn player_spray(entity_system: &mut World, target: Coordinate) -> (Vec<Coordinate>, Vec<Coordinate>)
+{
+    let extinguisher = match player_extinguisher(entity_system)
+    {
+        Some(extinguisher) => extinguisher,
+        None =>
+        {
+            entity_system.fetch_mut::<GameLog>().add("You have no extinguisher with anything left in it.");
+            return (Vec::new(), Vec::new());
+        }
+    };
+
+    let players = entity_system.read_storage::<Player>();
+    let coordinates = entity_system.read_storage::<Coordinate>();
+    let mut extinguishers = entity_system.write_storage::<Extinguisher>();
+    let mut map = entity_system.fetch_mut::<Map>();
+    let mut log = entity_system.fetch_mut::<GameLog>();
+
+    // Spraying at your own feet is allowed, it puts out the fire you are standing in
+    let from = match (&players, &coordinates).join().map(|(_player, coordinate)| *coordinate).next()
+    {
+        Some(from) => from,
+        None => return (Vec::new(), Vec::new()),
+    };
+    let landing = landing_point(&map, from, target, SPRAY_RANGE);
+    let flight: Vec<Coordinate> = projectile_path(from, landing, SPRAY_RANGE, map.map_size).into_iter()
+                                                                                           .take_while(|step| *step != landing)
+                                                                                           .chain(std::iter::once(landing))
+                                                                                           .collect();
+    let area = spray_area(landing);
+    let mut smothered = 0;
+
+    for coordinate in area.iter()
+    {
+        if map.is_burning(*coordinate)
+        {
+            map.extinguish(*coordinate);
+            smothered += 1;
+        }
+    }
+
+    if let Some(extinguisher) = extinguishers.get_mut(extinguisher)
+    {
+        extinguisher.charges -= 1;
+    }
+
+    match smothered
+    {
+        0 => log.add("You spray foam, but there is nothing burning there."),
+        _ => log.add("You smother the flames with foam."),
+    }
+
+    (flight, area)
+}
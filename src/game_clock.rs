@@ -0,0 +1,14 @@
+/// Counts game turns, which advance when the player acts rather than every frame
+#[derive(Default)]
+pub struct GameClock
+{
+    pub turn: u64,
+}
+
+impl GameClock
+{
+    pub fn advance(&mut self)
+    {
+        self.turn += 1;
+    }
+}
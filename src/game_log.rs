@@ -0,0 +1,14 @@
+/// Messages shown to the player, oldest first
+#[derive(Default)]
+pub struct GameLog
+{
+    pub entries: Vec<String>,
+}
+
+impl GameLog
+{
+    pub fn add<S: ToString>(&mut self, message: S)
+    {
+        self.entries.push(message.to_string());
+    }
+}
@@ -0,0 +1,1344 @@
+use std::collections::HashSet;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rltk::{GameState, Rltk, RGB, VirtualKeyCode};
+use specs::prelude::*;
+
+use super::{entity_components::*, coordinate::Coordinate};
+use super::{map_memory::*, game_clock::GameClock};
+use super::map::{Map, BELOW_DECK_DIMMING};
+use super::map_tile::Ladder;
+use super::camera::Camera;
+use super::input_recording::{InputEvent, InputRecorder, RecordedInput, world_hash};
+use super::key_bindings::{Action, KeyBindings, Preset, DEFAULT_KEY_BINDINGS_PATH};
+use super::key_names::KeyPress;
+use super::game_log::GameLog;
+use super::navigation::{exploration_step, remembered_path};
+use super::player::{player_act, nearest_visible_hostile, visible_hostile, visible_items};
+use super::ranged_combat::{hit_chance, player_fire, player_weapon, projectile_path};
+use super::damage_system::{delete_the_dead, DamageSystem};
+use super::explosions::{blast_area, landing_point, player_explosive, player_throw, throw_range};
+use super::fire_system::{player_extinguisher, player_spray, spray_area, SPRAY_RANGE};
+use super::status_effects::StatusEffects;
+use super::skills::{player_skill_bonus, Skill, Skills};
+use super::character::{player_attributes, Attributes, Background, Character, CharacterBackground, MAX_NAME_LENGTH};
+use super::survival::{Consumable, Needs};
+use super::turn_scheduler::TurnScheduler;
+use super::factions::{FactionMember, Relations};
+use super::ai::{BehaviourProfiles, Brain};
+use super::noise::Sneaking;
+use super::station_network::{run_command, SecurityCamera, SecurityMonitor, StationNetwork, Terminal, Turret};
+use super::power::{is_powered, Battery, Generator, PowerConsumer, PowerGrid};
+use super::life_support::LifeSupport;
+use super::hazards::{Overlay, SolarFlare};
+use super::physics::Velocity;
+use super::{gui, spawner};
+
+
+/// What the game is waiting on this frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunState
+{
+    /// Naming the character while `naming`, then picking one of the backgrounds
+    CharacterCreation { selected: usize, naming: bool },
+    AwaitingInput,
+    /// The rebinding screen, `capturing` while it waits for the key to bind to the selected action
+    KeyBindingMenu { selected: usize, capturing: bool },
+    /// The player's attributes and skills, until any key is pressed
+    CharacterSheet,
+    /// The right click menu for a tile, opened at a screen position
+    ContextMenu { target: Coordinate, screen_x: i32, screen_y: i32 },
+    /// Moving a cursor over the map to examine what is under it
+    Look { cursor: Coordinate },
+    /// Aiming a shot or a throw with the map cursor
+    Targeting { cursor: Coordinate, purpose: TargetPurpose },
+    /// Typing commands into a terminal's shell until Escape or `exit`
+    Terminal { terminal: Entity },
+    /// Watching a camera's feed on a security monitor
+    CameraFeed { monitor: Entity, camera: Entity },
+    /// The player has died and nothing more can be done
+    Dead,
+}
+
+/// What the targeting cursor is aiming
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetPurpose
+{
+    Fire,
+    Throw,
+    Spray,
+}
+
+impl TargetPurpose
+{
+    fn action(&self) -> Action
+    {
+        match self
+        {
+            TargetPurpose::Fire => Action::Fire,
+            TargetPurpose::Throw => Action::Throw,
+            TargetPurpose::Spray => Action::Spray,
+        }
+    }
+}
+
+/// A shot, a thrown item or a spray being drawn flying along its path one tile every couple of frames,
+/// followed by a flash over the area it covered if it covered one
+pub struct ProjectileAnimation
+{
+    pub path: Vec<Coordinate>,
+    pub blast: Vec<Coordinate>,
+    pub blast_color: RGB,
+    pub frame: usize,
+}
+
+const FRAMES_PER_PROJECTILE_STEP: usize = 2;
+const BLAST_FRAMES: usize = 12;
+/// Longest command that can be typed at a terminal
+const MAX_TERMINAL_COMMAND_LENGTH: usize = 40;
+
+/// What the player did with a map cursor this frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorInput
+{
+    Idle,
+    Moved(Coordinate),
+    Confirm,
+    Cancel,
+}
+
+/// Where the mouse is on screen and which buttons went down this frame
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MouseInput
+{
+    pub screen_x: i32,
+    pub screen_y: i32,
+    pub left_click: bool,
+    pub right_click: bool,
+}
+
+
+pub struct State
+{
+    pub entity_system: World,
+    pub map_size: Coordinate,
+    pub screen_width: usize,
+    pub screen_height: usize,
+    /// Seed everything random in this game is drawn from
+    pub seed: u64,
+    /// Writes every input to a replay file when recording is switched on
+    pub recorder: Option<InputRecorder>,
+    /// Runs everything that happens as turns pass
+    pub turn_scheduler: TurnScheduler,
+    /// Backgrounds offered on the character creation screen
+    pub backgrounds: Vec<Background>,
+    /// Name typed so far on the character creation screen
+    character_name: String,
+    /// Command typed so far at a terminal and everything the terminal has printed since it was opened
+    terminal_command: String,
+    terminal_screen: Vec<String>,
+    /// What the map is tinted by
+    overlay: Overlay,
+    /// Where the player appears once the character is made
+    player_start: Coordinate,
+    pub run_state: RunState,
+    pub key_bindings: KeyBindings,
+    /// Where the rebinding screen saves key bindings
+    pub key_bindings_path: String,
+    /// Remaining steps of a click to travel, taken one per frame
+    pub travel_path: Vec<Coordinate>,
+    /// Whether the player is auto-exploring, taking one step per frame until something interrupts
+    pub exploring: bool,
+    /// Items already in view when exploring started or last stopped, which do not stop it again
+    explore_ignored_items: Vec<Entity>,
+    /// The last shot fired, while it is still being drawn
+    pub projectile: Option<ProjectileAnimation>,
+    /// Mouse as of the last frame, `None` when running without a screen
+    pub mouse: Option<MouseInput>,
+    /// Which of the left and right mouse buttons were down last frame, to spot new clicks
+    mouse_buttons_down: (bool, bool),
+}
+
+impl State
+{
+    /// Builds a fresh station from the seed, ready for a character to be made and placed in its first room
+    pub fn new(map_size: Coordinate, screen_width: usize, screen_height: usize, seed: u64) -> State
+    {
+        let mut game_state = State
+        {
+            entity_system: World::new(),
+            map_size,
+            screen_width,
+            screen_height,
+            seed,
+            recorder: None,
+            turn_scheduler: TurnScheduler::default(),
+            backgrounds: Background::built_in(),
+            character_name: String::new(),
+            terminal_command: String::new(),
+            terminal_screen: Vec::new(),
+            overlay: Overlay::Off,
+            player_start: Coordinate::new(0, 0, 0),
+            run_state: RunState::CharacterCreation { selected: 0, naming: true },
+            key_bindings: KeyBindings::preset(Preset::Numpad),
+            key_bindings_path: DEFAULT_KEY_BINDINGS_PATH.to_string(),
+            travel_path: Vec::new(),
+            exploring: false,
+            explore_ignored_items: Vec::new(),
+            projectile: None,
+            mouse: None,
+            mouse_buttons_down: (false, false),
+        };
+
+        register_components(&mut game_state);
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (map, rooms) = Map::rooms_and_corridors_map(20,5, 10, game_state.map_size, &mut rng);
+        game_state.player_start = rooms[0].center();
+
+        game_state.entity_system.insert(map);
+        game_state.entity_system.insert(GameClock::default());
+        game_state.entity_system.insert(GameLog::default());
+        game_state.entity_system.insert(Relations::default());
+        game_state.entity_system.insert(BehaviourProfiles::built_in());
+        game_state.entity_system.insert(StationNetwork::default());
+        game_state.entity_system.insert(PowerGrid::default());
+        game_state.entity_system.insert(SolarFlare::default());
+
+        for room in rooms.iter().skip(1)
+        {
+            spawner::fill_room(&mut game_state.entity_system, room, &mut rng);
+        }
+        spawner::assign_patrol_routes(&mut game_state.entity_system, &rooms, &mut rng);
+        spawner::wire_station_network(&mut game_state.entity_system, &rooms, &mut rng);
+        spawner::install_power(&mut game_state.entity_system, &rooms, &mut rng);
+        spawner::install_life_support(&mut game_state.entity_system, &rooms, &mut rng);
+
+        game_state.entity_system.insert(rng);
+
+        game_state
+    }
+
+    /// Puts the finished character on the station and starts play
+    pub fn create_character(&mut self, character: Character)
+    {
+        spawner::player(&mut self.entity_system, self.player_start, &character);
+        TurnScheduler::observe(&mut self.entity_system);
+        self.entity_system.write_resource::<Relations>().join(character.background.faction);
+        self.entity_system.fetch_mut::<GameLog>().add(format!("Welcome aboard, {} the {}.", character.name, character.background.title.to_lowercase()));
+
+        if let Some(recorder) = &mut self.recorder
+        {
+            if let Err(error) = recorder.record_character(&character)
+            {
+                eprintln!("stopped recording input: {}", error);
+                self.recorder = None;
+            }
+        }
+
+        self.run_state = RunState::AwaitingInput;
+    }
+
+    pub fn run_systems(&mut self)
+    {
+        let mut damage_system = DamageSystem{};
+
+        self.turn_scheduler.run(&mut self.entity_system);
+        damage_system.run_now(&self.entity_system);
+
+        if delete_the_dead(&mut self.entity_system) && self.run_state != RunState::Dead
+        {
+            self.entity_system.fetch_mut::<GameLog>().add("You die.");
+            self.run_state = RunState::Dead;
+        }
+        self.entity_system.maintain();
+    }
+
+    /// Advances the simulation by one frame with whatever key was pressed and whatever the mouse did, without drawing anything
+    pub fn update(&mut self, key_press: Option<KeyPress>, mouse: Option<MouseInput>)
+    {
+        self.run_systems();
+        let previous_mouse = self.mouse;
+        self.mouse = mouse;
+
+        match self.run_state
+        {
+            RunState::CharacterCreation { selected, naming } =>
+            {
+                self.run_state = self.character_creation_input(key_press, selected, naming);
+            }
+            RunState::AwaitingInput =>
+            {
+                let clicked_mouse = mouse.filter(|mouse| mouse.left_click || mouse.right_click);
+
+                if let Some(key_press) = key_press
+                {
+                    // Any key interrupts travel and exploring, whether or not it does anything else
+                    self.travel_path.clear();
+                    self.exploring = false;
+
+                    if let Some(action) = self.key_bindings.action_for(key_press)
+                    {
+                        self.perform_action(action);
+                    }
+                }
+                else if let Some(mouse) = clicked_mouse
+                {
+                    self.travel_path.clear();
+                    self.exploring = false;
+                    self.mouse_click(mouse);
+                }
+                else if !self.travel_path.is_empty()
+                {
+                    self.continue_travel();
+                }
+                else if self.exploring
+                {
+                    self.continue_exploring();
+                }
+            }
+            RunState::CharacterSheet =>
+            {
+                if key_press.is_some()
+                {
+                    self.run_state = RunState::AwaitingInput;
+                }
+            }
+            RunState::KeyBindingMenu { selected, capturing } =>
+            {
+                self.run_state = self.key_binding_menu_input(key_press, selected, capturing);
+            }
+            RunState::ContextMenu { target, screen_x, screen_y } =>
+            {
+                self.run_state = self.context_menu_input(key_press, mouse, target, screen_x, screen_y);
+            }
+            RunState::Look { cursor } =>
+            {
+                self.run_state = match self.cursor_input(key_press, mouse, previous_mouse, cursor, Action::Look)
+                {
+                    CursorInput::Idle => RunState::Look { cursor },
+                    CursorInput::Moved(cursor) => RunState::Look { cursor },
+                    CursorInput::Confirm | CursorInput::Cancel => RunState::AwaitingInput,
+                };
+            }
+            RunState::Targeting { cursor, purpose } =>
+            {
+                self.run_state = match self.cursor_input(key_press, mouse, previous_mouse, cursor, purpose.action())
+                {
+                    CursorInput::Idle => RunState::Targeting { cursor, purpose },
+                    CursorInput::Moved(cursor) => RunState::Targeting { cursor, purpose },
+                    CursorInput::Confirm =>
+                    {
+                        match purpose
+                        {
+                            TargetPurpose::Fire => self.fire_at(cursor),
+                            TargetPurpose::Throw => self.throw_at(cursor),
+                            TargetPurpose::Spray => self.spray_at(cursor),
+                        }
+                        RunState::AwaitingInput
+                    }
+                    CursorInput::Cancel => RunState::AwaitingInput,
+                };
+            }
+            RunState::Terminal { terminal } =>
+            {
+                self.run_state = self.terminal_input(key_press, terminal);
+            }
+            RunState::CameraFeed { monitor, camera } =>
+            {
+                self.run_state = self.camera_feed_input(key_press, monitor, camera);
+            }
+            RunState::Dead => {}
+        }
+    }
+
+    /// Moves a map cursor with the movement keys or by following the mouse, keeping it on the player's deck and on screen.
+    /// Enter, a left click or the key of the action that opened the cursor confirm, Escape or a right click cancel.
+    pub fn cursor_input(&self, key_press: Option<KeyPress>, mouse: Option<MouseInput>, previous_mouse: Option<MouseInput>, cursor: Coordinate, opened_by: Action) -> CursorInput
+    {
+        let camera = match self.camera()
+        {
+            Some(camera) => camera,
+            None => return CursorInput::Cancel,
+        };
+
+        if let Some(key_press) = key_press
+        {
+            let action = self.key_bindings.action_for(key_press);
+
+            if key_press.key == VirtualKeyCode::Escape
+            {
+                return CursorInput::Cancel;
+            }
+            if key_press.key == VirtualKeyCode::Return || action == Some(opened_by)
+            {
+                return CursorInput::Confirm;
+            }
+
+            return match action.and_then(|action| action.movement_delta())
+            {
+                Some((delta_x, delta_y)) =>
+                {
+                    let (x, y) = (cursor.x as i32 + delta_x, cursor.y as i32 + delta_y);
+                    let moved = Coordinate::new(x.max(0) as usize, y.max(0) as usize, cursor.z);
+
+                    match x >= 0 && y >= 0 && camera.to_screen(moved).is_some()
+                    {
+                        true => CursorInput::Moved(moved),
+                        false => CursorInput::Idle,
+                    }
+                }
+                None => CursorInput::Idle,
+            };
+        }
+
+        if let Some(mouse) = mouse
+        {
+            if mouse.left_click
+            {
+                return CursorInput::Confirm;
+            }
+            if mouse.right_click
+            {
+                return CursorInput::Cancel;
+            }
+
+            // The mouse only takes over the cursor once it moves, so it does not fight the keyboard
+            let mouse_moved = previous_mouse.map(|previous| (previous.screen_x, previous.screen_y)) != Some((mouse.screen_x, mouse.screen_y));
+
+            if let Some(target) = camera.to_map(mouse.screen_x, mouse.screen_y).filter(|_target| mouse_moved)
+            {
+                return CursorInput::Moved(target);
+            }
+        }
+
+        CursorInput::Idle
+    }
+
+    /// The view onto the player's deck as it is drawn this frame
+    pub fn camera(&self) -> Option<Camera>
+    {
+        let map_view_height = self.screen_height.saturating_sub(gui::HUD_HEIGHT);
+
+        self.player_coordinate().map(|coordinate| Camera::centered_on(coordinate, self.screen_width, map_view_height, self.map_size))
+    }
+
+    fn mouse_click(&mut self, mouse: MouseInput)
+    {
+        let target = match self.camera().and_then(|camera| camera.to_map(mouse.screen_x, mouse.screen_y))
+        {
+            Some(target) => target,
+            None => return,
+        };
+
+        if mouse.left_click
+        {
+            self.start_travel(target);
+        }
+        else if !gui::context_menu_options(&self.entity_system, target).is_empty()
+        {
+            self.run_state = RunState::ContextMenu { target, screen_x: mouse.screen_x, screen_y: mouse.screen_y };
+        }
+    }
+
+    /// Path to the player's destination over the tiles the player remembers
+    pub fn path_for_player(&self, target: Coordinate) -> Option<Vec<Coordinate>>
+    {
+        let players = self.entity_system.read_storage::<Player>();
+        let coordinates = self.entity_system.read_storage::<Coordinate>();
+        let memories = self.entity_system.read_storage::<MapMemory>();
+
+        (&players, &coordinates, &memories).join()
+                                            .next()
+                                            .and_then(|(_player, coordinate, memory)| remembered_path(memory, self.map_size, *coordinate, target))
+    }
+
+    pub fn start_travel(&mut self, target: Coordinate)
+    {
+        if let Some(hostile) = visible_hostile(&self.entity_system)
+        {
+            self.entity_system.fetch_mut::<GameLog>().add(format!("Not with a {} in view.", hostile));
+            return;
+        }
+
+        match self.path_for_player(target)
+        {
+            Some(path) => self.travel_path = path,
+            None => self.entity_system.fetch_mut::<GameLog>().add("You don't know a way there."),
+        }
+    }
+
+    /// Takes the next travel step, unless something hostile has come into view or the way is blocked
+    fn continue_travel(&mut self)
+    {
+        if let Some(hostile) = visible_hostile(&self.entity_system)
+        {
+            self.entity_system.fetch_mut::<GameLog>().add(format!("You spot a {} and stop.", hostile));
+            self.travel_path.clear();
+            return;
+        }
+
+        let (current, next) = match (self.player_coordinate(), self.travel_path.first())
+        {
+            (Some(current), Some(next)) => (current, *next),
+            _ => return,
+        };
+
+        match Action::movement(next.x as i32 - current.x as i32, next.y as i32 - current.y as i32)
+        {
+            Some(action) =>
+            {
+                self.perform_action(action);
+
+                if self.player_coordinate() == Some(next)
+                {
+                    self.travel_path.remove(0);
+                }
+                else
+                {
+                    self.travel_path.clear();
+                }
+            }
+            None => self.travel_path.clear(),
+        }
+    }
+
+    fn start_exploring(&mut self)
+    {
+        if let Some(hostile) = visible_hostile(&self.entity_system)
+        {
+            self.entity_system.fetch_mut::<GameLog>().add(format!("Not with a {} in view.", hostile));
+            return;
+        }
+
+        self.explore_ignored_items = visible_items(&self.entity_system).into_iter().map(|(entity, _name)| entity).collect();
+        self.exploring = true;
+    }
+
+    /// Takes the next step towards unexplored tiles, stopping for hostiles, newly seen items or a fully explored deck
+    fn continue_exploring(&mut self)
+    {
+        if let Some(hostile) = visible_hostile(&self.entity_system)
+        {
+            self.entity_system.fetch_mut::<GameLog>().add(format!("You spot a {} and stop.", hostile));
+            self.exploring = false;
+            return;
+        }
+
+        let new_item = visible_items(&self.entity_system).into_iter().find(|(entity, _name)| !self.explore_ignored_items.contains(entity));
+
+        if let Some((_entity, name)) = new_item
+        {
+            self.entity_system.fetch_mut::<GameLog>().add(format!("You spot a {} and stop.", name));
+            self.exploring = false;
+            return;
+        }
+
+        let (current, next) = match (self.player_coordinate(), self.exploration_step())
+        {
+            (Some(current), Some(next)) => (current, next),
+            _ =>
+            {
+                self.entity_system.fetch_mut::<GameLog>().add("There is nothing left to explore on this deck.");
+                self.exploring = false;
+                return;
+            }
+        };
+
+        match Action::movement(next.x as i32 - current.x as i32, next.y as i32 - current.y as i32)
+        {
+            Some(action) =>
+            {
+                self.perform_action(action);
+
+                if self.player_coordinate() != Some(next)
+                {
+                    self.exploring = false;
+                }
+            }
+            None => self.exploring = false,
+        }
+    }
+
+    fn exploration_step(&self) -> Option<Coordinate>
+    {
+        let players = self.entity_system.read_storage::<Player>();
+        let coordinates = self.entity_system.read_storage::<Coordinate>();
+        let viewsheds = self.entity_system.read_storage::<Viewshed>();
+        let memories = self.entity_system.read_storage::<MapMemory>();
+
+        (&players, &coordinates, &viewsheds, &memories).join()
+                                                        .next()
+                                                        .and_then(|(_player, coordinate, viewshed, memory)|
+                                                        {
+                                                            let visible_tiles: HashSet<Coordinate> = viewshed.visible_tiles.iter().copied().collect();
+                                                            exploration_step(memory, self.map_size, &visible_tiles, *coordinate)
+                                                        })
+    }
+
+    fn context_menu_input(&mut self, key_press: Option<KeyPress>, mouse: Option<MouseInput>, target: Coordinate, screen_x: i32, screen_y: i32) -> RunState
+    {
+        let options = gui::context_menu_options(&self.entity_system, target);
+        let area = gui::context_menu_area(&options, screen_x, screen_y, self.screen_width, self.screen_height);
+
+        let chosen = match (key_press, mouse)
+        {
+            (Some(key_press), _) if key_press.key == VirtualKeyCode::Escape => return RunState::AwaitingInput,
+            (Some(key_press), _) => menu_digit(key_press.key).filter(|digit| *digit >= 1).map(|digit| digit - 1),
+            (None, Some(mouse)) if mouse.left_click =>
+            {
+                match gui::context_menu_entry_at(&options, area, mouse.screen_x, mouse.screen_y)
+                {
+                    Some(entry) => Some(entry),
+                    None => return RunState::AwaitingInput,
+                }
+            }
+            (None, Some(mouse)) if mouse.right_click => return RunState::AwaitingInput,
+            _ => None,
+        };
+
+        match chosen.and_then(|entry| options.get(entry))
+        {
+            Some((_label, context_action)) =>
+            {
+                match context_action
+                {
+                    gui::ContextAction::Examine(entity) =>
+                    {
+                        let name = self.entity_system.read_storage::<Name>().get(*entity).map(|name| name.name.clone());
+                        let faction = self.entity_system.read_storage::<FactionMember>().get(*entity).map(|member| member.faction);
+                        let behaviour = self.entity_system.read_storage::<Brain>().get(*entity).and_then(|brain| brain.behaviour);
+
+                        match (name, faction)
+                        {
+                            (Some(name), Some(faction)) =>
+                            {
+                                let attitude = self.entity_system.fetch::<Relations>().reputation_label(faction).to_lowercase();
+                                let doing = behaviour.map(|behaviour| format!(", {}", behaviour.description())).unwrap_or_default();
+                                self.entity_system.fetch_mut::<GameLog>().add(format!("You see a {} ({}, {}{}).", name, faction.title(), attitude, doing));
+                            }
+                            (Some(name), None) => self.entity_system.fetch_mut::<GameLog>().add(format!("You see a {}.", name)),
+                            _ => {}
+                        }
+                    }
+                    gui::ContextAction::PickUp => self.perform_action(Action::PickUp),
+                    gui::ContextAction::Climb(Ladder::Down) => self.perform_action(Action::Descend),
+                    gui::ContextAction::Climb(Ladder::Up) => self.perform_action(Action::Ascend),
+                    gui::ContextAction::TravelHere => self.start_travel(target),
+                }
+                RunState::AwaitingInput
+            }
+            None => RunState::ContextMenu { target, screen_x, screen_y },
+        }
+    }
+
+    /// Carries out an action whichever key or replay it came from, recording it if it is a game action
+    pub fn perform_action(&mut self, action: Action)
+    {
+        if !action.is_interface()
+        {
+            let turn = self.entity_system.fetch::<GameClock>().turn;
+
+            if player_act(&mut self.entity_system, action)
+            {
+                self.entity_system.write_resource::<GameClock>().advance();
+            }
+            self.record_input(turn, InputEvent::Action(action));
+            return;
+        }
+
+        match action
+        {
+            Action::KeyBindingMenu => self.run_state = RunState::KeyBindingMenu { selected: 0, capturing: false },
+            Action::CharacterSheet => self.run_state = RunState::CharacterSheet,
+            Action::AutoExplore => self.start_exploring(),
+            Action::UseTerminal => self.open_terminal(),
+            Action::CycleOverlay =>
+            {
+                self.overlay = self.overlay.next();
+                self.entity_system.fetch_mut::<GameLog>().add(format!("Overlay: {}.", self.overlay.name()));
+            }
+            Action::Fire =>
+            {
+                match player_weapon(&self.entity_system)
+                {
+                    Some(_weapon) =>
+                    {
+                        let cursor = nearest_visible_hostile(&self.entity_system).or_else(|| self.player_coordinate());
+
+                        if let Some(cursor) = cursor
+                        {
+                            self.run_state = RunState::Targeting { cursor, purpose: TargetPurpose::Fire };
+                        }
+                    }
+                    None => self.entity_system.fetch_mut::<GameLog>().add("You have nothing loaded to shoot with."),
+                }
+            }
+            Action::Throw =>
+            {
+                match player_explosive(&self.entity_system)
+                {
+                    Some(_explosive) =>
+                    {
+                        let cursor = nearest_visible_hostile(&self.entity_system).or_else(|| self.player_coordinate());
+
+                        if let Some(cursor) = cursor
+                        {
+                            self.run_state = RunState::Targeting { cursor, purpose: TargetPurpose::Throw };
+                        }
+                    }
+                    None => self.entity_system.fetch_mut::<GameLog>().add("You have nothing to throw."),
+                }
+            }
+            Action::Spray =>
+            {
+                match (player_extinguisher(&self.entity_system), self.player_coordinate())
+                {
+                    (Some(_extinguisher), Some(cursor)) => self.run_state = RunState::Targeting { cursor, purpose: TargetPurpose::Spray },
+                    (None, _) => self.entity_system.fetch_mut::<GameLog>().add("You have no extinguisher with anything left in it."),
+                    _ => {}
+                }
+            }
+            Action::Look =>
+            {
+                if let Some(cursor) = self.player_coordinate()
+                {
+                    self.run_state = RunState::Look { cursor };
+                }
+            }
+            // Carried out and recorded above
+            Action::MoveNorth | Action::MoveNorthEast | Action::MoveEast | Action::MoveSouthEast | Action::MoveSouth |
+            Action::MoveSouthWest | Action::MoveWest | Action::MoveNorthWest | Action::Wait | Action::PickUp |
+            Action::Descend | Action::Ascend | Action::Consume | Action::Sneak => {}
+        }
+    }
+
+    /// Shoots the player's weapon at a tile, wherever the target came from, and starts drawing the shot
+    pub fn fire_at(&mut self, target: Coordinate)
+    {
+        let turn = self.entity_system.fetch::<GameClock>().turn;
+        let flight = player_fire(&mut self.entity_system, target);
+
+        if !flight.is_empty()
+        {
+            self.entity_system.write_resource::<GameClock>().advance();
+            self.projectile = Some(ProjectileAnimation { path: flight, blast: Vec::new(), blast_color: RGB::named(rltk::BLACK), frame: 0 });
+        }
+        self.record_input(turn, InputEvent::FireAt(target));
+    }
+
+    /// Throws the player's explosive at a tile, wherever the target came from, and starts drawing the throw and blast
+    pub fn throw_at(&mut self, target: Coordinate)
+    {
+        let turn = self.entity_system.fetch::<GameClock>().turn;
+        let (flight, blast) = player_throw(&mut self.entity_system, target);
+
+        if !flight.is_empty()
+        {
+            self.entity_system.write_resource::<GameClock>().advance();
+            self.projectile = Some(ProjectileAnimation { path: flight, blast, blast_color: RGB::named(rltk::ORANGE), frame: 0 });
+        }
+        self.record_input(turn, InputEvent::ThrowAt(target));
+    }
+
+    /// Sprays the player's extinguisher at a tile, wherever the target came from, and starts drawing the foam
+    pub fn spray_at(&mut self, target: Coordinate)
+    {
+        let turn = self.entity_system.fetch::<GameClock>().turn;
+        let (flight, foam) = player_spray(&mut self.entity_system, target);
+
+        if !flight.is_empty()
+        {
+            self.entity_system.write_resource::<GameClock>().advance();
+            self.projectile = Some(ProjectileAnimation { path: flight, blast: foam, blast_color: RGB::named(rltk::WHITE_SMOKE), frame: 0 });
+        }
+        self.record_input(turn, InputEvent::SprayAt(target));
+    }
+
+    /// Logs on to a terminal or switches on a security monitor on or next to the player's tile
+    fn open_terminal(&mut self)
+    {
+        let player = match self.player_coordinate()
+        {
+            Some(player) => player,
+            None => return,
+        };
+        let within_reach = |coordinate: &Coordinate| coordinate.z == player.z && (coordinate.x as i32 - player.x as i32).abs() <= 1
+                                                     && (coordinate.y as i32 - player.y as i32).abs() <= 1;
+        let (terminal, monitor) =
+        {
+            let entities = self.entity_system.entities();
+            let terminals = self.entity_system.read_storage::<Terminal>();
+            let monitors = self.entity_system.read_storage::<SecurityMonitor>();
+            let coordinates = self.entity_system.read_storage::<Coordinate>();
+
+            ((&entities, &terminals, &coordinates).join().find(|(_entity, _terminal, coordinate)| within_reach(coordinate)).map(|(entity, _terminal, _coordinate)| entity),
+             (&entities, &monitors, &coordinates).join().find(|(_entity, _monitor, coordinate)| within_reach(coordinate)).map(|(entity, _monitor, _coordinate)| entity))
+        };
+
+        let dark = |entity: Entity| !is_powered(&self.entity_system.read_storage::<PowerConsumer>(), entity);
+
+        match (terminal, monitor)
+        {
+            (Some(terminal), _) if dark(terminal) => self.entity_system.fetch_mut::<GameLog>().add("The terminal's screen is dark. There is no power."),
+            (None, Some(monitor)) if dark(monitor) => self.entity_system.fetch_mut::<GameLog>().add("The monitor's screens are dark. There is no power."),
+            (Some(terminal), _) =>
+            {
+                self.terminal_command.clear();
+                self.terminal_screen = vec!["Station network terminal. Type help for commands.".to_string()];
+                self.run_state = RunState::Terminal { terminal };
+            }
+            (None, Some(monitor)) =>
+            {
+                let first_camera = self.entity_system.fetch::<StationNetwork>().online_cameras(player.z).first().map(|(camera, _label)| *camera);
+
+                match first_camera
+                {
+                    Some(camera) => self.run_state = RunState::CameraFeed { monitor, camera },
+                    None => self.entity_system.fetch_mut::<GameLog>().add("Every screen shows static. No cameras are online on this deck."),
+                }
+            }
+            (None, None) => self.entity_system.fetch_mut::<GameLog>().add("There is no terminal within reach."),
+        }
+    }
+
+    /// Flicks between the feeds of the cameras online on the monitor's deck with the east and west movement keys,
+    /// until Escape switches the monitor off. Watching takes no time.
+    fn camera_feed_input(&mut self, key_press: Option<KeyPress>, monitor: Entity, camera: Entity) -> RunState
+    {
+        let key_press = match key_press
+        {
+            Some(key_press) => key_press,
+            None => return RunState::CameraFeed { monitor, camera },
+        };
+
+        if key_press.key == VirtualKeyCode::Escape
+        {
+            return RunState::AwaitingInput;
+        }
+
+        let step = match self.key_bindings.action_for(key_press).and_then(|action| action.movement_delta())
+        {
+            Some((delta_x, _delta_y)) if delta_x != 0 => delta_x,
+            _ => return RunState::CameraFeed { monitor, camera },
+        };
+        let deck = self.entity_system.read_storage::<Coordinate>().get(monitor).map(|coordinate| coordinate.z).unwrap_or(0);
+        let cameras: Vec<Entity> = self.entity_system.fetch::<StationNetwork>().online_cameras(deck).iter().map(|(camera, _label)| *camera).collect();
+
+        match cameras.iter().position(|online| *online == camera)
+        {
+            Some(current) => RunState::CameraFeed { monitor, camera: cameras[(current as i32 + step).rem_euclid(cameras.len() as i32) as usize] },
+            None if !cameras.is_empty() => RunState::CameraFeed { monitor, camera: cameras[0] },
+            None => RunState::CameraFeed { monitor, camera },
+        }
+    }
+
+    fn terminal_input(&mut self, key_press: Option<KeyPress>, terminal: Entity) -> RunState
+    {
+        let key_press = match key_press
+        {
+            Some(key_press) => key_press,
+            None => return RunState::Terminal { terminal },
+        };
+
+        match key_press.key
+        {
+            VirtualKeyCode::Escape => return RunState::AwaitingInput,
+            VirtualKeyCode::Back => { self.terminal_command.pop(); }
+            VirtualKeyCode::Return =>
+            {
+                let command = self.terminal_command.trim().to_string();
+                self.terminal_command.clear();
+
+                if command == "exit"
+                {
+                    return RunState::AwaitingInput;
+                }
+
+                let position = self.entity_system.read_storage::<Coordinate>().get(terminal).copied();
+                if let Some(position) = position.filter(|_position| !command.is_empty())
+                {
+                    self.terminal_command_at(position, command);
+                }
+            }
+            _ =>
+            {
+                if let Some(typed) = key_press.typed_char().filter(|_typed| self.terminal_command.len() < MAX_TERMINAL_COMMAND_LENGTH)
+                {
+                    self.terminal_command.push(typed);
+                }
+            }
+        }
+
+        RunState::Terminal { terminal }
+    }
+
+    /// Runs a command at the terminal standing on a tile, wherever the command came from, printing what it gives
+    /// back on the terminal screen
+    pub fn terminal_command_at(&mut self, position: Coordinate, command: String)
+    {
+        let turn = self.entity_system.fetch::<GameClock>().turn;
+        let terminal =
+        {
+            let entities = self.entity_system.entities();
+            let terminals = self.entity_system.read_storage::<Terminal>();
+            let coordinates = self.entity_system.read_storage::<Coordinate>();
+
+            (&entities, &terminals, &coordinates).join().find(|(_entity, _terminal, coordinate)| **coordinate == position).map(|(entity, _terminal, _coordinate)| entity)
+        };
+
+        if let Some(terminal) = terminal
+        {
+            let (lines, took_turn) = run_command(&mut self.entity_system, terminal, &command);
+
+            if took_turn
+            {
+                self.entity_system.write_resource::<GameClock>().advance();
+            }
+            self.terminal_screen.push(format!("> {}", command));
+            self.terminal_screen.extend(lines);
+        }
+        self.record_input(turn, InputEvent::TerminalCommand(position, command));
+    }
+
+    fn character_creation_input(&mut self, key_press: Option<KeyPress>, selected: usize, naming: bool) -> RunState
+    {
+        let key_press = match key_press
+        {
+            Some(key_press) => key_press,
+            None => return RunState::CharacterCreation { selected, naming },
+        };
+
+        if naming
+        {
+            match key_press.key
+            {
+                VirtualKeyCode::Return if !self.character_name.trim().is_empty() => return RunState::CharacterCreation { selected, naming: false },
+                VirtualKeyCode::Back => { self.character_name.pop(); }
+                _ =>
+                {
+                    if let Some(typed) = key_press.typed_char().filter(|_typed| self.character_name.len() < MAX_NAME_LENGTH)
+                    {
+                        self.character_name.push(typed);
+                    }
+                }
+            }
+            return RunState::CharacterCreation { selected, naming };
+        }
+
+        let count = self.backgrounds.len();
+
+        match key_press.key
+        {
+            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 => RunState::CharacterCreation { selected: (selected + count - 1) % count, naming },
+            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 => RunState::CharacterCreation { selected: (selected + 1) % count, naming },
+            VirtualKeyCode::Escape | VirtualKeyCode::Back => RunState::CharacterCreation { selected, naming: true },
+            VirtualKeyCode::Return =>
+            {
+                let character = Character { name: self.character_name.trim().to_string(), background: self.backgrounds[selected].clone() };
+                self.create_character(character);
+                self.run_state
+            }
+            _ => RunState::CharacterCreation { selected, naming },
+        }
+    }
+
+    fn key_binding_menu_input(&mut self, key_press: Option<KeyPress>, selected: usize, capturing: bool) -> RunState
+    {
+        let key_press = match key_press
+        {
+            Some(key_press) => key_press,
+            None => return RunState::KeyBindingMenu { selected, capturing },
+        };
+        let selected_action = Action::ALL[selected];
+
+        if capturing
+        {
+            if key_press.key != VirtualKeyCode::Escape
+            {
+                self.key_bindings.bind(key_press, selected_action);
+            }
+            return RunState::KeyBindingMenu { selected, capturing: false };
+        }
+
+        match key_press.key
+        {
+            VirtualKeyCode::Up => RunState::KeyBindingMenu { selected: (selected + Action::ALL.len() - 1) % Action::ALL.len(), capturing },
+            VirtualKeyCode::Down => RunState::KeyBindingMenu { selected: (selected + 1) % Action::ALL.len(), capturing },
+            VirtualKeyCode::Return => RunState::KeyBindingMenu { selected, capturing: true },
+            VirtualKeyCode::Delete | VirtualKeyCode::Back =>
+            {
+                self.key_bindings.unbind_action(selected_action);
+                RunState::KeyBindingMenu { selected, capturing }
+            }
+            VirtualKeyCode::N | VirtualKeyCode::V | VirtualKeyCode::W =>
+            {
+                let preset = match key_press.key
+                {
+                    VirtualKeyCode::N => Preset::Numpad,
+                    VirtualKeyCode::V => Preset::Vi,
+                    _ => Preset::Wasd,
+                };
+                self.key_bindings = KeyBindings::preset(preset);
+                RunState::KeyBindingMenu { selected, capturing }
+            }
+            VirtualKeyCode::Escape =>
+            {
+                match self.key_bindings.save(&self.key_bindings_path)
+                {
+                    Ok(()) => self.entity_system.fetch_mut::<GameLog>().add(format!("Key bindings saved to {}.", self.key_bindings_path)),
+                    Err(error) => self.entity_system.fetch_mut::<GameLog>().add(format!("Could not save key bindings: {}", error)),
+                }
+                RunState::AwaitingInput
+            }
+            _ => RunState::KeyBindingMenu { selected, capturing },
+        }
+    }
+
+    fn record_input(&mut self, turn: u64, event: InputEvent)
+    {
+        if let Some(recorder) = &mut self.recorder
+        {
+            let input = RecordedInput { turn, event, world_hash: world_hash(&self.entity_system) };
+
+            if let Err(error) = recorder.record(input)
+            {
+                eprintln!("stopped recording input: {}", error);
+                self.recorder = None;
+            }
+        }
+    }
+
+    pub fn player_coordinate(&self) -> Option<Coordinate>
+    {
+        let coordinates = self.entity_system.read_storage::<Coordinate>();
+        let players = self.entity_system.read_storage::<Player>();
+
+        (&players, &coordinates).join().map(|(_player, coordinate)| *coordinate).next()
+    }
+
+    pub fn render(&self, context: &mut Rltk)
+    {
+        if let RunState::CharacterCreation { selected, naming } = self.run_state
+        {
+            gui::draw_character_creation(context, &self.character_name, naming, &self.backgrounds, selected);
+            return;
+        }
+
+        let map_view_height = self.screen_height.saturating_sub(gui::HUD_HEIGHT);
+        let hover_path = self.hover_path();
+        let coordinates = self.entity_system.read_storage::<Coordinate>();
+        let renderables = self.entity_system.read_storage::<Renderable>();
+        let players = self.entity_system.read_storage::<Player>();
+        let viewsheds = self.entity_system.read_storage::<Viewshed>();
+        let memories = self.entity_system.read_storage::<MapMemory>();
+        let map = self.entity_system.fetch::<Map>();
+        let network = self.entity_system.fetch::<StationNetwork>();
+
+        // Everything on screen is drawn from the player's point of view, or from a camera's while watching its feed
+        for (_player, coordinate, viewshed, memory) in (&players, &coordinates, &viewsheds, &memories).join()
+        {
+            let (coordinate, viewshed, memory) = match self.run_state
+            {
+                RunState::CameraFeed { camera, .. } if network.is_online(camera) => match (coordinates.get(camera), viewsheds.get(camera), memories.get(camera))
+                {
+                    (Some(feed_coordinate), Some(feed_viewshed), Some(feed_memory)) => (feed_coordinate, feed_viewshed, feed_memory),
+                    _ => (coordinate, viewshed, memory),
+                },
+                _ => (coordinate, viewshed, memory),
+            };
+            let camera = Camera::centered_on(*coordinate, self.screen_width, map_view_height, self.map_size);
+            let visible_tiles: HashSet<Coordinate> = viewshed.visible_tiles.iter().copied().collect();
+
+            map.draw(context, &camera, memory, &visible_tiles, self.overlay);
+
+            for step in hover_path.iter()
+            {
+                if let Some((screen_x, screen_y)) = camera.to_screen(*step)
+                {
+                    context.set_bg(screen_x, screen_y, RGB::from_f32(0.0, 0.15, 0.35));
+                }
+            }
+
+            let mut drawn_entities: Vec<(&Coordinate, &Renderable)> = (&coordinates, &renderables).join().collect();
+            drawn_entities.sort_by_key(|(_coordinate, renderable)| -renderable.render_order);
+
+            // Entities one deck down are only seen through the transparent floor above them
+            for (coordinate, renderable) in drawn_entities.iter()
+            {
+                let above = Coordinate::new(coordinate.x, coordinate.y, camera.origin.z);
+
+                if coordinate.z == camera.origin.z + 1 && visible_tiles.contains(coordinate) && visible_tiles.contains(&above)
+                {
+                    if let Some((screen_x, screen_y)) = camera.to_screen(above)
+                    {
+                        context.set(screen_x, screen_y, renderable.foreground_color * BELOW_DECK_DIMMING, renderable.background_color, renderable.glyph);
+                    }
+                }
+            }
+
+            for (coordinate, renderable) in drawn_entities.iter()
+            {
+                if visible_tiles.contains(coordinate)
+                {
+                    if let Some((screen_x, screen_y)) = camera.to_screen(**coordinate)
+                    {
+                        context.set(screen_x, screen_y, renderable.foreground_color, renderable.background_color, renderable.glyph);
+                    }
+                }
+            }
+        }
+
+        drop((map, network));
+        gui::draw_hud(&self.entity_system, context, self.screen_width, self.screen_height);
+
+        if self.run_state == RunState::CharacterSheet
+        {
+            gui::draw_character_sheet(&self.entity_system, context);
+        }
+
+        if let RunState::KeyBindingMenu { selected, capturing } = self.run_state
+        {
+            gui::draw_key_binding_menu(context, &self.key_bindings, selected, capturing);
+        }
+
+        if let RunState::ContextMenu { target, screen_x, screen_y } = self.run_state
+        {
+            let options = gui::context_menu_options(&self.entity_system, target);
+            let area = gui::context_menu_area(&options, screen_x, screen_y, self.screen_width, self.screen_height);
+            let (mouse_x, mouse_y) = self.mouse.map(|mouse| (mouse.screen_x, mouse.screen_y)).unwrap_or((-1, -1));
+
+            gui::draw_context_menu(context, &options, area, mouse_x, mouse_y);
+        }
+
+        if let RunState::Look { cursor } = self.run_state
+        {
+            if let Some((cursor_x, cursor_y)) = self.camera().and_then(|camera| camera.to_screen(cursor))
+            {
+                gui::draw_look_panel(context, &gui::describe_location(&self.entity_system, cursor), cursor_x, cursor_y, self.screen_width);
+            }
+        }
+
+        if let RunState::Targeting { cursor, purpose } = self.run_state
+        {
+            self.draw_targeting(context, cursor, purpose);
+        }
+
+        if let RunState::CameraFeed { monitor, camera } = self.run_state
+        {
+            let deck = self.entity_system.read_storage::<Coordinate>().get(monitor).map(|coordinate| coordinate.z).unwrap_or(0);
+            let network = self.entity_system.fetch::<StationNetwork>();
+            let cameras = network.online_cameras(deck);
+
+            match cameras.iter().position(|(online, _label)| *online == camera)
+            {
+                Some(current) =>
+                {
+                    let title = format!(" {} ({} of {}) ", cameras[current].1, current + 1, cameras.len());
+                    gui::draw_camera_feed_caption(context, &title, self.screen_width);
+                }
+                None => gui::draw_camera_feed_caption(context, " No signal ", self.screen_width),
+            }
+        }
+
+        if let RunState::Terminal { .. } = self.run_state
+        {
+            gui::draw_terminal(context, &self.terminal_screen, &self.terminal_command, self.screen_width, self.screen_height);
+        }
+
+        if let (Some(projectile), Some(camera)) = (&self.projectile, self.camera())
+        {
+            let step = projectile.frame / FRAMES_PER_PROJECTILE_STEP;
+
+            match projectile.path.get(step).and_then(|step| camera.to_screen(*step))
+            {
+                Some((screen_x, screen_y)) => context.set(screen_x, screen_y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), rltk::to_cp437('*')),
+                None if step >= projectile.path.len() =>
+                {
+                    for (screen_x, screen_y) in projectile.blast.iter().filter_map(|tile| camera.to_screen(*tile))
+                    {
+                        context.set_bg(screen_x, screen_y, projectile.blast_color);
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Shows where a shot at the cursor would fly and how likely it is to hit, or what a throw's blast or a spray
+    /// would cover
+    fn draw_targeting(&self, context: &mut Rltk, cursor: Coordinate, purpose: TargetPurpose)
+    {
+        let (camera, from) = match (self.camera(), self.player_coordinate())
+        {
+            (Some(camera), Some(from)) => (camera, from),
+            _ => return,
+        };
+        let map = self.entity_system.fetch::<Map>();
+
+        if purpose != TargetPurpose::Fire
+        {
+            let area = match purpose
+            {
+                TargetPurpose::Spray => spray_area(landing_point(&map, from, cursor, SPRAY_RANGE)),
+                _ =>
+                {
+                    let radius = player_explosive(&self.entity_system).and_then(|explosive| self.entity_system.read_storage::<Explosive>().get(explosive).map(|explosive| explosive.radius))
+                                                                      .unwrap_or(0);
+                    blast_area(&map, landing_point(&map, from, cursor, throw_range(&player_attributes(&self.entity_system))), radius)
+                }
+            };
+
+            for (screen_x, screen_y) in area.iter().filter_map(|tile| camera.to_screen(*tile))
+            {
+                context.set_bg(screen_x, screen_y, RGB::from_f32(0.35, 0.2, 0.0));
+            }
+            if let Some((cursor_x, cursor_y)) = camera.to_screen(cursor)
+            {
+                gui::draw_look_panel(context, &gui::describe_location(&self.entity_system, cursor), cursor_x, cursor_y, self.screen_width);
+            }
+            return;
+        }
+
+        let range = player_weapon(&self.entity_system).and_then(|(weapon, _magazine)| self.entity_system.read_storage::<RangedWeapon>().get(weapon).map(|weapon| weapon.range))
+                                                     .unwrap_or(0);
+
+        for step in projectile_path(from, cursor, range, map.map_size)
+        {
+            if let Some((screen_x, screen_y)) = camera.to_screen(step)
+            {
+                context.set_bg(screen_x, screen_y, RGB::from_f32(0.35, 0.1, 0.1));
+            }
+            if map.get(step).map(|tile| !tile.holds_atmosphere()).unwrap_or(true)
+            {
+                break;
+            }
+        }
+
+        if let Some((cursor_x, cursor_y)) = camera.to_screen(cursor)
+        {
+            let mut lines = gui::describe_location(&self.entity_system, cursor);
+
+            if cursor != from
+            {
+                lines.push(format!("Hit chance {}%", hit_chance(&map, from, cursor, player_attributes(&self.entity_system).hit_bonus() + player_skill_bonus(&self.entity_system, Skill::Firearms))));
+            }
+            gui::draw_look_panel(context, &lines, cursor_x, cursor_y, self.screen_width);
+        }
+    }
+
+    /// Moves the shot being drawn along, dropping it once it has reached the end of its path
+    fn advance_projectile(&mut self)
+    {
+        if let Some(projectile) = &mut self.projectile
+        {
+            projectile.frame += 1;
+
+            let blast_frames = if projectile.blast.is_empty() { 0 } else { BLAST_FRAMES };
+
+            if projectile.frame >= projectile.path.len() * FRAMES_PER_PROJECTILE_STEP + blast_frames
+            {
+                self.projectile = None;
+            }
+        }
+    }
+
+    /// The way the player would travel to the tile under the mouse, shown while nothing else is going on
+    fn hover_path(&self) -> Vec<Coordinate>
+    {
+        if self.run_state != RunState::AwaitingInput || !self.travel_path.is_empty() || self.exploring
+        {
+            return Vec::new();
+        }
+
+        self.mouse.and_then(|mouse| self.camera().and_then(|camera| camera.to_map(mouse.screen_x, mouse.screen_y)))
+                  .and_then(|target| self.path_for_player(target))
+                  .unwrap_or_default()
+    }
+}
+
+impl GameState for State
+{
+    fn tick(&mut self, context : &mut Rltk)
+    {
+        context.cls();
+
+        let key_press = context.key.map(|key| KeyPress { key, shift: context.shift });
+        let (left_down, right_down) =
+        {
+            let input = rltk::INPUT.lock();
+            (input.is_mouse_button_pressed(0), input.is_mouse_button_pressed(1))
+        };
+        let (mouse_x, mouse_y) = context.mouse_pos();
+
+        // Only the frame a button goes down counts as a click
+        let mouse = MouseInput
+        {
+            screen_x: mouse_x,
+            screen_y: mouse_y,
+            left_click: left_down && !self.mouse_buttons_down.0,
+            right_click: right_down && !self.mouse_buttons_down.1,
+        };
+        self.mouse_buttons_down = (left_down, right_down);
+
+        self.update(key_press, Some(mouse));
+        self.render(context);
+        self.advance_projectile();
+    }
+}
+
+fn register_components(game_state: &mut State)
+{
+    game_state.entity_system.register::<Coordinate>();
+    game_state.entity_system.register::<Renderable>();
+    game_state.entity_system.register::<Player>();
+    game_state.entity_system.register::<Viewshed>();
+    game_state.entity_system.register::<MapMemory>();
+    game_state.entity_system.register::<SharedMemory>();
+    game_state.entity_system.register::<FactionMember>();
+    game_state.entity_system.register::<Brain>();
+    game_state.entity_system.register::<MeleeAttack>();
+    game_state.entity_system.register::<Sneaking>();
+    game_state.entity_system.register::<Terminal>();
+    game_state.entity_system.register::<SecurityCamera>();
+    game_state.entity_system.register::<SecurityMonitor>();
+    game_state.entity_system.register::<Turret>();
+    game_state.entity_system.register::<Generator>();
+    game_state.entity_system.register::<Battery>();
+    game_state.entity_system.register::<PowerConsumer>();
+    game_state.entity_system.register::<LifeSupport>();
+    game_state.entity_system.register::<Velocity>();
+    game_state.entity_system.register::<Name>();
+    game_state.entity_system.register::<Item>();
+    game_state.entity_system.register::<InBackpack>();
+    game_state.entity_system.register::<CombatStats>();
+    game_state.entity_system.register::<SufferDamage>();
+    game_state.entity_system.register::<RangedWeapon>();
+    game_state.entity_system.register::<Ammunition>();
+    game_state.entity_system.register::<Explosive>();
+    game_state.entity_system.register::<Flammable>();
+    game_state.entity_system.register::<Extinguisher>();
+    game_state.entity_system.register::<Breathes>();
+    game_state.entity_system.register::<StatusEffects>();
+    game_state.entity_system.register::<Needs>();
+    game_state.entity_system.register::<Consumable>();
+    game_state.entity_system.register::<Attributes>();
+    game_state.entity_system.register::<Skills>();
+    game_state.entity_system.register::<CharacterBackground>();
+}
+
+/// The number on a top row or numpad digit key, for picking menu entries
+fn menu_digit(key: VirtualKeyCode) -> Option<usize>
+{
+    const DIGIT_KEYS: [(VirtualKeyCode, VirtualKeyCode); 9] =
+    [
+        (VirtualKeyCode::Key1, VirtualKeyCode::Numpad1), (VirtualKeyCode::Key2, VirtualKeyCode::Numpad2),
+        (VirtualKeyCode::Key3, VirtualKeyCode::Numpad3), (VirtualKeyCode::Key4, VirtualKeyCode::Numpad4),
+        (VirtualKeyCode::Key5, VirtualKeyCode::Numpad5), (VirtualKeyCode::Key6, VirtualKeyCode::Numpad6),
+        (VirtualKeyCode::Key7, VirtualKeyCode::Numpad7), (VirtualKeyCode::Key8, VirtualKeyCode::Numpad8),
+        (VirtualKeyCode::Key9, VirtualKeyCode::Numpad9),
+    ];
+
+    DIGIT_KEYS.iter().position(|(top_row, numpad)| key == *top_row || key == *numpad).map(|index| index + 1)
+}
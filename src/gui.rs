@@ -0,0 +1,502 @@
+use rltk::{Rltk, RGB};
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::entity_components::{Player, Viewshed, Name, Item, CombatStats, Ammunition, InBackpack};
+use super::map::Map;
+use super::map_memory::MapMemory;
+use super::game_clock::GameClock;
+use super::game_log::GameLog;
+use super::key_bindings::{Action, KeyBindings};
+use super::map_tile::Ladder;
+use super::status_effects::StatusEffects;
+use super::survival::{Needs, MAX_SUIT_OXYGEN};
+use super::character::{Attributes, Background, CharacterBackground};
+use super::skills::{Skill, Skills, MAX_SKILL_LEVEL};
+use super::factions::{Faction, Relations};
+use super::noise::Sneaking;
+use super::life_support::{LifeSupport, MAX_CONDITION};
+
+
+/// Rows at the bottom of the screen taken by the status panel and message log
+pub const HUD_HEIGHT: usize = 7;
+
+pub fn draw_hud(entity_system: &World, context: &mut Rltk, screen_width: usize, screen_height: usize)
+{
+    let top = screen_height.saturating_sub(HUD_HEIGHT);
+    let white = RGB::named(rltk::WHITE);
+    let black = RGB::named(rltk::BLACK);
+
+    context.draw_box(0, top, screen_width.saturating_sub(1), HUD_HEIGHT - 1, white, black);
+
+    let turn = entity_system.fetch::<GameClock>().turn;
+    let entities = entity_system.entities();
+    let players = entity_system.read_storage::<Player>();
+    let coordinates = entity_system.read_storage::<Coordinate>();
+    let combat_stats = entity_system.read_storage::<CombatStats>();
+    let ammunition = entity_system.read_storage::<Ammunition>();
+    let backpacks = entity_system.read_storage::<InBackpack>();
+    let status_effects = entity_system.read_storage::<StatusEffects>();
+    let needs = entity_system.read_storage::<Needs>();
+    let sneaking = entity_system.read_storage::<Sneaking>();
+    let map = entity_system.fetch::<Map>();
+
+    for (player, _player, coordinate, stats) in (&entities, &players, &coordinates, &combat_stats).join()
+    {
+        let rounds: i32 = (&ammunition, &backpacks).join()
+                                                   .filter(|(_ammunition, backpack)| backpack.owner == player)
+                                                   .map(|(ammunition, _backpack)| ammunition.rounds)
+                                                   .sum();
+        let status = format!(" Deck {}  Turn {}  HP {}/{}  Rounds {} ", coordinate.z + 1, turn, stats.hp.max(0), stats.max_hp, rounds);
+
+        context.print_color(2, top, RGB::named(rltk::YELLOW), black, &status);
+
+        // Running status effects follow the status line along the top border, each in its own colour
+        let mut x = 2 + status.len() + 1;
+        for effect in status_effects.get(player).map(|effects| effects.effects.as_slice()).unwrap_or(&[])
+        {
+            let label = match effect.stacks > 1
+            {
+                true => format!(" {} x{} ({}) ", effect.kind.name(), effect.stacks, effect.turns_left),
+                false => format!(" {} ({}) ", effect.kind.name(), effect.turns_left),
+            };
+
+            if x + label.len() >= screen_width
+            {
+                break;
+            }
+            context.print_color(x, top, effect.kind.color(), black, &label);
+            x += label.len() + 1;
+        }
+
+        // Survival needs run along the bottom border
+        if let Some(needs) = needs.get(player)
+        {
+            let meters = [(format!(" Suit O2 {}/{} ", needs.suit_oxygen, MAX_SUIT_OXYGEN), needs.oxygen_level()),
+                          (format!(" {} ", needs.food_label()), needs.food_level()),
+                          (format!(" {} ", needs.water_label()), needs.water_level()),
+                          (format!(" {} ", needs.rest_label()), needs.rest_level())];
+            let mut x = 2;
+
+            for (label, level) in meters.iter()
+            {
+                context.print_color(x, top + HUD_HEIGHT - 1, level.color(), black, label);
+                x += label.len() + 1;
+            }
+        }
+
+        // Sneaking shows at the right end of the bottom border, along with whether the shadows hide the player
+        if sneaking.get(player).is_some()
+        {
+            let label = match map.is_dim(*coordinate)
+            {
+                true => " Sneaking in shadow ",
+                false => " Sneaking ",
+            };
+            context.print_color(screen_width.saturating_sub(label.len() + 2), top + HUD_HEIGHT - 1, RGB::named(rltk::GRAY), black, label);
+        }
+    }
+
+    let log = entity_system.fetch::<GameLog>();
+    for (row, message) in log.entries.iter().rev().take(HUD_HEIGHT - 2).enumerate()
+    {
+        context.print(2, top + 1 + row, message);
+    }
+}
+
+/// Lists every action with its keys, highlighting the selected one
+pub fn draw_key_binding_menu(context: &mut Rltk, key_bindings: &KeyBindings, selected: usize, capturing: bool)
+{
+    let white = RGB::named(rltk::WHITE);
+    let black = RGB::named(rltk::BLACK);
+    let width = 60;
+    let height = Action::ALL.len() + 5;
+    let (left, top) = (2, 2);
+
+    context.draw_box(left, top, width, height, white, black);
+    context.print_color(left + 2, top, RGB::named(rltk::YELLOW), black, " Key bindings ");
+
+    for (row, action) in Action::ALL.iter().enumerate()
+    {
+        let keys: Vec<String> = key_bindings.keys_for(*action).iter().map(|key_press| key_press.name()).collect();
+        let background = if row == selected { RGB::named(rltk::DARK_BLUE) } else { black };
+        let line = format!("{:<18} {}", action.name(), keys.join(", "));
+
+        context.print_color(left + 2, top + 2 + row, white, background, line);
+    }
+
+    let help = match capturing
+    {
+        true => "Press the key to bind, Escape to cancel",
+        false => "Up/Down select, Enter bind, Delete clear, N/V/W preset, Escape save",
+    };
+    context.print_color(left + 2, top + height, RGB::named(rltk::GRAY), black, help);
+}
+
+/// The character creation screen: the name being typed, then the backgrounds with what the selected one brings
+pub fn draw_character_creation(context: &mut Rltk, name: &str, naming: bool, backgrounds: &[Background], selected: usize)
+{
+    let white = RGB::named(rltk::WHITE);
+    let black = RGB::named(rltk::BLACK);
+    let gray = RGB::named(rltk::GRAY);
+    let width = 70;
+    let height = backgrounds.len() + 16;
+    let (left, top) = (2, 2);
+
+    context.draw_box(left, top, width, height, white, black);
+    context.print_color(left + 2, top, RGB::named(rltk::YELLOW), black, " New character ");
+
+    let cursor = if naming { "_" } else { "" };
+    context.print_color(left + 2, top + 2, if naming { white } else { gray }, black, format!("Name: {}{}", name, cursor));
+
+    for (row, background) in backgrounds.iter().enumerate()
+    {
+        let highlight = if row == selected && !naming { RGB::named(rltk::DARK_BLUE) } else { black };
+        context.print_color(left + 2, top + 4 + row, if naming { gray } else { white }, highlight, &background.title);
+    }
+
+    if let Some(background) = backgrounds.get(selected)
+    {
+        let mut row = top + 5 + backgrounds.len();
+        let mut line = String::new();
+
+        // Wrap the description on word boundaries to fit inside the box
+        for word in background.description.split_whitespace()
+        {
+            if !line.is_empty() && line.len() + word.len() + 1 > width - 4
+            {
+                context.print(left + 2, row, &line);
+                row += 1;
+                line.clear();
+            }
+            if !line.is_empty()
+            {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        context.print(left + 2, row, &line);
+
+        let attributes: Vec<String> = Attributes::NAMES.iter()
+                                                       .map(|attribute| format!("{} {}", capitalise(attribute), background.attributes.get(attribute).unwrap_or(0)))
+                                                       .collect();
+        context.print_color(left + 2, row + 2, RGB::named(rltk::CYAN), black, format!("{}  HP {}", attributes.join("  "), background.attributes.max_hp()));
+
+        let skills: Vec<String> = Skill::ALL.iter()
+                                            .filter(|skill| background.skills.level(**skill) > 0)
+                                            .map(|skill| format!("{} {}", skill.title(), background.skills.level(*skill)))
+                                            .collect();
+        context.print(left + 2, row + 3, format!("Skills: {}", skills.join(", ")));
+
+        let kit: Vec<String> = background.kit.iter().map(|item| item.replace('_', " ")).collect();
+        context.print(left + 2, row + 4, format!("Kit: {}", kit.join(", ")));
+        context.print_color(left + 2, row + 5, background.faction.color(), black, format!("Faction: {}", background.faction.title()));
+    }
+
+    let help = match naming
+    {
+        true => "Type a name, Enter to go on",
+        false => "Up/Down select, Enter start, Escape back to the name",
+    };
+    context.print_color(left + 2, top + height, gray, black, help);
+}
+
+/// Names the camera whose feed fills the map view, with the keys for flicking between feeds
+pub fn draw_camera_feed_caption(context: &mut Rltk, title: &str, screen_width: usize)
+{
+    let black = RGB::named(rltk::BLACK);
+    let help = " Left/Right to switch cameras, Escape to stop watching ";
+
+    context.print_color(1, 0, RGB::named(rltk::LIGHT_BLUE), black, title);
+    context.print_color(screen_width.saturating_sub(help.len() + 1), 0, RGB::named(rltk::GRAY), black, help);
+}
+
+/// A terminal's screen, showing as much of what it has printed as fits above the command being typed
+pub fn draw_terminal(context: &mut Rltk, screen: &[String], command: &str, screen_width: usize, screen_height: usize)
+{
+    let green = RGB::named(rltk::GREEN);
+    let black = RGB::named(rltk::BLACK);
+    let (left, top) = (2, 2);
+    let width = screen_width.saturating_sub(6).min(70);
+    let height = screen_height.saturating_sub(HUD_HEIGHT + 4).max(6);
+    let rows = height - 3;
+
+    context.draw_box(left, top, width, height, green, black);
+    context.print_color(left + 2, top, RGB::named(rltk::YELLOW), black, " Terminal ");
+
+    for (row, line) in screen.iter().skip(screen.len().saturating_sub(rows)).enumerate()
+    {
+        context.print_color(left + 2, top + 1 + row, green, black, line.chars().take(width - 3).collect::<String>());
+    }
+
+    context.print_color(left + 2, top + height - 1, RGB::named(rltk::WHITE), black, format!("> {}_", command));
+    context.print_color(left + 2, top + height, RGB::named(rltk::GRAY), black, "Enter to run, Escape to log off");
+}
+
+/// The player's name, background, attributes and how far along each skill is
+pub fn draw_character_sheet(entity_system: &World, context: &mut Rltk)
+{
+    let white = RGB::named(rltk::WHITE);
+    let black = RGB::named(rltk::BLACK);
+    let width = 50;
+    let height = Attributes::NAMES.len() + Skill::ALL.len() + Faction::ALL.len() + 11;
+    let (left, top) = (2, 2);
+
+    context.draw_box(left, top, width, height, white, black);
+    context.print_color(left + 2, top, RGB::named(rltk::YELLOW), black, " Character ");
+
+    let players = entity_system.read_storage::<Player>();
+    let names = entity_system.read_storage::<Name>();
+    let backgrounds = entity_system.read_storage::<CharacterBackground>();
+    let combat_stats = entity_system.read_storage::<CombatStats>();
+    let attributes = entity_system.read_storage::<Attributes>();
+    let skills = entity_system.read_storage::<Skills>();
+
+    for (_player, name, background, stats, attributes, skills) in (&players, &names, backgrounds.maybe(), &combat_stats, &attributes, &skills).join()
+    {
+        let title = background.map(|background| background.title.as_str()).unwrap_or("Drifter");
+        context.print(left + 2, top + 2, format!("{}, {}", name.name, title));
+        context.print(left + 2, top + 3, format!("HP {}/{}", stats.hp.max(0), stats.max_hp));
+
+        let mut row = top + 5;
+        for attribute in Attributes::NAMES.iter()
+        {
+            context.print_color(left + 2, row, RGB::named(rltk::CYAN), black, format!("{:<12}{:>2}", capitalise(attribute), attributes.get(attribute).unwrap_or(0)));
+            row += 1;
+        }
+
+        row += 1;
+        for skill in Skill::ALL.iter()
+        {
+            let progress = skills.progress(*skill);
+            let needed = Skills::experience_to_next(progress.level);
+            let filled = match progress.level >= MAX_SKILL_LEVEL
+            {
+                true => 10,
+                false => (progress.experience * 10 / needed) as usize,
+            };
+            let bar = format!("[{}{}]", "#".repeat(filled), ".".repeat(10 - filled));
+
+            context.print(left + 2, row, format!("{:<12}{:>2}  {}  {}/{} xp", skill.title(), progress.level, bar, progress.experience, needed));
+            row += 1;
+        }
+
+        row += 1;
+        let relations = entity_system.fetch::<Relations>();
+        for faction in Faction::ALL.iter()
+        {
+            let label = relations.reputation_label(*faction);
+            let color = match label
+            {
+                "Hostile" => RGB::named(rltk::RED),
+                "Friendly" => RGB::named(rltk::GREEN),
+                _ => RGB::named(rltk::GRAY),
+            };
+
+            context.print_color(left + 2, row, color, black, format!("{:<14}{:>4}  {}", faction.title(), relations.reputation(*faction), label));
+            row += 1;
+        }
+    }
+
+    context.print_color(left + 2, top + height, RGB::named(rltk::GRAY), black, "Press any key to close");
+}
+
+/// Something that can be done from the right click menu
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContextAction
+{
+    Examine(Entity),
+    PickUp,
+    Climb(Ladder),
+    TravelHere,
+}
+
+/// Menu entries for a tile, covering every entity on it that the player can see and any ladder the player stands on
+pub fn context_menu_options(entity_system: &World, target: Coordinate) -> Vec<(String, ContextAction)>
+{
+    let entities = entity_system.entities();
+    let players = entity_system.read_storage::<Player>();
+    let viewsheds = entity_system.read_storage::<Viewshed>();
+    let coordinates = entity_system.read_storage::<Coordinate>();
+    let names = entity_system.read_storage::<Name>();
+    let items = entity_system.read_storage::<Item>();
+    let map = entity_system.fetch::<Map>();
+    let mut options: Vec<(String, ContextAction)> = Vec::new();
+
+    for (_player, player_coordinate, viewshed) in (&players, &coordinates, &viewsheds).join()
+    {
+        if !viewshed.visible_tiles.contains(&target)
+        {
+            continue;
+        }
+
+        for (entity, coordinate, name) in (&entities, &coordinates, &names).join()
+        {
+            if *coordinate != target || players.get(entity).is_some()
+            {
+                continue;
+            }
+
+            options.push((format!("Examine {}", name.name), ContextAction::Examine(entity)));
+
+            if items.get(entity).is_some() && *player_coordinate == target
+            {
+                options.push((format!("Pick up {}", name.name), ContextAction::PickUp));
+            }
+        }
+
+        match map.get(target).and_then(|tile| tile.ladder).filter(|_ladder| *player_coordinate == target)
+        {
+            Some(Ladder::Down) => options.push(("Climb down".to_string(), ContextAction::Climb(Ladder::Down))),
+            Some(Ladder::Up) => options.push(("Climb up".to_string(), ContextAction::Climb(Ladder::Up))),
+            None => {}
+        }
+
+        if *player_coordinate != target
+        {
+            options.push(("Travel here".to_string(), ContextAction::TravelHere));
+        }
+    }
+
+    options
+}
+
+/// Screen area of the right click menu as left, top and width, kept on screen
+pub fn context_menu_area(options: &[(String, ContextAction)], screen_x: i32, screen_y: i32, screen_width: usize, screen_height: usize) -> (usize, usize, usize)
+{
+    let width = options.iter().map(|(label, _action)| label.len()).max().unwrap_or(0) + 4;
+    let left = (screen_x.max(0) as usize + 1).min(screen_width.saturating_sub(width + 1));
+    let top = (screen_y.max(0) as usize).min(screen_height.saturating_sub(options.len() + 2));
+
+    (left, top, width)
+}
+
+/// Which menu entry, if any, is under a screen position
+pub fn context_menu_entry_at(options: &[(String, ContextAction)], area: (usize, usize, usize), screen_x: i32, screen_y: i32) -> Option<usize>
+{
+    let (left, top, width) = area;
+    let row = screen_y - top as i32 - 1;
+
+    if screen_x > left as i32 && screen_x < (left + width) as i32 && row >= 0 && (row as usize) < options.len()
+    {
+        Some(row as usize)
+    }
+    else
+    {
+        None
+    }
+}
+
+pub fn draw_context_menu(context: &mut Rltk, options: &[(String, ContextAction)], area: (usize, usize, usize), mouse_x: i32, mouse_y: i32)
+{
+    let (left, top, width) = area;
+    let white = RGB::named(rltk::WHITE);
+    let black = RGB::named(rltk::BLACK);
+    let hovered = context_menu_entry_at(options, area, mouse_x, mouse_y);
+
+    context.draw_box(left, top, width, options.len() + 1, white, black);
+
+    for (row, (label, _action)) in options.iter().enumerate()
+    {
+        let background = if hovered == Some(row) { RGB::named(rltk::DARK_BLUE) } else { black };
+        context.print_color(left + 1, top + 1 + row, white, background, format!("{}.{}", row + 1, label));
+    }
+}
+
+/// What the player knows about a tile: its type, whether it is in view or only remembered, the gas in it
+/// when in view, and whatever is there or was last seen there
+pub fn describe_location(entity_system: &World, target: Coordinate) -> Vec<String>
+{
+    let entities = entity_system.entities();
+    let players = entity_system.read_storage::<Player>();
+    let viewsheds = entity_system.read_storage::<Viewshed>();
+    let memories = entity_system.read_storage::<MapMemory>();
+    let coordinates = entity_system.read_storage::<Coordinate>();
+    let names = entity_system.read_storage::<Name>();
+    let units = entity_system.read_storage::<LifeSupport>();
+    let map = entity_system.fetch::<Map>();
+    let mut lines: Vec<String> = Vec::new();
+
+    for (_player, viewshed, memory) in (&players, &viewsheds, &memories).join()
+    {
+        let visible = viewshed.visible_tiles.contains(&target);
+
+        match (visible, map.get(target), memory.get_tile(target))
+        {
+            (true, Some(tile), _) =>
+            {
+                lines.push(capitalise(tile.name));
+                if tile.holds_atmosphere()
+                {
+                    lines.push(map.atmosphere(target).reading());
+                }
+                if !map.has_gravity(target)
+                {
+                    lines.push("No gravity".to_string());
+                }
+                if map.radiation(target) >= 0.5
+                {
+                    lines.push(format!("Radiation {:.1}", map.radiation(target)));
+                }
+                if map.has_conduit(target)
+                {
+                    lines.push("Power conduit under the deck plating".to_string());
+                }
+            }
+            (true, None, _) => lines.push("Open space".to_string()),
+            (false, _, Some(remembered)) => lines.push(format!("{} (remembered from turn {})", capitalise(remembered.tile.name), remembered.turn)),
+            (false, _, None) => lines.push("Unexplored".to_string()),
+        }
+
+        for (entity, name) in (&entities, &names).join()
+        {
+            if visible && coordinates.get(entity) == Some(&target)
+            {
+                let name = if players.get(entity).is_some() { "You".to_string() } else { name.name.clone() };
+                match units.get(entity).map(|unit| unit.condition)
+                {
+                    Some(0) => lines.push(format!("{} (broken down)", name)),
+                    Some(condition) if condition < MAX_CONDITION => lines.push(format!("{} (worn, {}/{})", name, condition, MAX_CONDITION)),
+                    _ => lines.push(name),
+                }
+            }
+            else if !visible
+            {
+                if let Some(remembered) = memory.entities.get(&entity).filter(|remembered| remembered.coordinate == target)
+                {
+                    lines.push(format!("{} (last seen turn {})", name.name, remembered.turn));
+                }
+            }
+        }
+    }
+
+    lines
+}
+
+fn capitalise(text: &str) -> String
+{
+    let mut characters = text.chars();
+
+    match characters.next()
+    {
+        Some(first) => first.to_uppercase().chain(characters).collect(),
+        None => String::new(),
+    }
+}
+
+/// Highlights the cursor and lists what is under it, in a box on whichever side of the screen the cursor is not
+pub fn draw_look_panel(context: &mut Rltk, lines: &[String], cursor_x: usize, cursor_y: usize, screen_width: usize)
+{
+    let white = RGB::named(rltk::WHITE);
+    let black = RGB::named(rltk::BLACK);
+    let width = lines.iter().map(|line| line.len()).max().unwrap_or(0) + 3;
+    let left = if cursor_x < screen_width / 2 { screen_width.saturating_sub(width + 2) } else { 1 };
+
+    context.set_bg(cursor_x, cursor_y, RGB::named(rltk::MAGENTA));
+    context.draw_box(left, 1, width, lines.len() + 1, white, black);
+
+    for (row, line) in lines.iter().enumerate()
+    {
+        context.print_color(left + 2, 2 + row, white, black, line);
+    }
+}
@@ -0,0 +1,84 @@
+use rand::Rng;
+use rand::rngs::StdRng;
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::game_log::GameLog;
+use super::hazards::*;
+use super::map::Map;
+use super::status_effects::{StatusEffects, StatusKind};
+
+
+/// Runs the heat and radiation of the station for a turn. Reactor cores and cryogenic decks pull the air around them
+/// towards their own temperature, radiation spreads out through whatever shielding is in its way, solar flares come
+/// and go, and anything alive caught out in the cold, the heat or the radiation suffers for it.
+pub struct HazardSystem {}
+
+impl<'a> System<'a> for HazardSystem
+{
+    type SystemData = (WriteExpect<'a, Map>,
+                       WriteExpect<'a, SolarFlare>,
+                       WriteExpect<'a, StdRng>,
+                       WriteExpect<'a, GameLog>,
+                       Entities<'a>,
+                       ReadStorage<'a, Coordinate>,
+                       ReadStorage<'a, Breathes>,
+                       ReadStorage<'a, Player>,
+                       WriteStorage<'a, StatusEffects>);
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let (mut map, mut flare, mut rng, mut log, entities, coordinates, breathers, players, mut status_effects) = data;
+
+        if flare.turns_left > 0
+        {
+            flare.turns_left -= 1;
+
+            if flare.turns_left == 0
+            {
+                log.add("The solar flare passes.");
+            }
+        }
+        else if rng.gen_range(0..SOLAR_FLARE_CHANCE) == 0
+        {
+            flare.turns_left = SOLAR_FLARE_TURNS;
+            log.add("Solar flare warning! Radiation is pouring in through the hull.");
+        }
+
+        map.apply_temperature_sources();
+        map.spread_radiation(flare.space_radiation());
+
+        for (entity, _breathes, coordinate) in (&entities, &breathers, &coordinates).join()
+        {
+            let is_player = players.get(entity).is_some();
+            let air = map.atmosphere(*coordinate);
+            let radiation = map.radiation(*coordinate);
+            let mut exposures: Vec<(StatusKind, i32, &str)> = Vec::new();
+
+            if air.pressure() >= THIN_AIR_PRESSURE && air.temperature <= COLD_TEMPERATURE
+            {
+                exposures.push((StatusKind::Hypothermia, EXPOSURE_TURNS, "The cold bites through your clothes. You are freezing."));
+            }
+            if air.pressure() >= THIN_AIR_PRESSURE && air.temperature >= HOT_TEMPERATURE
+            {
+                exposures.push((StatusKind::Heatstroke, EXPOSURE_TURNS, "The heat is overwhelming. You are overheating."));
+            }
+            if radiation > RADIATION_THRESHOLD && rng.gen_range(0.0..100.0) < radiation - RADIATION_THRESHOLD
+            {
+                exposures.push((StatusKind::Irradiated, IRRADIATED_TURNS, "Your dosimeter crackles. You are taking a dose of radiation."));
+            }
+
+            for (kind, turns, warning) in exposures
+            {
+                let already = status_effects.get(entity).map(|effects| effects.has(kind)).unwrap_or(false);
+                StatusEffects::apply_to(&mut status_effects, entity, kind, turns);
+
+                if is_player && !already
+                {
+                    log.add(warning);
+                }
+            }
+        }
+    }
+}
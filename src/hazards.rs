@@ -0,0 +1,76 @@
+/// Air temperature at or below which the unprotected start to freeze, in degrees Celsius
+pub const COLD_TEMPERATURE: f32 = 0.0;
+/// Air temperature at or above which the unprotected start to overheat, in degrees Celsius
+pub const HOT_TEMPERATURE: f32 = 45.0;
+/// Pressure below which the air is too thin to carry heat to a body, in kilopascals
+pub const THIN_AIR_PRESSURE: f32 = 10.0;
+/// Radiation at which a body starts picking up radiation sickness, each unit over it a percent chance a turn
+pub const RADIATION_THRESHOLD: f32 = 2.0;
+/// Radiation at which the overlay shows its strongest green
+pub const RADIATION_SHOWN: f32 = 40.0;
+
+/// Chance in one per turn a solar flare starts, how long it lasts and the radiation it fills space with
+pub const SOLAR_FLARE_CHANCE: i32 = 2000;
+pub const SOLAR_FLARE_TURNS: i32 = 40;
+pub const SOLAR_FLARE_RADIATION: f32 = 60.0;
+
+/// Turns radiation sickness, freezing and overheating last once out of harm's way
+pub const IRRADIATED_TURNS: i32 = 30;
+pub const EXPOSURE_TURNS: i32 = 5;
+
+/// What the map tints the tiles in view by, if anything
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Overlay
+{
+    #[default]
+    Off,
+    Temperature,
+    Radiation,
+    Gravity,
+}
+
+impl Overlay
+{
+    /// The overlay shown after this one when cycling through them
+    pub fn next(&self) -> Overlay
+    {
+        match self
+        {
+            Overlay::Off => Overlay::Temperature,
+            Overlay::Temperature => Overlay::Radiation,
+            Overlay::Radiation => Overlay::Gravity,
+            Overlay::Gravity => Overlay::Off,
+        }
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Overlay::Off => "off",
+            Overlay::Temperature => "temperature",
+            Overlay::Radiation => "radiation",
+            Overlay::Gravity => "gravity",
+        }
+    }
+}
+
+/// A solar flare washing over the station, if one is under way
+#[derive(Default)]
+pub struct SolarFlare
+{
+    pub turns_left: i32,
+}
+
+impl SolarFlare
+{
+    /// Radiation filling the space around the station this turn
+    pub fn space_radiation(&self) -> f32
+    {
+        match self.turns_left > 0
+        {
+            true => SOLAR_FLARE_RADIATION,
+            false => 0.0,
+        }
+    }
+}
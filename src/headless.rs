@@ -0,0 +1,166 @@
+use std::io::{BufRead, Write};
+
+use super::game_state::{RunState, State};
+use super::game_clock::GameClock;
+use super::key_names::KeyPress;
+use super::input_recording::{InputEvent, Recording, world_hash};
+
+
+/// Drives the game without drawing anything, taking one key name per line of input, such as `Left` or
+/// `Shift+Period`, as one frame's key press.
+/// A blank line lets a frame pass with no key pressed and `quit` stops early, as does the player dying.
+pub fn run_headless<R: BufRead, W: Write>(game_state: &mut State, input: R, output: &mut W) -> std::io::Result<()>
+{
+    for line in input.lines()
+    {
+        if game_state.run_state == RunState::Dead
+        {
+            break;
+        }
+
+        let line = line?;
+        let name = line.trim();
+
+        if name.eq_ignore_ascii_case("quit")
+        {
+            break;
+        }
+
+        let key_press = match name.is_empty()
+        {
+            true => None,
+            false => match KeyPress::from_name(name)
+            {
+                Some(key_press) => Some(key_press),
+                None =>
+                {
+                    writeln!(output, "unknown key '{}'", name)?;
+                    continue;
+                }
+            }
+        };
+
+        game_state.update(key_press, None);
+    }
+
+    // Let the last move settle so the summary matches what a window would have shown
+    game_state.run_systems();
+    write_summary(game_state, output)
+}
+
+pub fn write_summary<W: Write>(game_state: &State, output: &mut W) -> std::io::Result<()>
+{
+    let turn = game_state.entity_system.fetch::<GameClock>().turn;
+
+    match game_state.player_coordinate()
+    {
+        Some(coordinate) if game_state.run_state == RunState::Dead => writeln!(output, "turn {} player died at {} {} {}", turn, coordinate.x, coordinate.y, coordinate.z),
+        Some(coordinate) => writeln!(output, "turn {} player at {} {} {}", turn, coordinate.x, coordinate.y, coordinate.z),
+        None => writeln!(output, "turn {} no player", turn),
+    }
+}
+
+/// Frames a replay waits for the game to reach an input's turn on its own before giving up
+const MAX_IDLE_FRAMES: usize = 10_000;
+
+/// Plays a recording back without drawing, checking the world hash after every input.
+/// Returns whether the whole recording was reproduced.
+pub fn run_replay<W: Write>(game_state: &mut State, recording: &Recording, output: &mut W) -> std::io::Result<bool>
+{
+    for (index, input) in recording.inputs.iter().enumerate()
+    {
+        // Let frames without input pass until the game gets to the turn the input was given on
+        let mut idle_frames = 0;
+        while game_state.entity_system.fetch::<GameClock>().turn < input.turn && idle_frames < MAX_IDLE_FRAMES
+        {
+            game_state.update(None, None);
+            idle_frames += 1;
+        }
+
+        // Every input arrives through a frame, and every frame starts by running the systems
+        game_state.run_systems();
+
+        match input.event.clone()
+        {
+            InputEvent::Action(action) => game_state.perform_action(action),
+            InputEvent::FireAt(target) => game_state.fire_at(target),
+            InputEvent::ThrowAt(target) => game_state.throw_at(target),
+            InputEvent::SprayAt(target) => game_state.spray_at(target),
+            InputEvent::TerminalCommand(terminal, command) => game_state.terminal_command_at(terminal, command),
+        }
+
+        let hash = world_hash(&game_state.entity_system);
+        if hash != input.world_hash
+        {
+            writeln!(output, "replay diverged at input {} on turn {}: expected world hash {:016x}, got {:016x}",
+                     index + 1, input.turn, input.world_hash, hash)?;
+            return Ok(false);
+        }
+    }
+
+    game_state.run_systems();
+    writeln!(output, "replay matched {} inputs, final world hash {:016x}", recording.inputs.len(), world_hash(&game_state.entity_system))?;
+    write_summary(game_state, output)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use specs::prelude::*;
+
+    use super::*;
+    use super::super::character::{Background, Character};
+    use super::super::coordinate::Coordinate;
+    use super::super::entity_components::{CombatStats, Player};
+    use super::super::input_recording::InputRecorder;
+
+    fn new_game(seed: u64) -> State
+    {
+        let mut game_state = State::new(Coordinate::new(80, 50, 2), 80, 45, seed);
+        game_state.create_character(Character::from_choices(&Background::built_in(), None, None).unwrap());
+        game_state
+    }
+
+    #[test]
+    fn recorded_sessions_replay_exactly()
+    {
+        // Wandering about with idle frames in between, so the station gets on with things while the player dawdles
+        let frames = ["Up", "", "Right", "Period", "", "", "Down", "Left", "PageUp", "", "End", "Period"].repeat(8).join("\n");
+
+        for seed in [22, 28, 46]
+        {
+            let path = std::env::temp_dir().join(format!("headless_replay_{}_{}.txt", std::process::id(), seed));
+            let path = path.to_str().unwrap();
+
+            let mut game_state = new_game(seed);
+            game_state.recorder = Some(InputRecorder::create(path, seed).unwrap());
+            run_headless(&mut game_state, frames.as_bytes(), &mut Vec::new()).unwrap();
+
+            let recording = Recording::load(path).unwrap();
+            let mut output = Vec::new();
+            let matched = run_replay(&mut new_game(seed), &recording, &mut output).unwrap();
+            std::fs::remove_file(path).unwrap();
+
+            assert!(!recording.inputs.is_empty(), "seed {} recorded nothing", seed);
+            assert!(matched, "seed {}: {}", seed, String::from_utf8_lossy(&output));
+        }
+    }
+
+    #[test]
+    fn headless_runs_stop_once_the_player_dies()
+    {
+        let mut game_state = new_game(5);
+        for (_player, stats) in (&game_state.entity_system.read_storage::<Player>(), &mut game_state.entity_system.write_storage::<CombatStats>()).join()
+        {
+            stats.hp = 0;
+        }
+
+        let mut output = Vec::new();
+        run_headless(&mut game_state, "Up\nUp\nUp\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.starts_with("turn 0 player died at "), "{}", output);
+    }
+}
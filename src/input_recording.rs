@@ -0,0 +1,191 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+use specs::prelude::*;
+
+use super::character::Character;
+use super::coordinate::Coordinate;
+use super::game_clock::GameClock;
+use super::key_bindings::Action;
+use super::map::Map;
+
+
+/// Something the player did that changed what the game does next. Actions are recorded rather than
+/// keys, so a replay works whatever key bindings it is played back with.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputEvent
+{
+    Action(Action),
+    /// A shot fired at a tile picked with the targeting cursor
+    FireAt(Coordinate),
+    /// Something thrown at a tile picked with the targeting cursor
+    ThrowAt(Coordinate),
+    /// An extinguisher sprayed at a tile picked with the targeting cursor
+    SprayAt(Coordinate),
+    /// A command typed at the terminal standing on a tile
+    TerminalCommand(Coordinate, String),
+}
+
+impl InputEvent
+{
+    fn write(&self) -> String
+    {
+        match self
+        {
+            InputEvent::Action(action) => format!("action {}", action.name()),
+            InputEvent::FireAt(target) => format!("fire {} {} {}", target.x, target.y, target.z),
+            InputEvent::ThrowAt(target) => format!("throw {} {} {}", target.x, target.y, target.z),
+            InputEvent::SprayAt(target) => format!("spray {} {} {}", target.x, target.y, target.z),
+            InputEvent::TerminalCommand(terminal, command) => format!("terminal {} {} {} {}", terminal.x, terminal.y, terminal.z, command),
+        }
+    }
+
+    fn parse(fields: &[&str]) -> Option<InputEvent>
+    {
+        match fields
+        {
+            ["action", name] => Action::from_name(name).map(InputEvent::Action),
+            ["fire", x, y, z] => Some(InputEvent::FireAt(Coordinate::new(x.parse().ok()?, y.parse().ok()?, z.parse().ok()?))),
+            ["throw", x, y, z] => Some(InputEvent::ThrowAt(Coordinate::new(x.parse().ok()?, y.parse().ok()?, z.parse().ok()?))),
+            ["spray", x, y, z] => Some(InputEvent::SprayAt(Coordinate::new(x.parse().ok()?, y.parse().ok()?, z.parse().ok()?))),
+            ["terminal", x, y, z, command @ ..] =>
+            {
+                Some(InputEvent::TerminalCommand(Coordinate::new(x.parse().ok()?, y.parse().ok()?, z.parse().ok()?), command.join(" ")))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// One input as it was played, with the turn it was given on and the world hash right after it
+#[derive(Clone, Debug)]
+pub struct RecordedInput
+{
+    pub turn: u64,
+    pub event: InputEvent,
+    pub world_hash: u64,
+}
+
+/// Writes each input to a recording file as soon as it happens, so a crash still leaves a usable replay.
+///
+/// The file is plain text, a `seed` line, a `character <background> <name>` line once the character is made,
+/// then one `input <turn> <event...> <world hash>` line per input.
+pub struct InputRecorder
+{
+    writer: BufWriter<File>,
+}
+
+impl InputRecorder
+{
+    pub fn create(path: &str, seed: u64) -> std::io::Result<InputRecorder>
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writeln!(writer, "seed {}", seed)?;
+        writer.flush()?;
+
+        Ok(InputRecorder { writer })
+    }
+
+    pub fn record_character(&mut self, character: &Character) -> std::io::Result<()>
+    {
+        writeln!(self.writer, "character {} {}", character.background.id, character.name)?;
+        self.writer.flush()
+    }
+
+    pub fn record(&mut self, input: RecordedInput) -> std::io::Result<()>
+    {
+        writeln!(self.writer, "input {} {} {:016x}", input.turn, input.event.write(), input.world_hash)?;
+        self.writer.flush()
+    }
+}
+
+/// A recording read back from disk
+pub struct Recording
+{
+    pub seed: u64,
+    /// Background id and name of the character played, missing from recordings made before characters were made
+    pub character: Option<(String, String)>,
+    pub inputs: Vec<RecordedInput>,
+}
+
+impl Recording
+{
+    pub fn load(path: &str) -> Result<Recording, String>
+    {
+        let file = File::open(path).map_err(|error| format!("could not open recording '{}': {}", path, error))?;
+        let mut seed: Option<u64> = None;
+        let mut character: Option<(String, String)> = None;
+        let mut inputs: Vec<RecordedInput> = Vec::new();
+
+        for (line_number, line) in BufReader::new(file).lines().enumerate()
+        {
+            let line = line.map_err(|error| error.to_string())?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let bad_line = || format!("{}:{}: bad recording line '{}'", path, line_number + 1, line);
+
+            match fields.as_slice()
+            {
+                [] => {}
+                ["seed", value] => seed = Some(value.parse().map_err(|_| bad_line())?),
+                ["character", background, _name, ..] =>
+                {
+                    // The name is everything after the background, spaces and all
+                    let name = line.trim().splitn(3, ' ').nth(2).unwrap_or("").trim();
+                    character = Some((background.to_string(), name.to_string()));
+                }
+                ["input", turn, event @ .., world_hash] =>
+                {
+                    inputs.push(RecordedInput
+                    {
+                        turn: turn.parse().map_err(|_| bad_line())?,
+                        event: InputEvent::parse(event).ok_or_else(bad_line)?,
+                        world_hash: u64::from_str_radix(world_hash, 16).map_err(|_| bad_line())?,
+                    });
+                }
+                _ => return Err(bad_line()),
+            }
+        }
+
+        match seed
+        {
+            Some(seed) => Ok(Recording { seed, character, inputs }),
+            None => Err(format!("recording '{}' has no seed", path)),
+        }
+    }
+}
+
+/// Hashes everything a replay has to reproduce, the turn, every entity's position and the map's terrain
+pub fn world_hash(entity_system: &World) -> u64
+{
+    let mut hasher = DefaultHasher::new();
+    let entities = entity_system.entities();
+    let coordinates = entity_system.read_storage::<Coordinate>();
+    let map = entity_system.fetch::<Map>();
+
+    entity_system.fetch::<GameClock>().turn.hash(&mut hasher);
+
+    for (entity, coordinate) in (&entities, &coordinates).join()
+    {
+        entity.id().hash(&mut hasher);
+        coordinate.hash(&mut hasher);
+    }
+
+    for x in 0..map.map_size.x
+    {
+        for y in 0..map.map_size.y
+        {
+            for z in 0..map.map_size.z
+            {
+                if let Some(tile) = map.get(Coordinate::new(x, y, z))
+                {
+                    (x, y, z, tile.glyph, tile.passable, tile.opaque).hash(&mut hasher);
+                }
+            }
+        }
+    }
+
+    hasher.finish()
+}
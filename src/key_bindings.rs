@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::fs;
+
+use rltk::VirtualKeyCode;
+
+use super::key_names::KeyPress;
+
+
+/// Everything the player can ask for, independent of which key asked for it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action
+{
+    MoveNorth,
+    MoveNorthEast,
+    MoveEast,
+    MoveSouthEast,
+    MoveSouth,
+    MoveSouthWest,
+    MoveWest,
+    MoveNorthWest,
+    Wait,
+    PickUp,
+    Descend,
+    Ascend,
+    AutoExplore,
+    Look,
+    Fire,
+    Throw,
+    Spray,
+    Consume,
+    Sneak,
+    UseTerminal,
+    CycleOverlay,
+    CharacterSheet,
+    KeyBindingMenu,
+}
+
+impl Action
+{
+    /// Every action in the order the key binding screen lists them
+    pub const ALL: [Action; 23] =
+    [
+        Action::MoveNorth, Action::MoveNorthEast, Action::MoveEast, Action::MoveSouthEast,
+        Action::MoveSouth, Action::MoveSouthWest, Action::MoveWest, Action::MoveNorthWest,
+        Action::Wait, Action::PickUp, Action::Descend, Action::Ascend, Action::AutoExplore,
+        Action::Look, Action::Fire, Action::Throw, Action::Spray, Action::Consume,
+        Action::Sneak, Action::UseTerminal, Action::CycleOverlay, Action::CharacterSheet, Action::KeyBindingMenu,
+    ];
+
+    /// Name used in key binding files and recordings
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Action::MoveNorth => "move_north",
+            Action::MoveNorthEast => "move_north_east",
+            Action::MoveEast => "move_east",
+            Action::MoveSouthEast => "move_south_east",
+            Action::MoveSouth => "move_south",
+            Action::MoveSouthWest => "move_south_west",
+            Action::MoveWest => "move_west",
+            Action::MoveNorthWest => "move_north_west",
+            Action::Wait => "wait",
+            Action::PickUp => "pick_up",
+            Action::Descend => "descend",
+            Action::Ascend => "ascend",
+            Action::AutoExplore => "auto_explore",
+            Action::Look => "look",
+            Action::Fire => "fire",
+            Action::Throw => "throw",
+            Action::Spray => "spray",
+            Action::Consume => "consume",
+            Action::Sneak => "sneak",
+            Action::UseTerminal => "use_terminal",
+            Action::CycleOverlay => "cycle_overlay",
+            Action::CharacterSheet => "character_sheet",
+            Action::KeyBindingMenu => "key_binding_menu",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action>
+    {
+        Action::ALL.iter().copied().find(|action| action.name() == name.trim())
+    }
+
+    /// Step taken on the current deck, for the movement actions
+    pub fn movement_delta(&self) -> Option<(i32, i32)>
+    {
+        match self
+        {
+            Action::MoveNorth => Some((0, -1)),
+            Action::MoveNorthEast => Some((1, -1)),
+            Action::MoveEast => Some((1, 0)),
+            Action::MoveSouthEast => Some((1, 1)),
+            Action::MoveSouth => Some((0, 1)),
+            Action::MoveSouthWest => Some((-1, 1)),
+            Action::MoveWest => Some((-1, 0)),
+            Action::MoveNorthWest => Some((-1, -1)),
+            _ => None,
+        }
+    }
+
+    /// The movement action that takes a single step by the given amount, if there is one
+    pub fn movement(delta_x: i32, delta_y: i32) -> Option<Action>
+    {
+        Action::ALL.iter().copied().find(|action| action.movement_delta() == Some((delta_x, delta_y)))
+    }
+
+    /// Whether the action only opens a screen or starts a series of other actions rather than taking game time,
+    /// so it is handled by the game rather than the player and never recorded
+    pub fn is_interface(&self) -> bool
+    {
+        matches!(self, Action::KeyBindingMenu | Action::CharacterSheet | Action::AutoExplore | Action::Look | Action::Fire | Action::Throw
+                     | Action::Spray | Action::UseTerminal | Action::CycleOverlay)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset
+{
+    Numpad,
+    Vi,
+    Wasd,
+}
+
+impl Preset
+{
+    pub fn from_name(name: &str) -> Option<Preset>
+    {
+        match name.trim()
+        {
+            "numpad" => Some(Preset::Numpad),
+            "vi" => Some(Preset::Vi),
+            "wasd" => Some(Preset::Wasd),
+            _ => None,
+        }
+    }
+}
+
+pub const DEFAULT_KEY_BINDINGS_PATH: &str = "keybindings.cfg";
+
+/// Which key presses trigger which actions
+#[derive(Clone, Debug)]
+pub struct KeyBindings
+{
+    pub bindings: HashMap<KeyPress, Action>,
+}
+
+impl KeyBindings
+{
+    /// Arrow keys and the shared keys, plus the movement keys of the preset
+    pub fn preset(preset: Preset) -> KeyBindings
+    {
+        let mut key_bindings = KeyBindings { bindings: HashMap::new() };
+
+        key_bindings.bind_all(&[
+            (VirtualKeyCode::Up, Action::MoveNorth), (VirtualKeyCode::PageUp, Action::MoveNorthEast),
+            (VirtualKeyCode::Right, Action::MoveEast), (VirtualKeyCode::PageDown, Action::MoveSouthEast),
+            (VirtualKeyCode::Down, Action::MoveSouth), (VirtualKeyCode::End, Action::MoveSouthWest),
+            (VirtualKeyCode::Left, Action::MoveWest), (VirtualKeyCode::Home, Action::MoveNorthWest),
+            (VirtualKeyCode::Period, Action::Wait), (VirtualKeyCode::G, Action::PickUp),
+            (VirtualKeyCode::O, Action::AutoExplore), (VirtualKeyCode::Semicolon, Action::Look),
+            (VirtualKeyCode::F, Action::Fire), (VirtualKeyCode::T, Action::Throw),
+            (VirtualKeyCode::R, Action::Spray), (VirtualKeyCode::F1, Action::KeyBindingMenu),
+            (VirtualKeyCode::Tab, Action::CharacterSheet), (VirtualKeyCode::V, Action::Sneak),
+        ]);
+        key_bindings.bind(KeyPress::shifted(VirtualKeyCode::Period), Action::Descend);
+        key_bindings.bind(KeyPress::shifted(VirtualKeyCode::Comma), Action::Ascend);
+        key_bindings.bind(KeyPress::shifted(VirtualKeyCode::E), Action::Consume);
+        key_bindings.bind(KeyPress::shifted(VirtualKeyCode::U), Action::UseTerminal);
+        key_bindings.bind(KeyPress::shifted(VirtualKeyCode::O), Action::CycleOverlay);
+
+        match preset
+        {
+            Preset::Numpad => key_bindings.bind_all(&[
+                (VirtualKeyCode::Numpad8, Action::MoveNorth), (VirtualKeyCode::Numpad9, Action::MoveNorthEast),
+                (VirtualKeyCode::Numpad6, Action::MoveEast), (VirtualKeyCode::Numpad3, Action::MoveSouthEast),
+                (VirtualKeyCode::Numpad2, Action::MoveSouth), (VirtualKeyCode::Numpad1, Action::MoveSouthWest),
+                (VirtualKeyCode::Numpad4, Action::MoveWest), (VirtualKeyCode::Numpad7, Action::MoveNorthWest),
+                (VirtualKeyCode::Numpad5, Action::Wait),
+            ]),
+            Preset::Vi => key_bindings.bind_all(&[
+                (VirtualKeyCode::K, Action::MoveNorth), (VirtualKeyCode::U, Action::MoveNorthEast),
+                (VirtualKeyCode::L, Action::MoveEast), (VirtualKeyCode::N, Action::MoveSouthEast),
+                (VirtualKeyCode::J, Action::MoveSouth), (VirtualKeyCode::B, Action::MoveSouthWest),
+                (VirtualKeyCode::H, Action::MoveWest), (VirtualKeyCode::Y, Action::MoveNorthWest),
+            ]),
+            Preset::Wasd => key_bindings.bind_all(&[
+                (VirtualKeyCode::W, Action::MoveNorth), (VirtualKeyCode::E, Action::MoveNorthEast),
+                (VirtualKeyCode::D, Action::MoveEast), (VirtualKeyCode::C, Action::MoveSouthEast),
+                (VirtualKeyCode::S, Action::MoveSouth), (VirtualKeyCode::Z, Action::MoveSouthWest),
+                (VirtualKeyCode::A, Action::MoveWest), (VirtualKeyCode::Q, Action::MoveNorthWest),
+                (VirtualKeyCode::X, Action::Wait),
+            ]),
+        }
+
+        key_bindings
+    }
+
+    pub fn bind(&mut self, key_press: KeyPress, action: Action)
+    {
+        self.bindings.insert(key_press, action);
+    }
+
+    fn bind_all(&mut self, bindings: &[(VirtualKeyCode, Action)])
+    {
+        for (key, action) in bindings.iter()
+        {
+            self.bind(KeyPress::new(*key), *action);
+        }
+    }
+
+    pub fn unbind_action(&mut self, action: Action)
+    {
+        self.bindings.retain(|_key_press, bound_action| *bound_action != action);
+    }
+
+    pub fn action_for(&self, key_press: KeyPress) -> Option<Action>
+    {
+        self.bindings.get(&key_press).copied()
+    }
+
+    /// Every key press bound to the action, sorted by name so they list the same way each time
+    pub fn keys_for(&self, action: Action) -> Vec<KeyPress>
+    {
+        let mut key_presses: Vec<KeyPress> = self.bindings.iter()
+                                                          .filter(|(_key_press, bound_action)| **bound_action == action)
+                                                          .map(|(key_press, _bound_action)| *key_press)
+                                                          .collect();
+        key_presses.sort_by_key(|key_press| key_press.name());
+
+        key_presses
+    }
+
+    /// Reads a key binding file made of `preset <numpad|vi|wasd>`, `clear`, `bind <key> <action>` and `unbind <key>` lines.
+    /// A missing file gives the numpad preset.
+    pub fn load(path: &str) -> Result<KeyBindings, String>
+    {
+        let contents = match fs::read_to_string(path)
+        {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(KeyBindings::preset(Preset::Numpad)),
+            Err(error) => return Err(format!("could not read key bindings '{}': {}", path, error)),
+        };
+
+        KeyBindings::parse(&contents).map_err(|(line_number, line)| format!("{}:{}: bad key binding line '{}'", path, line_number, line))
+    }
+
+    /// Parses key binding file contents, giving back the line number and text of the first bad line
+    pub fn parse(contents: &str) -> Result<KeyBindings, (usize, String)>
+    {
+        let mut key_bindings = KeyBindings::preset(Preset::Numpad);
+
+        for (line_number, line) in contents.lines().enumerate()
+        {
+            let fields: Vec<&str> = line.split('#').next().unwrap_or("").split_whitespace().collect();
+            let bad_line = || (line_number + 1, line.to_string());
+
+            match fields.as_slice()
+            {
+                [] => {}
+                ["clear"] => key_bindings.bindings.clear(),
+                ["preset", name] => key_bindings = KeyBindings::preset(Preset::from_name(name).ok_or_else(bad_line)?),
+                ["bind", key, action] =>
+                {
+                    key_bindings.bind(KeyPress::from_name(key).ok_or_else(bad_line)?, Action::from_name(action).ok_or_else(bad_line)?);
+                }
+                ["unbind", key] =>
+                {
+                    key_bindings.bindings.remove(&KeyPress::from_name(key).ok_or_else(bad_line)?);
+                }
+                _ => return Err(bad_line()),
+            }
+        }
+
+        Ok(key_bindings)
+    }
+
+    /// Writes out every binding explicitly, so the file no longer depends on a preset
+    pub fn save(&self, path: &str) -> std::io::Result<()>
+    {
+        let mut contents = String::from("# Key bindings, one \"bind <key> <action>\" per line\nclear\n");
+
+        for action in Action::ALL.iter()
+        {
+            for key_press in self.keys_for(*action)
+            {
+                contents.push_str(&format!("bind {} {}\n", key_press.name(), action.name()));
+            }
+        }
+
+        fs::write(path, contents)
+    }
+}
@@ -0,0 +1,93 @@
+use rltk::VirtualKeyCode;
+
+
+/// Keys that can be named in headless input, recordings and key binding files
+const NAMED_KEYS: [VirtualKeyCode; 80] =
+[
+    VirtualKeyCode::A, VirtualKeyCode::B, VirtualKeyCode::C, VirtualKeyCode::D, VirtualKeyCode::E,
+    VirtualKeyCode::F, VirtualKeyCode::G, VirtualKeyCode::H, VirtualKeyCode::I, VirtualKeyCode::J,
+    VirtualKeyCode::K, VirtualKeyCode::L, VirtualKeyCode::M, VirtualKeyCode::N, VirtualKeyCode::O,
+    VirtualKeyCode::P, VirtualKeyCode::Q, VirtualKeyCode::R, VirtualKeyCode::S, VirtualKeyCode::T,
+    VirtualKeyCode::U, VirtualKeyCode::V, VirtualKeyCode::W, VirtualKeyCode::X, VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::Key0, VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3, VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5, VirtualKeyCode::Key6, VirtualKeyCode::Key7, VirtualKeyCode::Key8, VirtualKeyCode::Key9,
+    VirtualKeyCode::Numpad0, VirtualKeyCode::Numpad1, VirtualKeyCode::Numpad2, VirtualKeyCode::Numpad3, VirtualKeyCode::Numpad4,
+    VirtualKeyCode::Numpad5, VirtualKeyCode::Numpad6, VirtualKeyCode::Numpad7, VirtualKeyCode::Numpad8, VirtualKeyCode::Numpad9,
+    VirtualKeyCode::Left, VirtualKeyCode::Right, VirtualKeyCode::Up, VirtualKeyCode::Down,
+    VirtualKeyCode::Home, VirtualKeyCode::End, VirtualKeyCode::PageUp, VirtualKeyCode::PageDown,
+    VirtualKeyCode::Insert, VirtualKeyCode::Delete,
+    VirtualKeyCode::Escape, VirtualKeyCode::Return, VirtualKeyCode::Space, VirtualKeyCode::Tab, VirtualKeyCode::Back,
+    VirtualKeyCode::Period, VirtualKeyCode::Comma, VirtualKeyCode::Slash, VirtualKeyCode::Semicolon,
+    VirtualKeyCode::Minus, VirtualKeyCode::Equals, VirtualKeyCode::Grave,
+    VirtualKeyCode::F1, VirtualKeyCode::F2, VirtualKeyCode::F3, VirtualKeyCode::F4, VirtualKeyCode::F5, VirtualKeyCode::F6,
+    VirtualKeyCode::F7, VirtualKeyCode::F8, VirtualKeyCode::F9, VirtualKeyCode::F10, VirtualKeyCode::F11, VirtualKeyCode::F12,
+];
+
+/// The name a key is written as, which is the same as its variant name
+pub fn key_name(key: VirtualKeyCode) -> String
+{
+    format!("{:?}", key)
+}
+
+/// Parses a key name, ignoring case
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode>
+{
+    NAMED_KEYS.iter().copied().find(|key| key_name(*key).eq_ignore_ascii_case(name.trim()))
+}
+
+/// A key together with whether shift was held, so `>` can be told apart from `.`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyPress
+{
+    pub key: VirtualKeyCode,
+    pub shift: bool,
+}
+
+impl KeyPress
+{
+    pub fn new(key: VirtualKeyCode) -> KeyPress
+    {
+        KeyPress { key, shift: false }
+    }
+
+    pub fn shifted(key: VirtualKeyCode) -> KeyPress
+    {
+        KeyPress { key, shift: true }
+    }
+
+    /// Written as the key name, prefixed with `Shift+` when shift is held
+    pub fn name(&self) -> String
+    {
+        match self.shift
+        {
+            true => format!("Shift+{}", key_name(self.key)),
+            false => key_name(self.key),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<KeyPress>
+    {
+        let name = name.trim();
+
+        match name.get(..6)
+        {
+            Some(prefix) if prefix.eq_ignore_ascii_case("shift+") => key_from_name(&name[6..]).map(KeyPress::shifted),
+            _ => key_from_name(name).map(KeyPress::new),
+        }
+    }
+
+    /// The character the key types when entering text, if it types one
+    pub fn typed_char(&self) -> Option<char>
+    {
+        let name = key_name(self.key);
+
+        match self.key
+        {
+            VirtualKeyCode::Space => Some(' '),
+            VirtualKeyCode::Minus => Some('-'),
+            _ if name.len() == 1 => name.chars().next().map(|letter| if self.shift { letter } else { letter.to_ascii_lowercase() }),
+            _ => name.strip_prefix("Key").and_then(|digit| digit.chars().next()),
+        }
+    }
+}
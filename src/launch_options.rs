@@ -0,0 +1,74 @@
+/// Settings picked on the command line when the game starts
+#[derive(Clone, Debug)]
+pub struct LaunchOptions
+{
+    /// Runs the game without any window or terminal output, reading key names from standard input
+    pub headless: bool,
+    pub screen_width: usize,
+    pub screen_height: usize,
+    /// Seed for map generation and every other random roll, picked at random when not given
+    pub seed: Option<u64>,
+    /// File to write every input to while playing
+    pub record_path: Option<String>,
+    /// Recording to play back without drawing, checking it reproduces the same world
+    pub replay_path: Option<String>,
+    /// Key binding file to use instead of the default one
+    pub key_bindings_path: Option<String>,
+    /// Backgrounds file to use instead of the default one
+    pub backgrounds_path: Option<String>,
+    /// Character name and background id to start with, skipping the character creation screen when either is given
+    pub character_name: Option<String>,
+    pub background: Option<String>,
+}
+
+pub const USAGE: &str = "usage: main [--headless] [--size WIDTHxHEIGHT] [--seed SEED] [--record FILE | --replay FILE] [--keys FILE] [--backgrounds FILE] [--name NAME] [--background ID]";
+
+impl LaunchOptions
+{
+    pub fn new(screen_width: usize, screen_height: usize) -> LaunchOptions
+    {
+        LaunchOptions { headless: false, screen_width, screen_height, seed: None, record_path: None, replay_path: None, key_bindings_path: None,
+                        backgrounds_path: None, character_name: None, background: None }
+    }
+
+    /// Reads options from the program arguments, not including the program name
+    pub fn from_args(mut self, args: impl Iterator<Item = String>) -> Result<LaunchOptions, String>
+    {
+        let mut args = args;
+
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "--headless" => self.headless = true,
+                "--size" =>
+                {
+                    let size = args.next().ok_or("--size needs a value like 80x50")?;
+                    let (width, height) = size.split_once('x').ok_or(format!("bad screen size '{}'", size))?;
+
+                    self.screen_width = width.parse().map_err(|_| format!("bad screen width '{}'", width))?;
+                    self.screen_height = height.parse().map_err(|_| format!("bad screen height '{}'", height))?;
+                }
+                "--seed" =>
+                {
+                    let seed = args.next().ok_or("--seed needs a number")?;
+                    self.seed = Some(seed.parse().map_err(|_| format!("bad seed '{}'", seed))?);
+                }
+                "--record" => self.record_path = Some(args.next().ok_or("--record needs a file name")?),
+                "--replay" => self.replay_path = Some(args.next().ok_or("--replay needs a file name")?),
+                "--keys" => self.key_bindings_path = Some(args.next().ok_or("--keys needs a file name")?),
+                "--backgrounds" => self.backgrounds_path = Some(args.next().ok_or("--backgrounds needs a file name")?),
+                "--name" => self.character_name = Some(args.next().ok_or("--name needs a name")?),
+                "--background" => self.background = Some(args.next().ok_or("--background needs a background id")?),
+                _ => return Err(format!("unknown option '{}'", arg)),
+            }
+        }
+
+        if self.record_path.is_some() && self.replay_path.is_some()
+        {
+            return Err("--record and --replay can't be used together".to_string());
+        }
+
+        Ok(self)
+    }
+}
@@ -8,4 +8,90 @@ pub mod coordinate;
 
 pub mod rectangle;
 
-pub mod visibility_system;
\ No newline at end of file
+pub mod visibility_system;
+
+pub mod map_memory;
+
+pub mod memory_sharing_system;
+
+pub mod game_clock;
+
+pub mod camera;
+
+pub mod key_names;
+
+pub mod launch_options;
+
+pub mod game_state;
+
+pub mod headless;
+
+pub mod input_recording;
+
+pub mod key_bindings;
+
+pub mod game_log;
+
+pub mod spawner;
+
+pub mod gui;
+
+pub mod player;
+
+pub mod navigation;
+
+pub mod atmosphere;
+
+pub mod damage_system;
+
+pub mod atmosphere_system;
+
+pub mod ranged_combat;
+
+pub mod explosions;
+
+pub mod fire_system;
+
+pub mod status_effects;
+
+pub mod status_effect_system;
+
+pub mod turn_scheduler;
+
+pub mod survival;
+
+pub mod survival_system;
+
+pub mod character;
+
+pub mod skills;
+
+pub mod factions;
+
+pub mod ai;
+
+pub mod ai_system;
+
+pub mod noise;
+
+pub mod station_network;
+
+pub mod turret_system;
+
+pub mod security_camera_system;
+
+pub mod power;
+
+pub mod power_system;
+
+pub mod life_support;
+
+pub mod life_support_system;
+
+pub mod hazards;
+
+pub mod hazard_system;
+
+pub mod physics;
+
+pub mod physics_system;
@@ -0,0 +1,141 @@
+use specs_derive::Component;
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::map::Map;
+
+
+/// Condition of a life support unit in perfect repair, its output falls off in step as it wears down to nothing
+pub const MAX_CONDITION: i32 = 10;
+/// Chance in one per turn a unit picks up a fault that wears it down a step
+pub const WEAR_CHANCE: i32 = 400;
+
+/// Oxygen turned into carbon dioxide each turn in every tile of air by the crew, the machinery and slow leaks, in
+/// kilopascals
+pub const STALE_AIR_PER_TURN: f32 = 0.004;
+/// Oxygen a body breathing in a tile turns into carbon dioxide each turn, in kilopascals
+pub const BREATH_PER_TURN: f32 = 0.05;
+/// Temperature the hull would cool the air to without heating, and the share of the way there it cools each turn
+pub const HULL_TEMPERATURE: f32 = -40.0;
+pub const HEAT_LEAK: f32 = 0.0005;
+
+/// What a life support unit does for the air of its section
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LifeSupportKind
+{
+    /// Takes carbon dioxide out of the air
+    Scrubber,
+    /// Tops the oxygen back up
+    OxygenGenerator,
+    /// Keeps the air warm
+    Heater,
+}
+
+impl LifeSupportKind
+{
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            LifeSupportKind::Scrubber => "scrubber",
+            LifeSupportKind::OxygenGenerator => "oxygen generator",
+            LifeSupportKind::Heater => "heater",
+        }
+    }
+}
+
+//LifeSupport
+/// A unit keeping the air of the section it stands in breathable, as long as it has power and is in repair. Its
+/// output is shared between every tile of the section, in kilopascals or degrees a turn.
+#[derive(Component)]
+pub struct LifeSupport
+{
+    pub kind: LifeSupportKind,
+    pub output: f32,
+    pub condition: i32,
+}
+
+impl LifeSupport
+{
+    /// What the unit puts out this turn given whether it has power, less the more worn it is
+    pub fn effective_output(&self, powered: bool) -> f32
+    {
+        match powered
+        {
+            true => self.output * self.condition.max(0) as f32 / MAX_CONDITION as f32,
+            false => 0.0,
+        }
+    }
+}
+
+/// The air of the station split into sections sealed off from each other by bulkheads and shut doors
+pub struct AirSections
+{
+    /// Section of every tile indexed by `Map::tile_index`, `None` where there is no air to keep
+    pub section_of: Vec<Option<usize>>,
+    pub tiles: Vec<Vec<Coordinate>>,
+}
+
+impl AirSections
+{
+    /// Floods out from every tile of air across its deck, leaving out tiles open to space, which no unit can keep
+    pub fn trace(map: &Map) -> AirSections
+    {
+        let size = map.map_size;
+        let mut section_of: Vec<Option<usize>> = vec![None; size.x * size.y * size.z];
+        let mut tiles: Vec<Vec<Coordinate>> = Vec::new();
+        let holds_air = |coordinate: Coordinate| map.get(coordinate).map(|tile| tile.holds_atmosphere() && !tile.open_to_space).unwrap_or(false);
+
+        for z in 0..size.z
+        {
+            for y in 0..size.y
+            {
+                for x in 0..size.x
+                {
+                    let start = Coordinate::new(x, y, z);
+
+                    if !holds_air(start) || section_of[map.tile_index(start)].is_some()
+                    {
+                        continue;
+                    }
+
+                    let section = tiles.len();
+                    let mut section_tiles: Vec<Coordinate> = vec![start];
+                    let mut frontier: Vec<Coordinate> = vec![start];
+                    section_of[map.tile_index(start)] = Some(section);
+
+                    while let Some(current) = frontier.pop()
+                    {
+                        for (delta_x, delta_y) in [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                        {
+                            let (neighbour_x, neighbour_y) = (current.x as i32 + delta_x, current.y as i32 + delta_y);
+
+                            if neighbour_x < 0 || neighbour_y < 0
+                            {
+                                continue;
+                            }
+
+                            let neighbour = Coordinate::new(neighbour_x as usize, neighbour_y as usize, z);
+
+                            if holds_air(neighbour) && section_of[map.tile_index(neighbour)].is_none()
+                            {
+                                section_of[map.tile_index(neighbour)] = Some(section);
+                                section_tiles.push(neighbour);
+                                frontier.push(neighbour);
+                            }
+                        }
+                    }
+                    tiles.push(section_tiles);
+                }
+            }
+        }
+
+        AirSections { section_of, tiles }
+    }
+
+    pub fn section_at(&self, map: &Map, coordinate: Coordinate) -> Option<usize>
+    {
+        map.get(coordinate)?;
+        self.section_of[map.tile_index(coordinate)]
+    }
+}
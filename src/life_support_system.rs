@@ -0,0 +1,105 @@
+use rand::Rng;
+use rand::rngs::StdRng;
+use specs::prelude::*;
+
+use super::atmosphere::{STANDARD_CARBON_DIOXIDE, STANDARD_OXYGEN, STANDARD_TEMPERATURE};
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::game_log::GameLog;
+use super::life_support::*;
+use super::map::Map;
+use super::power::{is_powered, PowerConsumer};
+
+
+/// Lets the air go stale and cold for a turn and has the life support units of each sealed section win it back.
+/// Every tile of air loses a little oxygen to carbon dioxide and a little heat to the hull, more where someone is
+/// breathing. Each unit shares its output between the tiles of its own section, so a room with none slowly becomes
+/// unlivable unless its doors are open to one that has. Units wear down now and then until someone repairs them.
+pub struct LifeSupportSystem {}
+
+impl<'a> System<'a> for LifeSupportSystem
+{
+    type SystemData = (WriteExpect<'a, Map>,
+                       WriteExpect<'a, StdRng>,
+                       WriteExpect<'a, GameLog>,
+                       ReadStorage<'a, Coordinate>,
+                       ReadStorage<'a, Breathes>,
+                       ReadStorage<'a, Player>,
+                       ReadStorage<'a, Viewshed>,
+                       ReadStorage<'a, PowerConsumer>,
+                       Entities<'a>,
+                       WriteStorage<'a, LifeSupport>);
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let (mut map, mut rng, mut log, coordinates, breathers, players, viewsheds, consumers, entities, mut units) = data;
+
+        let sections = AirSections::trace(&map);
+        let seen = |coordinate: &Coordinate| (&players, &viewsheds).join().any(|(_player, viewshed)| viewshed.visible_tiles.contains(coordinate));
+
+        for (unit, coordinate) in (&mut units, &coordinates).join()
+        {
+            if unit.condition > 0 && rng.gen_range(0..WEAR_CHANCE) == 0
+            {
+                unit.condition -= 1;
+
+                if unit.condition == 0 && seen(coordinate)
+                {
+                    log.add(format!("The {} shudders and breaks down.", unit.kind.name()));
+                }
+            }
+        }
+
+        for tile in sections.tiles.iter().flatten()
+        {
+            let mut air = map.atmosphere(*tile);
+            let used = STALE_AIR_PER_TURN.min(air.oxygen);
+
+            air.oxygen -= used;
+            air.carbon_dioxide += used;
+            air.temperature += (HULL_TEMPERATURE - air.temperature) * HEAT_LEAK;
+            map.set_atmosphere(*tile, air);
+        }
+
+        for (_breathes, coordinate) in (&breathers, &coordinates).join()
+        {
+            if sections.section_at(&map, *coordinate).is_some()
+            {
+                let mut air = map.atmosphere(*coordinate);
+                let used = BREATH_PER_TURN.min(air.oxygen);
+
+                air.oxygen -= used;
+                air.carbon_dioxide += used;
+                map.set_atmosphere(*coordinate, air);
+            }
+        }
+
+        for (entity, unit, coordinate) in (&entities, &units, &coordinates).join()
+        {
+            let section = match sections.section_at(&map, *coordinate)
+            {
+                Some(section) => &sections.tiles[section],
+                None => continue,
+            };
+            let share = unit.effective_output(is_powered(&consumers, entity)) / section.len() as f32;
+
+            if share <= 0.0
+            {
+                continue;
+            }
+
+            for tile in section.iter()
+            {
+                let mut air = map.atmosphere(*tile);
+
+                match unit.kind
+                {
+                    LifeSupportKind::Scrubber => air.carbon_dioxide -= share.min(air.carbon_dioxide - STANDARD_CARBON_DIOXIDE).max(0.0),
+                    LifeSupportKind::OxygenGenerator => air.oxygen += share.min(STANDARD_OXYGEN - air.oxygen).max(0.0),
+                    LifeSupportKind::Heater => air.temperature += share.min(STANDARD_TEMPERATURE - air.temperature).max(0.0),
+                }
+                map.set_atmosphere(*tile, air);
+            }
+        }
+    }
+}
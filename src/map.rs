@@ -1,22 +1,50 @@
 use std::cmp::{min, max};
+use std::collections::{HashMap, HashSet, VecDeque};
 use super::coordinate::Coordinate;
 
 use rand::Rng;
+use rand::rngs::StdRng;
 use rltk::{Rltk, RGB, Algorithm2D, Point, BaseMap};
 
 use super::rectangle::Rectangle;
 use super::map_tile::MapTile;
+use super::atmosphere::{Atmosphere, OPAQUE_SMOKE};
+use super::map_memory::MapMemory;
+use super::camera::Camera;
+use super::hazards::{Overlay, COLD_TEMPERATURE, HOT_TEMPERATURE, RADIATION_SHOWN};
 
 
 pub struct Map
 {
     pub map_size: Coordinate,
     pub tiles: Vec<Vec<Vec<Option<MapTile>>>>,
+    /// Flat copy of every tile's opacity indexed by `tile_index`, so field of view never has to unpack tiles
+    opaque_tiles: Vec<bool>,
+    /// Tiles changed since the visibility system last ran, so it only dirties the viewsheds that could see them
+    changed_tiles: Vec<Coordinate>,
+    /// Gas in every tile indexed by `tile_index`, vacuum wherever there is no tile or only bulkhead
+    atmosphere: Vec<Atmosphere>,
+    /// Fuel left to every fire indexed by `tile_index`, zero where nothing is burning
+    fire_fuel: Vec<i32>,
+    /// Every tile fitted out as a medical bay when the station was built
+    medbay_tiles: Vec<Coordinate>,
+    /// Whether each tile indexed by `tile_index` is left in dim light, as corridors and rooms with failing lights are
+    dim_tiles: Vec<bool>,
+    /// Whether a power conduit runs under each tile indexed by `tile_index`
+    conduits: Vec<bool>,
+    /// Whether any conduit has been severed since the power system last traced the grid
+    conduits_severed: bool,
+    /// Radiation in every tile indexed by `tile_index`, out in space as well as aboard
+    radiation: Vec<f32>,
+    /// Whether the gravity plating under each tile indexed by `tile_index` is working
+    gravity: Vec<bool>,
+    /// What stood where each piece of wreckage is before it was wrecked, to put back when it is repaired
+    wrecked_tiles: HashMap<Coordinate, MapTile>,
 }
 
 impl Map
 {
-    /// Makes a map with solid boundries
+    // Makes a map with solid boundries
     /*pub fn new(map_size: Coordinate) -> Map
     {
         let mut tiles: Vec<Vec<Vec<Option<MapTile>>>> = vec![vec![vec![None; map_size.z]; map_size.y]; map_size.x];
@@ -53,90 +81,467 @@ impl Map
             map_size,
         }
     }*/
-
-    /// Makes a map consisting of random rooms and corridors connecting them
-    pub fn rooms_and_corridors_map(number_of_rooms: usize, min_room_size: usize, max_room_size: usize,  map_size: Coordinate) -> (Map,  Vec<Rectangle>)
+    /// Makes a map consisting of random rooms and corridors connecting them on every deck, a ladder
+    /// between each pair of decks, and glass panels, grates and open shafts looking down where rooms overlap
+    pub fn rooms_and_corridors_map(number_of_rooms: usize, min_room_size: usize, max_room_size: usize,  map_size: Coordinate, rng: &mut StdRng) -> (Map,  Vec<Rectangle>)
     {
-        const MAX_ATTEMPTS: usize = 100;
-        let mut tiles: Vec<Vec<Vec<Option<MapTile>>>> = vec![vec![vec![None; map_size.z]; map_size.y]; map_size.x];
+        let mut map = Map::empty(map_size);
         let mut rooms: Vec<Rectangle> = Vec::new();
-        let mut previous_room_center = Coordinate::new(0, 0, 0);
 
-        for _ in 0..number_of_rooms
+        for z in 0..map_size.z
+        {
+            rooms.append(&mut add_deck_rooms(number_of_rooms, min_room_size, max_room_size, z, &mut map, rng));
+        }
+
+        let ladders = connect_decks(&rooms, &mut map, rng);
+        add_walls(&mut map);
+        add_vertical_openings(&rooms, &mut map, rng);
+        add_crew_quarters(&rooms, &mut map, rng);
+        add_medbays(&rooms, &mut map);
+        add_reactor(&rooms, &mut map, rng);
+        add_cryo_stores(&rooms, &mut map, rng);
+        add_gravity_failures(&rooms, &mut map, rng);
+        add_lighting(&rooms, &mut map, rng);
+        add_doors(&rooms, &mut map, rng);
+        add_conduits(&rooms, &mut map);
+
+        for ladder_top in ladders.iter()
+        {
+            map.set_tile(*ladder_top, MapTile::ladder_down());
+            map.set_tile(Coordinate::new(ladder_top.x, ladder_top.y, ladder_top.z + 1), MapTile::ladder_up());
+        }
+        map.refresh_opacity_cache();
+        map.pressurise();
+        map.changed_tiles.clear();
+
+        (map, rooms)
+    }
+
+    /// Makes a map of the given size with no tiles on it yet
+    pub fn empty(map_size: Coordinate) -> Map
+    {
+        let tiles: Vec<Vec<Vec<Option<MapTile>>>> = vec![vec![vec![None; map_size.z]; map_size.y]; map_size.x];
+        let opaque_tiles = vec![false; map_size.x * map_size.y * map_size.z];
+        let atmosphere = vec![Atmosphere::vacuum(); map_size.x * map_size.y * map_size.z];
+        let fire_fuel = vec![0; map_size.x * map_size.y * map_size.z];
+
+        Map { tiles, map_size, opaque_tiles, changed_tiles: Vec::new(), atmosphere, fire_fuel, medbay_tiles: Vec::new(),
+              dim_tiles: vec![true; map_size.x * map_size.y * map_size.z],
+              conduits: vec![false; map_size.x * map_size.y * map_size.z], conduits_severed: false,
+              radiation: vec![0.0; map_size.x * map_size.y * map_size.z],
+              gravity: vec![true; map_size.x * map_size.y * map_size.z],
+              wrecked_tiles: HashMap::new() }
+    }
+
+    /// Index of a coordinate into the flat per tile caches, decks are stored one after another
+    pub fn tile_index(&self, coordinate: Coordinate) -> usize
+    {
+        (coordinate.z * self.map_size.y + coordinate.y) * self.map_size.x + coordinate.x
+    }
+
+    /// Rebuilds the opacity cache after tiles were written directly instead of through `set_tile`
+    pub fn refresh_opacity_cache(&mut self)
+    {
+        for x in 0..self.map_size.x
         {
-            let mut current_attempts = 0;
-            let mut room_placed = false;
+            for y in 0..self.map_size.y
+            {
+                for z in 0..self.map_size.z
+                {
+                    let coordinate = Coordinate::new(x, y, z);
+                    let index = self.tile_index(coordinate);
+
+                    self.opaque_tiles[index] = self.tile_opacity(index, self.tiles[x][y][z]);
+                }
+            }
+        }
+    }
+
+    /// Whether a tile blocks sight, because it is solid or because it is full of smoke
+    fn tile_opacity(&self, index: usize, tile: Option<MapTile>) -> bool
+    {
+        tile.map(|tile| tile.opaque).unwrap_or(false) || self.atmosphere[index].is_smoky()
+    }
+
+    pub fn is_opaque(&self, coordinate: Coordinate) -> bool
+    {
+        coordinate.x < self.map_size.x && coordinate.y < self.map_size.y && coordinate.z < self.map_size.z
+            && self.opaque_tiles[self.tile_index(coordinate)]
+    }
 
-            while !room_placed && current_attempts < MAX_ATTEMPTS
+    /// Fills every tile that can hold gas with standard air
+    pub fn pressurise(&mut self)
+    {
+        for x in 0..self.map_size.x
+        {
+            for y in 0..self.map_size.y
             {
-                let mut room_valid = true;
+                for z in 0..self.map_size.z
+                {
+                    let index = self.tile_index(Coordinate::new(x, y, z));
 
-                let room_width = rand::thread_rng().gen_range(min_room_size..max_room_size + 1);
-                let room_height = rand::thread_rng().gen_range(min_room_size..max_room_size + 1);
+                    self.atmosphere[index] = match self.tiles[x][y][z]
+                    {
+                        Some(tile) if tile.holds_atmosphere() => Atmosphere::standard(),
+                        _ => Atmosphere::vacuum(),
+                    };
+                }
+            }
+        }
+    }
+
+    pub fn atmosphere(&self, coordinate: Coordinate) -> Atmosphere
+    {
+        match coordinate.x < self.map_size.x && coordinate.y < self.map_size.y && coordinate.z < self.map_size.z
+        {
+            true => self.atmosphere[self.tile_index(coordinate)],
+            false => Atmosphere::vacuum(),
+        }
+    }
 
-                let room_coordinate = Coordinate::new(rand::thread_rng().gen_range(1..map_size.x - (room_width + 1)),
-                                                                rand::thread_rng().gen_range(1..map_size.y - (room_height + 1)),
-                                                                0);
+    pub fn set_atmosphere(&mut self, coordinate: Coordinate, atmosphere: Atmosphere)
+    {
+        let index = self.tile_index(coordinate);
+        self.atmosphere[index] = atmosphere;
+    }
 
-                let room = Rectangle::new(room_coordinate, Coordinate::new(room_coordinate.x + room_width, 
-                                                    room_coordinate.y + room_height, 
-                                                    room_coordinate.z));
+    /// Lets gas flow one turn's worth between neighbouring tiles on each deck. Breaches and tiles bordering
+    /// open space drain towards vacuum, bulkheads hold it back. Tiles that smoke has just filled or cleared
+    /// have their opacity updated and are marked as changed.
+    pub fn diffuse_atmosphere(&mut self)
+    {
+        let mut next = self.atmosphere.clone();
+        let mut smoke_changed: Vec<(usize, Coordinate, bool)> = Vec::new();
 
-                for other_room in rooms.iter()
+        for z in 0..self.map_size.z
+        {
+            for y in 0..self.map_size.y
+            {
+                for x in 0..self.map_size.x
                 {
-                    if room.intersect(other_room)
+                    let coordinate = Coordinate::new(x, y, z);
+                    let tile = match self.tiles[x][y][z]
                     {
-                        room_valid = false;
+                        Some(tile) if tile.holds_atmosphere() => tile,
+                        _ => continue,
+                    };
+                    let index = self.tile_index(coordinate);
+
+                    next[index] = match tile.open_to_space
+                    {
+                        true => Atmosphere::vacuum(),
+                        false => self.diffused(coordinate),
+                    };
+
+                    let opaque = tile.opaque || next[index].is_smoky();
+                    if opaque != self.opaque_tiles[index]
+                    {
+                        smoke_changed.push((index, coordinate, opaque));
                     }
                 }
+            }
+        }
+
+        self.atmosphere = next;
+
+        for (index, coordinate, opaque) in smoke_changed
+        {
+            self.opaque_tiles[index] = opaque;
+            self.changed_tiles.push(coordinate);
+        }
+    }
+
+    /// What the gas in a tile becomes after a turn of flowing to and from its neighbours
+    fn diffused(&self, coordinate: Coordinate) -> Atmosphere
+    {
+        let here = self.atmosphere[self.tile_index(coordinate)];
+        let mut flow = Atmosphere::default();
+
+        for (delta_x, delta_y) in [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        {
+            let (neighbor_x, neighbor_y) = (coordinate.x as i32 + delta_x, coordinate.y as i32 + delta_y);
+            let neighbor = Coordinate::new(neighbor_x.max(0) as usize, neighbor_y.max(0) as usize, coordinate.z);
+            let outside = neighbor_x < 0 || neighbor_y < 0 || neighbor.x >= self.map_size.x || neighbor.y >= self.map_size.y;
+
+            let there = match if outside { None } else { self.get(neighbor) }
+            {
+                Some(neighbor_tile) if neighbor_tile.holds_atmosphere() => self.atmosphere[self.tile_index(neighbor)],
+                Some(_bulkhead) => continue,
+                None => Atmosphere::vacuum(),
+            };
+
+            flow.oxygen += (there.oxygen - here.oxygen) * DIFFUSION_RATE;
+            flow.nitrogen += (there.nitrogen - here.nitrogen) * DIFFUSION_RATE;
+            flow.carbon_dioxide += (there.carbon_dioxide - here.carbon_dioxide) * DIFFUSION_RATE;
+            flow.smoke += (there.smoke - here.smoke) * DIFFUSION_RATE;
+            // Heat is carried by the gas, so thin air next door hardly warms or cools a tile
+            let carried = match here.pressure() + there.pressure() > 0.0
+            {
+                true => (2.0 * there.pressure() / (here.pressure() + there.pressure())).min(1.0),
+                false => 0.0,
+            };
+            flow.temperature += (there.temperature - here.temperature) * DIFFUSION_RATE * carried;
+        }
+
+        Atmosphere
+        {
+            oxygen: here.oxygen + flow.oxygen,
+            nitrogen: here.nitrogen + flow.nitrogen,
+            carbon_dioxide: here.carbon_dioxide + flow.carbon_dioxide,
+            smoke: (here.smoke + flow.smoke) * SMOKE_SETTLING,
+            temperature: here.temperature + flow.temperature,
+        }
+    }
+
+    /// Tiles fitted out as medical bays when the station was built, some may have been wrecked since
+    pub fn medbay_tiles(&self) -> &[Coordinate]
+    {
+        &self.medbay_tiles
+    }
+
+    /// Whether a room's lights are on, going by its centre
+    pub fn lights_on(&self, room: &Rectangle) -> bool
+    {
+        !self.dim_tiles[self.tile_index(room.center())]
+    }
+
+    /// Switches the lights of a room on or off
+    pub fn set_lighting(&mut self, room: &Rectangle, lit: bool)
+    {
+        for x in room.corner_one.x + 1..=room.corner_two.x
+        {
+            for y in room.corner_one.y + 1..=room.corner_two.y
+            {
+                let index = self.tile_index(Coordinate::new(x, y, room.corner_one.z));
+                self.dim_tiles[index] = !lit;
+            }
+        }
+    }
+
+    /// Whether the tile is in dim light, which a fire on it lights up
+    pub fn is_dim(&self, coordinate: Coordinate) -> bool
+    {
+        self.get(coordinate).is_some() && self.dim_tiles[self.tile_index(coordinate)] && !self.is_burning(coordinate)
+    }
+
+    pub fn is_burning(&self, coordinate: Coordinate) -> bool
+    {
+        self.get(coordinate).is_some() && self.fire_fuel[self.tile_index(coordinate)] > 0
+    }
+
+    /// Sets a flammable tile alight if there is oxygen enough to burn, returning whether it caught
+    pub fn ignite(&mut self, coordinate: Coordinate) -> bool
+    {
+        match self.get(coordinate)
+        {
+            Some(tile) if tile.flammable && !self.is_burning(coordinate) && self.atmosphere(coordinate).oxygen >= MINIMUM_OXYGEN_TO_BURN =>
+            {
+                let index = self.tile_index(coordinate);
+                self.fire_fuel[index] = FIRE_FUEL;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn extinguish(&mut self, coordinate: Coordinate)
+    {
+        if self.get(coordinate).is_some()
+        {
+            let index = self.tile_index(coordinate);
+            self.fire_fuel[index] = 0;
+        }
+    }
+
+    /// Every tile currently on fire, in a fixed order so anything random done per fire replays the same
+    pub fn burning_tiles(&self) -> Vec<Coordinate>
+    {
+        let deck_size = self.map_size.x * self.map_size.y;
+
+        self.fire_fuel.iter()
+                      .enumerate()
+                      .filter(|(_index, fuel)| **fuel > 0)
+                      .map(|(index, _fuel)| Coordinate::new(index % self.map_size.x, (index % deck_size) / self.map_size.x, index / deck_size))
+                      .collect()
+    }
+
+    /// Lets every fire burn for a turn: each uses up oxygen and fuel and gives off smoke, goes out when the air
+    /// gets too thin, and leaves scorched deck behind once its fuel is gone. Spreading is left to the caller.
+    pub fn burn(&mut self)
+    {
+        for coordinate in self.burning_tiles()
+        {
+            let index = self.tile_index(coordinate);
+            let atmosphere = &mut self.atmosphere[index];
+
+            if atmosphere.oxygen < MINIMUM_OXYGEN_TO_BURN
+            {
+                self.fire_fuel[index] = 0;
+                continue;
+            }
+
+            atmosphere.oxygen -= OXYGEN_BURNED_PER_TURN.min(atmosphere.oxygen);
+            atmosphere.smoke += SMOKE_PER_TURN;
+            self.fire_fuel[index] -= 1;
+
+            if self.fire_fuel[index] == 0
+            {
+                self.set_tile(coordinate, MapTile::scorched_floor());
+            }
+        }
+    }
+
+    /// Whether the tile is a bulkhead with open space on the other side
+    pub fn is_hull(&self, coordinate: Coordinate) -> bool
+    {
+        match self.get(coordinate)
+        {
+            Some(tile) if !tile.holds_atmosphere() =>
+            {
+                [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(delta_x, delta_y)|
+                {
+                    let (x, y) = (coordinate.x as i32 + delta_x, coordinate.y as i32 + delta_y);
+                    x < 0 || y < 0 || self.get(Coordinate::new(x as usize, y as usize, coordinate.z)).is_none()
+                })
+            }
+            _ => false,
+        }
+    }
+
+    pub fn radiation(&self, coordinate: Coordinate) -> f32
+    {
+        match coordinate.x < self.map_size.x && coordinate.y < self.map_size.y && coordinate.z < self.map_size.z
+        {
+            true => self.radiation[self.tile_index(coordinate)],
+            false => 0.0,
+        }
+    }
+
+    /// Whether a tile has gravity, which anywhere off the map is taken to have
+    pub fn has_gravity(&self, coordinate: Coordinate) -> bool
+    {
+        self.get(coordinate).is_none() || self.gravity[self.tile_index(coordinate)]
+    }
+
+    pub fn set_gravity(&mut self, coordinate: Coordinate, on: bool)
+    {
+        if self.get(coordinate).is_some()
+        {
+            let index = self.tile_index(coordinate);
+            self.gravity[index] = on;
+        }
+    }
+
+    /// Switches the gravity plating of a whole deck on or off
+    pub fn set_deck_gravity(&mut self, deck: usize, on: bool)
+    {
+        for x in 0..self.map_size.x
+        {
+            for y in 0..self.map_size.y
+            {
+                self.set_gravity(Coordinate::new(x, y, deck), on);
+            }
+        }
+    }
 
-                if room_valid
+    /// Has every reactor core and cryogenic deck pull the air on and around it a step towards its own temperature
+    pub fn apply_temperature_sources(&mut self)
+    {
+        for z in 0..self.map_size.z
+        {
+            for y in 0..self.map_size.y
+            {
+                for x in 0..self.map_size.x
                 {
-                    add_room_to_map(&room, &mut tiles);
+                    let source = match self.tiles[x][y][z].and_then(|tile| tile.temperature_source)
+                    {
+                        Some(source) => source,
+                        None => continue,
+                    };
 
-                    if !rooms.is_empty()
+                    for (delta_x, delta_y) in [(0, 0), (-1, 0), (1, 0), (0, -1), (0, 1)]
                     {
-                        if rand::thread_rng().gen_range(0..2) == 1
-                        {
-                            add_horizontal_corridor(room.center(), previous_room_center, &mut tiles);
-                            add_vertical_corridor(room.center(), previous_room_center, &mut tiles);
-                        }
-                        else
+                        let (x, y) = (x as i32 + delta_x, y as i32 + delta_y);
+                        let coordinate = Coordinate::new(x.max(0) as usize, y.max(0) as usize, z);
+
+                        if x >= 0 && y >= 0 && self.get(coordinate).map(|tile| tile.holds_atmosphere()).unwrap_or(false)
                         {
-                            add_horizontal_corridor(room.center(), previous_room_center, &mut tiles);
-                            add_vertical_corridor(room.center(), previous_room_center, &mut tiles);
+                            let index = self.tile_index(coordinate);
+                            let air = &mut self.atmosphere[index];
+                            air.temperature += (source - air.temperature) * TEMPERATURE_SOURCE_RATE;
                         }
                     }
-                    previous_room_center = room.center();
-
-                    rooms.push(room);
-                    room_placed = true;
-                    
                 }
-                else 
+            }
+        }
+    }
+
+    /// Lets radiation spread a turn's worth between neighbouring tiles and die away. Every tile takes in only what
+    /// its shielding lets through, what reactor cores give off is added on and space is filled with `space_radiation`,
+    /// which is nothing unless a solar flare is washing over the station.
+    pub fn spread_radiation(&mut self, space_radiation: f32)
+    {
+        let mut next = self.radiation.clone();
+
+        for z in 0..self.map_size.z
+        {
+            for y in 0..self.map_size.y
+            {
+                for x in 0..self.map_size.x
                 {
-                    current_attempts += 1;
+                    let index = self.tile_index(Coordinate::new(x, y, z));
+                    let tile = self.tiles[x][y][z];
+
+                    if tile.map(|tile| tile.open_to_space).unwrap_or(true)
+                    {
+                        next[index] = space_radiation;
+                        continue;
+                    }
+
+                    let here = self.radiation[index];
+                    let mut flow = 0.0;
+
+                    for (delta_x, delta_y) in [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                    {
+                        let (neighbour_x, neighbour_y) = (x as i32 + delta_x, y as i32 + delta_y);
+                        let outside = neighbour_x < 0 || neighbour_y < 0 || neighbour_x as usize >= self.map_size.x || neighbour_y as usize >= self.map_size.y;
+                        let (there, neighbour_shielding) = match outside
+                        {
+                            true => (space_radiation, 0.0),
+                            false =>
+                            {
+                                let neighbour = Coordinate::new(neighbour_x as usize, neighbour_y as usize, z);
+                                (self.radiation[self.tile_index(neighbour)], self.get(neighbour).map(|tile| tile.shielding).unwrap_or(0.0))
+                            }
+                        };
+
+                        // Radiation gets into a tile only as far as the tile's own shielding lets it
+                        flow += match there > here
+                        {
+                            true => (there - here) * RADIATION_SPREAD * (1.0 - tile.map(|tile| tile.shielding).unwrap_or(0.0)),
+                            false => (there - here) * RADIATION_SPREAD * (1.0 - neighbour_shielding),
+                        };
+                    }
+
+                    next[index] = (here + flow) * RADIATION_DECAY + tile.map(|tile| tile.radiation).unwrap_or(0.0);
                 }
             }
-            
         }
-        
-        let mut map = Map { tiles, map_size };
 
-        add_walls(&mut map);
+        self.radiation = next;
+    }
 
-        (map, rooms)
+    /// Hands over every tile changed since the last call
+    pub fn take_changed_tiles(&mut self) -> Vec<Coordinate>
+    {
+        std::mem::take(&mut self.changed_tiles)
     }
 
     pub fn get(&self, coordinate: Coordinate) -> Option<MapTile>
     {
         if coordinate.x < self.map_size.x && coordinate.y < self.map_size.y && coordinate.z < self.map_size.z
         {
-            match &self.tiles[coordinate.x][coordinate.y][coordinate.z]
-            {
-                Some(target_tile) => return Some(*target_tile),
-                None => return None,
-            }
+            self.tiles[coordinate.x][coordinate.y][coordinate.z]
         }
         else
         {
@@ -146,97 +551,263 @@ impl Map
 
     pub fn set_tile(&mut self, coordinate: Coordinate, map_tile: MapTile)
     {
+        let index = self.tile_index(coordinate);
+
+        // Remember what wreckage replaced so a repair can put it back
+        if !map_tile.needs_repair()
+        {
+            self.wrecked_tiles.remove(&coordinate);
+        }
+        else if let Some(replaced) = self.get(coordinate).filter(|tile| !tile.needs_repair())
+        {
+            self.wrecked_tiles.insert(coordinate, replaced);
+        }
         self.tiles[coordinate.x][coordinate.y][coordinate.z] = Some(map_tile);
+        self.opaque_tiles[index] = self.tile_opacity(index, Some(map_tile));
+        self.changed_tiles.push(coordinate);
+
+        // Whatever wrecks a tile tears out the conduit under it
+        if map_tile.needs_repair() && self.conduits[index]
+        {
+            self.conduits[index] = false;
+            self.conduits_severed = true;
+        }
     }
 
-    pub fn set_tile_visibility(&mut self, coordinate: Coordinate, visible: bool)
+    pub fn has_conduit(&self, coordinate: Coordinate) -> bool
     {
-        let target_tile = &mut self.tiles[coordinate.x][coordinate.y][coordinate.z];
+        self.get(coordinate).is_some() && self.conduits[self.tile_index(coordinate)]
+    }
+
+    /// Whether conduits were severed since this was last asked, clearing the answer for next time
+    pub fn take_severed_conduits(&mut self) -> bool
+    {
+        std::mem::replace(&mut self.conduits_severed, false)
+    }
 
-        match target_tile
+    /// Puts back whatever a piece of wreckage used to be, or a plain bulkhead if it was never anything else
+    pub fn repair_tile(&mut self, coordinate: Coordinate)
+    {
+        let repaired = self.wrecked_tiles.get(&coordinate).copied().unwrap_or_else(MapTile::wall);
+        self.set_tile(coordinate, repaired);
+    }
+
+    /// Swings a door open, letting in a share of the air from either side so the doorway is not left airless
+    pub fn open_door(&mut self, coordinate: Coordinate)
+    {
+        let mut neighbors: Vec<Atmosphere> = Vec::new();
+
+        for (delta_x, delta_y) in [(-1, 0), (1, 0), (0, -1), (0, 1)]
         {
-            Some(tile) => tile.visible = visible,
-            _ => {},
+            let (x, y) = (coordinate.x as i32 + delta_x, coordinate.y as i32 + delta_y);
+            let neighbor = Coordinate::new(x.max(0) as usize, y.max(0) as usize, coordinate.z);
+
+            if x >= 0 && y >= 0 && self.get(neighbor).map(|tile| tile.holds_atmosphere()).unwrap_or(false)
+            {
+                neighbors.push(self.atmosphere(neighbor));
+            }
         }
+
+        if !neighbors.is_empty()
+        {
+            let count = neighbors.len() as f32;
+            let mixed = Atmosphere
+            {
+                oxygen: neighbors.iter().map(|gas| gas.oxygen).sum::<f32>() / count,
+                nitrogen: neighbors.iter().map(|gas| gas.nitrogen).sum::<f32>() / count,
+                carbon_dioxide: neighbors.iter().map(|gas| gas.carbon_dioxide).sum::<f32>() / count,
+                smoke: neighbors.iter().map(|gas| gas.smoke).sum::<f32>() / count,
+                temperature: neighbors.iter().map(|gas| gas.temperature).sum::<f32>() / count,
+            };
+            self.set_atmosphere(coordinate, mixed);
+        }
+
+        self.set_tile(coordinate, MapTile::open_door());
     }
 
-    pub fn set_tile_revealed(&mut self, coordinate: Coordinate, revealed: bool)
+    /// Returns the coordinate one deck below, if the tile at the coordinate can be seen through
+    pub fn below_transparent_floor(&self, coordinate: Coordinate) -> Option<Coordinate>
     {
-        let target_tile = &mut self.tiles[coordinate.x][coordinate.y][coordinate.z];
-
-        match target_tile
+        match self.get(coordinate)
         {
-            Some(tile) => tile.revealed = revealed,
-            _ => {},
+            Some(tile) if tile.transparent_floor && coordinate.z + 1 < self.map_size.z =>
+            {
+                Some(Coordinate::new(coordinate.x, coordinate.y, coordinate.z + 1))
+            }
+            _ => None,
         }
     }
 
-    pub fn reset_visibility(&mut self, z: usize)
+    /// Background an overlay gives a tile, blue for cold air and red for hot, green for radiation, purple for no
+    /// gravity, or nothing where there is nothing to warn of
+    fn overlay_tint(&self, coordinate: Coordinate, overlay: Overlay) -> Option<RGB>
     {
-        for x in 0..self.tiles.len() - 1
+        match overlay
         {
-            for y in 0..self.tiles[x].len() - 1
+            Overlay::Off => None,
+            Overlay::Temperature if self.atmosphere(coordinate).pressure() < 1.0 => None,
+            Overlay::Temperature =>
             {
-                self.set_tile_visibility(Coordinate { x, y, z}, false);      
+                let temperature = self.atmosphere(coordinate).temperature;
+                let comfortable = (COLD_TEMPERATURE + HOT_TEMPERATURE) / 2.0;
+                let strength = ((temperature - comfortable) / (HOT_TEMPERATURE - comfortable)).clamp(-1.0, 1.0);
+
+                match strength < 0.0
+                {
+                    true => Some(RGB::from_f32(0.0, 0.1 * -strength, 0.5 * -strength)),
+                    false => Some(RGB::from_f32(0.5 * strength, 0.05 * strength, 0.0)),
+                }
+            }
+            Overlay::Gravity => match self.has_gravity(coordinate)
+            {
+                true => None,
+                false => Some(RGB::from_f32(0.25, 0.0, 0.35)),
+            },
+            Overlay::Radiation =>
+            {
+                let strength = (self.radiation(coordinate) / RADIATION_SHOWN).min(1.0);
+                match strength > 0.05
+                {
+                    true => Some(RGB::from_f32(0.1 * strength, 0.5 * strength, 0.0)),
+                    false => None,
+                }
             }
         }
     }
 
-    pub fn draw(&self, context: &mut Rltk, camera_z: usize)
-    { 
+    /// Draws the part of a deck in the camera as the viewer remembers it, greying out whatever is not currently in view.
+    /// An overlay tints the tiles in view by how hot, cold or radioactive they are.
+    pub fn draw(&self, context: &mut Rltk, camera: &Camera, memory: &MapMemory, visible_tiles: &HashSet<Coordinate>, overlay: Overlay)
+    {
+        let camera_z = camera.origin.z;
 
-        for x in 0..self.tiles.len() - 1
+        for screen_x in 0..camera.width
         {
-            for y in 0..self.tiles[x].len() - 1
+            for screen_y in 0..camera.height
             {
-                let tile = self.tiles[x][y][camera_z];
-                
-                match tile
+                let coordinate = Coordinate::new(camera.origin.x + screen_x, camera.origin.y + screen_y, camera_z);
+                let (x, y) = (coordinate.x, coordinate.y);
+
+                if let Some(remembered) = memory.get_tile(coordinate)
                 {
-                    Some(tile) if tile.revealed =>
+                    let tile = remembered.tile;
+                    let below_tile = match tile.transparent_floor
+                    {
+                        true => memory.get_tile(Coordinate::new(x, y, camera_z + 1)),
+                        false => None,
+                    };
+
+                    let (glyph, mut foreground_color, mut background_color) = match below_tile
                     {
-                        if tile.visible
+                        Some(below_tile) =>
                         {
-                            context.set(x, y, tile.foreground_color, tile.background_color, tile.glyph);
+                            (below_tile.tile.glyph, below_tile.tile.foreground_color * BELOW_DECK_DIMMING, tile.background_color)
                         }
-                        else
+                        None => (tile.glyph, tile.foreground_color, tile.background_color),
+                    };
+
+                    let mut glyph = glyph;
+
+                    if !visible_tiles.contains(&coordinate)
+                    {
+                        foreground_color = foreground_color.to_greyscale();
+                        background_color = background_color.to_greyscale();
+                    }
+                    else if self.is_burning(coordinate)
+                    {
+                        glyph = rltk::to_cp437('^');
+                        foreground_color = RGB::named(rltk::ORANGE);
+                        background_color = RGB::from_f32(0.4, 0.05, 0.0);
+                    }
+                    else if self.atmosphere(coordinate).smoke >= SMOKE_SHOWN
+                    {
+                        let density = (self.atmosphere(coordinate).smoke / OPAQUE_SMOKE).min(1.0);
+                        background_color = RGB::from_f32(0.35 * density, 0.35 * density, 0.35 * density);
+                    }
+                    else if self.is_dim(coordinate)
+                    {
+                        foreground_color = foreground_color * DIM_LIGHT_SHADING;
+                    }
+
+                    if visible_tiles.contains(&coordinate)
+                    {
+                        if let Some(tint) = self.overlay_tint(coordinate, overlay)
                         {
-                            context.set(x, y, tile.foreground_color.to_greyscale(), tile.background_color.to_greyscale(), tile.glyph);
+                            background_color = tint;
                         }
                     }
-                    _ => {}
+
+                    context.set(screen_x, screen_y, foreground_color, background_color, glyph);
                 }
-            } 
+            }
         }
     }
 }
 
-impl Algorithm2D for Map
+/// How much colour is kept when looking at the deck below through a transparent floor
+pub const BELOW_DECK_DIMMING: f32 = 0.45;
+/// How much colour is kept on tiles in view that are only dimly lit
+const DIM_LIGHT_SHADING: f32 = 0.6;
+
+/// Share of the pressure difference with each neighbour that evens out per turn, kept under a quarter so four
+/// neighbours can never overshoot
+const DIFFUSION_RATE: f32 = 0.2;
+/// Share of the smoke in a tile still hanging in the air a turn later, the rest settles or is scrubbed out
+const SMOKE_SETTLING: f32 = 0.97;
+/// Share of the gap to its own temperature a reactor core or cryogenic deck closes in the air around it each turn
+const TEMPERATURE_SOURCE_RATE: f32 = 0.3;
+/// Share of the radiation difference with each neighbour that evens out per turn, and share of it left a turn later
+const RADIATION_SPREAD: f32 = 0.2;
+const RADIATION_DECAY: f32 = 0.85;
+/// Smoke thin enough to see through is still drawn as haze from this density
+const SMOKE_SHOWN: f32 = 0.3;
+
+/// Oxygen partial pressure below which fires go out, in kilopascals
+const MINIMUM_OXYGEN_TO_BURN: f32 = 8.0;
+/// Oxygen each fire uses up per turn, in kilopascals
+const OXYGEN_BURNED_PER_TURN: f32 = 3.0;
+const SMOKE_PER_TURN: f32 = 1.5;
+/// Turns a freshly lit tile burns for before its fuel runs out
+const FIRE_FUEL: i32 = 12;
+
+/// A single deck of a map, so rltk's 2D field of view and path finding can work on it
+pub struct DeckView<'a>
+{
+    pub map: &'a Map,
+    pub z: usize,
+}
+
+impl<'a> DeckView<'a>
+{
+    pub fn new(map: &'a Map, z: usize) -> DeckView<'a>
+    {
+        DeckView { map, z }
+    }
+
+    pub fn idx_to_coordinate(&self, idx: usize) -> Coordinate
+    {
+        let mut coordinate = idx_to_cartisian(idx, self.map.map_size);
+        coordinate.z = self.z;
+
+        coordinate
+    }
+}
+
+impl<'a> Algorithm2D for DeckView<'a>
 {
-    fn dimensions(&self) -> rltk::Point 
+    fn dimensions(&self) -> rltk::Point
     {
-        Point::new(self.map_size.x, self.map_size.y)
+        Point::new(self.map.map_size.x, self.map.map_size.y)
     }
 }
 
-impl BaseMap for Map
+impl<'a> BaseMap for DeckView<'a>
 {
-    fn is_opaque(&self, target_idx: usize) -> bool 
+    fn is_opaque(&self, target_idx: usize) -> bool
     {
-        let target_coordinate = idx_to_cartisian(target_idx, self.map_size);
-        let target_tile = self.get(target_coordinate);
+        let deck_size = self.map.map_size.x * self.map.map_size.y;
 
-        match target_tile 
-        {
-            Some(tile) => 
-            {
-                tile.opaque
-            }
-            _ =>
-            {
-                false
-            }
-        }
+        target_idx < deck_size && self.map.opaque_tiles[self.z * deck_size + target_idx]
     }
 }
 
@@ -294,49 +865,375 @@ pub fn get_tile_neighbors(target_tile: Coordinate, map: &Map) -> (Vec<Option<Map
     (neighbors, coordinates)
 }
 
-fn add_room_to_map(room: &Rectangle, tiles: &mut Vec<Vec<Vec<Option<MapTile>>>>)
+/// Places up to the given number of non overlapping rooms on one deck, each joined to the previous by corridors
+fn add_deck_rooms(number_of_rooms: usize, min_room_size: usize, max_room_size: usize, z: usize, map: &mut Map, rng: &mut StdRng) -> Vec<Rectangle>
 {
-    for x in room.corner_one.x + 1 ..= room.corner_two.x
+    const MAX_ATTEMPTS: usize = 100;
+    let map_size = map.map_size;
+    let mut rooms: Vec<Rectangle> = Vec::new();
+    let mut previous_room_center = Coordinate::new(0, 0, z);
+
+    for _ in 0..number_of_rooms
     {
-        for y in room.corner_one.y + 1 ..= room.corner_two.y
+        let mut current_attempts = 0;
+        let mut room_placed = false;
+
+        while !room_placed && current_attempts < MAX_ATTEMPTS
         {
-            tiles[x][y][room.corner_one.z] = Some(MapTile::new(rltk::to_cp437('.'), 
-                                RGB::from_f32(0.3, 0.3, 0.3),
-                                RGB::named(rltk::BLACK), 
-                                true, false, false, false));    
+            let mut room_valid = true;
+
+            let room_width = rng.gen_range(min_room_size..max_room_size + 1);
+            let room_height = rng.gen_range(min_room_size..max_room_size + 1);
+
+            let room_coordinate = Coordinate::new(rng.gen_range(1..map_size.x - (room_width + 1)),
+                                                            rng.gen_range(1..map_size.y - (room_height + 1)),
+                                                            z);
+
+            let room = Rectangle::new(room_coordinate, Coordinate::new(room_coordinate.x + room_width,
+                                                room_coordinate.y + room_height,
+                                                room_coordinate.z));
+
+            for other_room in rooms.iter()
+            {
+                if room.intersect(other_room)
+                {
+                    room_valid = false;
+                }
+            }
+
+            if room_valid
+            {
+                add_room_to_map(&room, &mut map.tiles);
+
+                if !rooms.is_empty()
+                {
+                    add_horizontal_corridor(room.center(), previous_room_center, &mut map.tiles);
+                    add_vertical_corridor(room.center(), previous_room_center, &mut map.tiles);
+                }
+                previous_room_center = room.center();
+
+                rooms.push(room);
+                room_placed = true;
+            }
+            else
+            {
+                current_attempts += 1;
+            }
         }
     }
+
+    rooms
 }
 
-fn add_horizontal_corridor(origin: Coordinate, target: Coordinate, tiles: &mut Vec<Vec<Vec<Option<MapTile>>>>)
+/// Builds the reactor into a room on the lowest deck away from the crew's starting room and the medical bays, its
+/// core at the centre and its walls lined with shielding. Corridors and doors through the walls still let some out.
+fn add_reactor(rooms: &[Rectangle], map: &mut Map, rng: &mut StdRng)
 {
-    for x in min(origin.x, target.x) ..= max(origin.x, target.x)
+    let deck = map.map_size.z - 1;
+    let candidates: Vec<&Rectangle> = rooms.iter()
+                                           .skip(1)
+                                           .filter(|room| room.corner_one.z == deck && !map.medbay_tiles.contains(&room.center()))
+                                           .collect();
+
+    if candidates.is_empty()
     {
-        tiles[x][origin.y][origin.z] = Some(MapTile::new(rltk::to_cp437('.'), 
-                            RGB::from_f32(0.3, 0.3, 0.3),
-                            RGB::named(rltk::BLACK), 
-                            true, false, false, false)); 
+        return;
     }
+
+    let room = candidates[rng.gen_range(0..candidates.len())];
+
+    for x in room.corner_one.x..=room.corner_two.x + 1
+    {
+        for y in room.corner_one.y..=room.corner_two.y + 1
+        {
+            if map.tiles[x][y][deck].map(|tile| tile.name == MapTile::wall().name).unwrap_or(false)
+            {
+                map.tiles[x][y][deck] = Some(MapTile::shielded_wall());
+            }
+        }
+    }
+
+    let core = room.center();
+    map.tiles[core.x][core.y][core.z] = Some(MapTile::reactor_core());
 }
 
-fn add_vertical_corridor(origin: Coordinate, target: Coordinate, tiles: &mut Vec<Vec<Vec<Option<MapTile>>>>)
+/// Fits out a room on about every other deck as a cryogenic store, kept far below freezing
+fn add_cryo_stores(rooms: &[Rectangle], map: &mut Map, rng: &mut StdRng)
 {
-    for y in min(origin.y, target.y) ..= max(origin.y, target.y)
+    let plain_floor = |map: &Map, coordinate: Coordinate| map.tiles[coordinate.x][coordinate.y][coordinate.z].map(|tile| tile.name == MapTile::floor().name).unwrap_or(false);
+
+    for z in 0..map.map_size.z
     {
-        tiles[target.x][y][origin.z] = Some(MapTile::new(rltk::to_cp437('.'), 
-                            RGB::from_f32(0.3, 0.3, 0.3),
-                            RGB::named(rltk::BLACK), 
-                            true, false, false, false)); 
+        let candidates: Vec<&Rectangle> = rooms.iter().skip(1).filter(|room| room.corner_one.z == z && plain_floor(map, room.center())).collect();
+
+        if candidates.is_empty() || rng.gen_range(0..2) == 0
+        {
+            continue;
+        }
+
+        let room = candidates[rng.gen_range(0..candidates.len())];
+
+        for x in room.corner_one.x + 1..=room.corner_two.x
+        {
+            for y in room.corner_one.y + 1..=room.corner_two.y
+            {
+                if plain_floor(map, Coordinate::new(x, y, z))
+                {
+                    map.tiles[x][y][z] = Some(MapTile::cryo_floor());
+                }
+            }
+        }
+    }
+}
+
+/// Leaves the gravity plating failed in about one room in eight, never the crew's starting room
+fn add_gravity_failures(rooms: &[Rectangle], map: &mut Map, rng: &mut StdRng)
+{
+    for room in rooms.iter().skip(1)
+    {
+        if rng.gen_range(0..8) != 0
+        {
+            continue;
+        }
+
+        for x in room.corner_one.x + 1..=room.corner_two.x
+        {
+            for y in room.corner_one.y + 1..=room.corner_two.y
+            {
+                map.set_gravity(Coordinate::new(x, y, room.corner_one.z), false);
+            }
+        }
+    }
+}
+
+/// Pads out the floor of about one room in four as crew quarters, which will burn
+fn add_crew_quarters(rooms: &[Rectangle], map: &mut Map, rng: &mut StdRng)
+{
+    for room in rooms.iter()
+    {
+        if rng.gen_range(0..4) != 0
+        {
+            continue;
+        }
+
+        for x in room.corner_one.x + 1..=room.corner_two.x
+        {
+            for y in room.corner_one.y + 1..=room.corner_two.y
+            {
+                if let Some(tile) = map.tiles[x][y][room.corner_one.z]
+                {
+                    if tile.name == MapTile::floor().name
+                    {
+                        map.tiles[x][y][room.corner_one.z] = Some(MapTile::padded_floor());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Fits out one room on each deck as a medical bay, the first past the crew's starting room with plain deck at its centre
+fn add_medbays(rooms: &[Rectangle], map: &mut Map)
+{
+    let plain_floor = |map: &Map, coordinate: Coordinate| map.tiles[coordinate.x][coordinate.y][coordinate.z].map(|tile| tile.name == MapTile::floor().name).unwrap_or(false);
+
+    for z in 0..map.map_size.z
+    {
+        let medbay = rooms.iter().skip(1).find(|room| room.corner_one.z == z && plain_floor(map, room.center()));
+
+        if let Some(room) = medbay
+        {
+            for x in room.corner_one.x + 1..=room.corner_two.x
+            {
+                for y in room.corner_one.y + 1..=room.corner_two.y
+                {
+                    if plain_floor(map, Coordinate::new(x, y, z))
+                    {
+                        map.tiles[x][y][z] = Some(MapTile::medbay_floor());
+                        map.medbay_tiles.push(Coordinate::new(x, y, z));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Lights up the rooms, leaving corridors dim along with about one room in four whose lights are failing.
+/// The crew's starting room and medical bays are always lit.
+fn add_lighting(rooms: &[Rectangle], map: &mut Map, rng: &mut StdRng)
+{
+    const FAILING_LIGHTS_CHANCE: i32 = 25;
+
+    for (room_number, room) in rooms.iter().enumerate()
+    {
+        let centre = room.center();
+        let essential = room_number == 0 || map.tiles[centre.x][centre.y][centre.z].map(|tile| tile.medical).unwrap_or(false);
+
+        if rng.gen_range(0..100) < FAILING_LIGHTS_CHANCE && !essential
+        {
+            continue;
+        }
+
+        map.set_lighting(room, true);
+    }
+}
+
+/// Lays power conduits under every room, and along the shortest way from the first room on each deck, where its
+/// generator stands, to every other room on the deck, through doors whether or not they are locked
+fn add_conduits(rooms: &[Rectangle], map: &mut Map)
+{
+    for room in rooms.iter()
+    {
+        for x in room.corner_one.x + 1..=room.corner_two.x
+        {
+            for y in room.corner_one.y + 1..=room.corner_two.y
+            {
+                let index = map.tile_index(Coordinate::new(x, y, room.corner_one.z));
+                map.conduits[index] = true;
+            }
+        }
+    }
+
+    for z in 0..map.map_size.z
+    {
+        let generator_room = match rooms.iter().find(|room| room.corner_one.z == z)
+        {
+            Some(room) => room.center(),
+            None => continue,
+        };
+        let walkable = |map: &Map, coordinate: Coordinate| map.get(coordinate).map(|tile| tile.passable || tile.door.is_some()).unwrap_or(false);
+
+        // Breadth first from the generator room, remembering where each tile was reached from
+        let mut came_from: Vec<Option<Coordinate>> = vec![None; map.map_size.x * map.map_size.y];
+        let mut frontier: VecDeque<Coordinate> = VecDeque::new();
+        let deck_index = |coordinate: Coordinate| coordinate.y * map.map_size.x + coordinate.x;
+
+        came_from[deck_index(generator_room)] = Some(generator_room);
+        frontier.push_back(generator_room);
+
+        while let Some(current) = frontier.pop_front()
+        {
+            for (delta_x, delta_y) in [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            {
+                let (x, y) = (current.x as i32 + delta_x, current.y as i32 + delta_y);
+
+                if x < 0 || y < 0 || x >= map.map_size.x as i32 || y >= map.map_size.y as i32
+                {
+                    continue;
+                }
+
+                let next = Coordinate::new(x as usize, y as usize, z);
+                if came_from[deck_index(next)].is_none() && walkable(map, next)
+                {
+                    came_from[deck_index(next)] = Some(current);
+                    frontier.push_back(next);
+                }
+            }
+        }
+
+        for room in rooms.iter().filter(|room| room.corner_one.z == z)
+        {
+            let mut step = room.center();
+
+            while step != generator_room
+            {
+                let index = map.tile_index(step);
+                map.conduits[index] = true;
+
+                match came_from[deck_index(step)]
+                {
+                    Some(previous) => step = previous,
+                    None => break,
+                }
+            }
+        }
+    }
+}
+
+/// Hangs a door in every narrow gap where a corridor passes through a room's wall, locking about one in five.
+/// The first room is where the crew starts, its doors are never locked.
+fn add_doors(rooms: &[Rectangle], map: &mut Map, rng: &mut StdRng)
+{
+    const LOCKED_CHANCE: i32 = 20;
+
+    for (room_number, room) in rooms.iter().enumerate()
+    {
+        let z = room.corner_one.z;
+        let (left, right) = (room.corner_one.x, room.corner_two.x + 1);
+        let (top, bottom) = (room.corner_one.y, room.corner_two.y + 1);
+        let mut gaps: Vec<(Coordinate, bool)> = Vec::new();
+
+        // The ring just outside the room, and whether each tile on it runs along the room's top or bottom edge
+        for x in left + 1..right
+        {
+            gaps.push((Coordinate::new(x, top, z), true));
+            gaps.push((Coordinate::new(x, bottom, z), true));
+        }
+        for y in top + 1..bottom
+        {
+            gaps.push((Coordinate::new(left, y, z), false));
+            gaps.push((Coordinate::new(right, y, z), false));
+        }
+
+        for (gap, horizontal_edge) in gaps
+        {
+            if gap.x >= map.map_size.x || gap.y >= map.map_size.y || map.tiles[gap.x][gap.y][z].map(|tile| tile.name) != Some(MapTile::floor().name)
+            {
+                continue;
+            }
+
+            // Only a gap with wall on both sides along the edge is narrow enough to take a door
+            let flanks = match horizontal_edge
+            {
+                true => [(gap.x - 1, gap.y), (gap.x + 1, gap.y)],
+                false => [(gap.x, gap.y - 1), (gap.x, gap.y + 1)],
+            };
+            let walled = flanks.iter().all(|(x, y)| map.tiles[*x][*y][z].map(|tile| tile.name == MapTile::wall().name).unwrap_or(false));
+
+            if walled
+            {
+                let locked = rng.gen_range(0..100) < LOCKED_CHANCE && room_number != 0;
+                let door = if locked { MapTile::locked_door() } else { MapTile::closed_door() };
+                map.tiles[gap.x][gap.y][z] = Some(door);
+            }
+        }
+    }
+}
+
+fn add_room_to_map(room: &Rectangle, tiles: &mut [Vec<Vec<Option<MapTile>>>])
+{
+    for column in tiles.iter_mut().take(room.corner_two.x + 1).skip(room.corner_one.x + 1)
+    {
+        for stack in column.iter_mut().take(room.corner_two.y + 1).skip(room.corner_one.y + 1)
+        {
+            stack[room.corner_one.z] = Some(MapTile::floor());
+        }
+    }
+}
+
+fn add_horizontal_corridor(origin: Coordinate, target: Coordinate, tiles: &mut [Vec<Vec<Option<MapTile>>>])
+{
+    for column in tiles.iter_mut().take(max(origin.x, target.x) + 1).skip(min(origin.x, target.x))
+    {
+        column[origin.y][origin.z] = Some(MapTile::floor());
+    }
+}
+
+fn add_vertical_corridor(origin: Coordinate, target: Coordinate, tiles: &mut [Vec<Vec<Option<MapTile>>>])
+{
+    for stack in tiles[target.x].iter_mut().take(max(origin.y, target.y) + 1).skip(min(origin.y, target.y))
+    {
+        stack[origin.z] = Some(MapTile::floor());
     }
 }
 
 pub fn add_walls(map: &mut Map)
 {
-    for x in 0..map.tiles.len() - 1
+    for x in 0..map.map_size.x
     {
-        for y in 0..map.tiles[x].len() - 1
+        for y in 0..map.map_size.y
         {
-            for z in 0..map.tiles[x][y].len() - 1
+            for z in 0..map.map_size.z
             {
                 let tile = map.tiles[x][y][z];
 
@@ -351,23 +1248,98 @@ pub fn add_walls(map: &mut Map)
                             let neighbor = neighbors[i];
                             let coordinate = coordinates[i];
 
-                            match neighbor
+                            if neighbor.is_none() && coordinate.x < map.map_size.x && coordinate.y < map.map_size.y
                             {
-                                None =>
-                                {
-                                    map.tiles[coordinate.x][coordinate.y][coordinate.z] = Some(MapTile::new(rltk::to_cp437('#'), 
-                                    RGB::from_f32(0.5, 0.5, 0.8),
-                                    RGB::named(rltk::BLACK), 
-                                    false, true, false, false)); 
-                                }
-                                _ => (),
+                                map.tiles[coordinate.x][coordinate.y][coordinate.z] = Some(MapTile::wall());
                             }
                         }
                     }
                     _ => (),
-                } 
+                }
             }
         }
     }
 }
 
+/// Picks a room on each lower deck to hold a ladder and carves a corridor to the spot above it
+/// when it isn't already floor. Returns the tops of the ladders, to be placed once walls are up.
+fn connect_decks(rooms: &[Rectangle], map: &mut Map, rng: &mut StdRng) -> Vec<Coordinate>
+{
+    let mut ladders: Vec<Coordinate> = Vec::new();
+
+    for z in 0..map.map_size.z.saturating_sub(1)
+    {
+        let upper_rooms: Vec<&Rectangle> = rooms.iter().filter(|room| room.corner_one.z == z).collect();
+        let lower_rooms: Vec<&Rectangle> = rooms.iter().filter(|room| room.corner_one.z == z + 1).collect();
+
+        if upper_rooms.is_empty() || lower_rooms.is_empty()
+        {
+            continue;
+        }
+
+        let bottom = lower_rooms[rng.gen_range(0..lower_rooms.len())].center();
+        let top = Coordinate::new(bottom.x, bottom.y, z);
+
+        if !matches!(map.get(top), Some(tile) if tile.passable)
+        {
+            let distance = |room: &&&Rectangle| (room.center().x as i32 - top.x as i32).abs() + (room.center().y as i32 - top.y as i32).abs();
+            let nearest_room = upper_rooms.iter().min_by_key(distance).unwrap();
+
+            add_horizontal_corridor(top, nearest_room.center(), &mut map.tiles);
+            add_vertical_corridor(top, nearest_room.center(), &mut map.tiles);
+        }
+
+        ladders.push(top);
+    }
+
+    ladders
+}
+
+/// Cuts glass panels, grates and open shafts into rooms that sit above floor on the next deck down,
+/// leaving the first room clear as it is where the player starts
+fn add_vertical_openings(rooms: &[Rectangle], map: &mut Map, rng: &mut StdRng)
+{
+    for room in rooms.iter().skip(1)
+    {
+        if room.corner_one.z + 1 >= map.map_size.z || rng.gen_range(0..2) == 0
+        {
+            continue;
+        }
+
+        let center = room.center();
+        let below = Coordinate::new(center.x, center.y, center.z + 1);
+
+        match map.get(below)
+        {
+            Some(below_tile) if below_tile.passable =>
+            {
+                match rng.gen_range(0..3)
+                {
+                    0 =>
+                    {
+                        // A 3x3 window set into the middle of the room
+                        for x in center.x - 1 ..= center.x + 1
+                        {
+                            for y in center.y - 1 ..= center.y + 1
+                            {
+                                let coordinate = Coordinate::new(x, y, center.z);
+                                let below_coordinate = Coordinate::new(x, y, center.z + 1);
+
+                                if let (Some(tile), Some(below_tile)) = (map.get(coordinate), map.get(below_coordinate))
+                                {
+                                    if tile.passable && below_tile.passable
+                                    {
+                                        map.set_tile(coordinate, MapTile::glass_floor());
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    1 => map.set_tile(center, MapTile::grate()),
+                    _ => map.set_tile(center, MapTile::open_shaft()),
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use specs_derive::Component;
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::map_tile::MapTile;
+
+
+/// A tile as an entity last saw it
+#[derive(Clone, Copy)]
+pub struct RememberedTile
+{
+    pub tile: MapTile,
+    pub turn: u64,
+}
+
+/// Where an entity last saw another entity
+#[derive(Clone, Copy, Debug)]
+pub struct RememberedEntity
+{
+    pub coordinate: Coordinate,
+    pub turn: u64,
+}
+
+//MapMemory
+/// Everything an entity has seen of the map and of other entities, and on which turn
+#[derive(Component, Default, Clone)]
+pub struct MapMemory
+{
+    pub tiles: HashMap<Coordinate, RememberedTile>,
+    pub entities: HashMap<Entity, RememberedEntity>,
+}
+
+impl MapMemory
+{
+    pub fn new() -> MapMemory
+    {
+        MapMemory::default()
+    }
+
+    pub fn remember_tile(&mut self, coordinate: Coordinate, tile: MapTile, turn: u64)
+    {
+        self.tiles.insert(coordinate, RememberedTile { tile, turn });
+    }
+
+    pub fn remember_entity(&mut self, entity: Entity, coordinate: Coordinate, turn: u64)
+    {
+        self.entities.insert(entity, RememberedEntity { coordinate, turn });
+    }
+
+    pub fn get_tile(&self, coordinate: Coordinate) -> Option<RememberedTile>
+    {
+        self.tiles.get(&coordinate).copied()
+    }
+
+    pub fn is_revealed(&self, coordinate: Coordinate) -> bool
+    {
+        self.tiles.contains_key(&coordinate)
+    }
+
+    /// Where and when the entity was last seen, if ever
+    pub fn last_known_position(&self, entity: Entity) -> Option<RememberedEntity>
+    {
+        self.entities.get(&entity).copied()
+    }
+
+    /// Takes on anything the other memory has seen no earlier than this one, so the sighting merged last wins a tie
+    pub fn merge(&mut self, other: &MapMemory)
+    {
+        for (coordinate, remembered) in other.tiles.iter()
+        {
+            match self.tiles.get(coordinate)
+            {
+                Some(own) if own.turn > remembered.turn => {}
+                _ => { self.tiles.insert(*coordinate, *remembered); }
+            }
+        }
+
+        for (entity, remembered) in other.entities.iter()
+        {
+            match self.entities.get(entity)
+            {
+                Some(own) if own.turn > remembered.turn => {}
+                _ => { self.entities.insert(*entity, *remembered); }
+            }
+        }
+    }
+}
+
+//SharedMemory
+/// Entities in the same group pool their map memories, like a crew keeping a shared station map
+#[derive(Component)]
+pub struct SharedMemory
+{
+    pub group: u32,
+}
@@ -1,30 +1,282 @@
 use rltk::RGB;
 
+/// Which way a ladder on a tile leads
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ladder
+{
+    Up,
+    Down,
+}
+
+/// Whether a door on a tile stands open, is shut or is shut and locked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Door
+{
+    Open,
+    Closed,
+    Locked,
+}
+
 #[derive(Clone, Copy)]
 pub struct MapTile
 {
+    /// What the tile is called when examined
+    pub name: &'static str,
     pub glyph: rltk::FontCharType,
     pub foreground_color: RGB,
     pub background_color: RGB,
     pub passable: bool,
     pub opaque: bool,
-    pub visible: bool,
-    pub revealed: bool,
+    /// Whether the deck below can be seen through this tile (glass, grates, open shafts)
+    pub transparent_floor: bool,
+    pub ladder: Option<Ladder>,
+    /// Holes in the hull, whatever gas reaches them is lost to space
+    pub open_to_space: bool,
+    /// Whether fire can take hold on the tile
+    pub flammable: bool,
+    pub door: Option<Door>,
+    /// Part of a medical bay, where the wounded go to be patched up
+    pub medical: bool,
+    /// Temperature the tile holds the air on and around it at, for reactor cores and cryogenic plant
+    pub temperature_source: Option<f32>,
+    /// Radiation the tile gives off each turn
+    pub radiation: f32,
+    /// Share of the radiation reaching the tile that it stops, from 0 to 1
+    pub shielding: f32,
 }
 
 impl MapTile
 {
-    pub fn new(glyph: rltk::FontCharType, foreground_color: RGB, background_color: RGB, passable: bool, opaque: bool, visible: bool, revealed: bool) -> MapTile
+    pub fn new(name: &'static str, glyph: rltk::FontCharType, foreground_color: RGB, background_color: RGB, passable: bool, opaque: bool) -> MapTile
     {
         MapTile 
         { 
+            name,
             glyph, 
             foreground_color, 
             background_color, 
             passable,
             opaque,
-            visible,
-            revealed,
+            transparent_floor: false,
+            ladder: None,
+            open_to_space: false,
+            flammable: false,
+            door: None,
+            medical: false,
+            temperature_source: None,
+            radiation: 0.0,
+            shielding: 0.0,
         }
     }
+
+    /// Plain deck plating
+    pub fn floor() -> MapTile
+    {
+        MapTile::new("deck plating", rltk::to_cp437('.'),
+                    RGB::from_f32(0.3, 0.3, 0.3),
+                    RGB::named(rltk::BLACK),
+                    true, false)
+    }
+
+    /// Deck covered in padding and clutter, as in crew quarters, which burns readily
+    pub fn padded_floor() -> MapTile
+    {
+        let mut tile = MapTile::new("padded deck", rltk::to_cp437('.'),
+                    RGB::from_f32(0.5, 0.35, 0.2),
+                    RGB::named(rltk::BLACK),
+                    true, false);
+        tile.flammable = true;
+        tile
+    }
+
+    /// Clean deck of a medical bay
+    pub fn medbay_floor() -> MapTile
+    {
+        let mut tile = MapTile::new("medical bay deck", rltk::to_cp437('.'),
+                    RGB::from_f32(0.6, 0.8, 0.8),
+                    RGB::named(rltk::BLACK),
+                    true, false);
+        tile.medical = true;
+        tile
+    }
+
+    /// Deck left blackened after a fire has burnt itself out
+    pub fn scorched_floor() -> MapTile
+    {
+        MapTile::new("scorched deck", rltk::to_cp437('.'),
+                    RGB::from_f32(0.2, 0.15, 0.1),
+                    RGB::named(rltk::BLACK),
+                    true, false)
+    }
+
+    /// Bulkhead wall
+    pub fn wall() -> MapTile
+    {
+        let mut tile = MapTile::new("bulkhead", rltk::to_cp437('#'),
+                    RGB::from_f32(0.5, 0.5, 0.8),
+                    RGB::named(rltk::BLACK),
+                    false, true);
+        tile.shielding = 0.5;
+        tile
+    }
+
+    /// Lead-lined bulkhead around a reactor, which stops nearly all radiation
+    pub fn shielded_wall() -> MapTile
+    {
+        let mut tile = MapTile::new("shielded bulkhead", rltk::to_cp437('#'),
+                    RGB::from_f32(0.55, 0.6, 0.45),
+                    RGB::named(rltk::BLACK),
+                    false, true);
+        tile.shielding = 0.95;
+        tile
+    }
+
+    /// Heart of the station's reactor, hot and giving off radiation
+    pub fn reactor_core() -> MapTile
+    {
+        let mut tile = MapTile::new("reactor core", rltk::to_cp437('Θ'),
+                    RGB::from_f32(0.4, 1.0, 0.4),
+                    RGB::from_f32(0.1, 0.25, 0.1),
+                    false, false);
+        tile.temperature_source = Some(80.0);
+        tile.radiation = 30.0;
+        tile
+    }
+
+    /// Deck of a cryogenic store, chilled far below freezing
+    pub fn cryo_floor() -> MapTile
+    {
+        let mut tile = MapTile::new("cryo deck", rltk::to_cp437('.'),
+                    RGB::from_f32(0.6, 0.9, 1.0),
+                    RGB::named(rltk::BLACK),
+                    true, false);
+        tile.temperature_source = Some(-60.0);
+        tile
+    }
+
+    /// Walkable glass panel looking onto the deck below
+    pub fn glass_floor() -> MapTile
+    {
+        let mut tile = MapTile::new("glass panel", rltk::to_cp437('.'),
+                    RGB::from_f32(0.4, 0.7, 0.8),
+                    RGB::from_f32(0.0, 0.1, 0.15),
+                    true, false);
+        tile.transparent_floor = true;
+        tile
+    }
+
+    /// Walkable metal grating looking onto the deck below
+    pub fn grate() -> MapTile
+    {
+        let mut tile = MapTile::new("grating", rltk::to_cp437('#'),
+                    RGB::from_f32(0.35, 0.35, 0.35),
+                    RGB::named(rltk::BLACK),
+                    true, false);
+        tile.transparent_floor = true;
+        tile
+    }
+
+    /// Railed off open lift shaft, nothing stops sight from falling through it
+    pub fn open_shaft() -> MapTile
+    {
+        let mut tile = MapTile::new("open shaft", rltk::to_cp437(' '),
+                    RGB::from_f32(0.3, 0.3, 0.3),
+                    RGB::named(rltk::BLACK),
+                    false, false);
+        tile.transparent_floor = true;
+        tile
+    }
+
+    /// Ladder down to the same spot on the deck below
+    pub fn ladder_down() -> MapTile
+    {
+        let mut tile = MapTile::new("ladder down", rltk::to_cp437('>'),
+                    RGB::from_f32(0.8, 0.8, 0.3),
+                    RGB::named(rltk::BLACK),
+                    true, false);
+        tile.ladder = Some(Ladder::Down);
+        tile
+    }
+
+    /// Ladder up to the same spot on the deck above
+    pub fn ladder_up() -> MapTile
+    {
+        let mut tile = MapTile::new("ladder up", rltk::to_cp437('<'),
+                    RGB::from_f32(0.8, 0.8, 0.3),
+                    RGB::named(rltk::BLACK),
+                    true, false);
+        tile.ladder = Some(Ladder::Up);
+        tile
+    }
+
+    /// Heap of wreckage left where a bulkhead was blown apart
+    pub fn rubble() -> MapTile
+    {
+        MapTile::new("rubble", rltk::to_cp437(','),
+                    RGB::from_f32(0.5, 0.4, 0.3),
+                    RGB::named(rltk::BLACK),
+                    true, false)
+    }
+
+    /// Hole torn through the hull, too jagged to climb through but open to space
+    pub fn hull_breach() -> MapTile
+    {
+        let mut tile = MapTile::new("hull breach", rltk::to_cp437('%'),
+                    RGB::from_f32(0.6, 0.3, 0.3),
+                    RGB::named(rltk::BLACK),
+                    false, false);
+        tile.open_to_space = true;
+        tile
+    }
+
+    /// Door left open, which can be walked and seen through
+    pub fn open_door() -> MapTile
+    {
+        let mut tile = MapTile::new("open door", rltk::to_cp437('\''),
+                    RGB::from_f32(0.7, 0.5, 0.3),
+                    RGB::named(rltk::BLACK),
+                    true, false);
+        tile.door = Some(Door::Open);
+        tile
+    }
+
+    /// Shut door, sealing off the air on either side until someone walks into it
+    pub fn closed_door() -> MapTile
+    {
+        let mut tile = MapTile::new("closed door", rltk::to_cp437('+'),
+                    RGB::from_f32(0.7, 0.5, 0.3),
+                    RGB::named(rltk::BLACK),
+                    false, true);
+        tile.door = Some(Door::Closed);
+        tile
+    }
+
+    /// Shut door with its lock engaged, which has to be hacked or blown open
+    pub fn locked_door() -> MapTile
+    {
+        let mut tile = MapTile::new("locked door", rltk::to_cp437('+'),
+                    RGB::from_f32(0.8, 0.2, 0.2),
+                    RGB::named(rltk::BLACK),
+                    false, true);
+        tile.door = Some(Door::Locked);
+        tile
+    }
+
+    /// Whether a path can be planned across the tile, which takes in doors that only need opening
+    pub fn can_path_through(&self) -> bool
+    {
+        self.passable || self.door == Some(Door::Closed)
+    }
+
+    /// Whether the tile is wreckage someone could patch back into whatever stood there before
+    pub fn needs_repair(&self) -> bool
+    {
+        self.open_to_space || self.name == MapTile::rubble().name
+    }
+
+    /// Whether gas can fill the tile, which is anything that is not solid bulkhead
+    pub fn holds_atmosphere(&self) -> bool
+    {
+        self.passable || self.transparent_floor || self.open_to_space
+    }
 }
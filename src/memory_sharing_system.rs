@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+
+use specs::prelude::*;
+
+use super::map_memory::{MapMemory, SharedMemory};
+
+pub struct MemorySharingSystem {}
+
+impl<'a> System<'a> for MemorySharingSystem
+{
+    type SystemData = (WriteStorage<'a, MapMemory>,
+                       ReadStorage<'a, SharedMemory>);
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let (mut memories, shared_memories) = data;
+        let mut pooled_memories: HashMap<u32, MapMemory> = HashMap::new();
+
+        for (memory, shared_memory) in (&memories, &shared_memories).join()
+        {
+            pooled_memories.entry(shared_memory.group).or_default().merge(memory);
+        }
+
+        for (memory, shared_memory) in (&mut memories, &shared_memories).join()
+        {
+            if let Some(pooled_memory) = pooled_memories.get(&shared_memory.group)
+            {
+                memory.merge(pooled_memory);
+            }
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+
+use rltk::{a_star_search, Algorithm2D, BaseMap, DijkstraMap, DistanceAlg, Point, SmallVec};
+
+use super::coordinate::Coordinate;
+use super::map::Map;
+use super::map_memory::MapMemory;
+
+
+/// One deck as an entity remembers it, so paths only cross tiles it has seen and knows to be passable
+pub struct RememberedDeck<'a>
+{
+    pub memory: &'a MapMemory,
+    pub map_size: Coordinate,
+    pub z: usize,
+}
+
+impl<'a> RememberedDeck<'a>
+{
+    pub fn new(memory: &'a MapMemory, map_size: Coordinate, z: usize) -> RememberedDeck<'a>
+    {
+        RememberedDeck { memory, map_size, z }
+    }
+
+    fn is_exit_valid(&self, x: i32, y: i32) -> bool
+    {
+        if x < 0 || y < 0 || x >= self.map_size.x as i32 || y >= self.map_size.y as i32
+        {
+            return false;
+        }
+
+        match self.memory.get_tile(Coordinate::new(x as usize, y as usize, self.z))
+        {
+            Some(remembered) => remembered.tile.can_path_through(),
+            None => false,
+        }
+    }
+
+    pub fn coordinate_to_idx(&self, coordinate: Coordinate) -> usize
+    {
+        coordinate.y * self.map_size.x + coordinate.x
+    }
+
+    pub fn idx_to_coordinate(&self, idx: usize) -> Coordinate
+    {
+        Coordinate::new(idx % self.map_size.x, idx / self.map_size.x, self.z)
+    }
+}
+
+impl<'a> Algorithm2D for RememberedDeck<'a>
+{
+    fn dimensions(&self) -> Point
+    {
+        Point::new(self.map_size.x, self.map_size.y)
+    }
+}
+
+impl<'a> BaseMap for RememberedDeck<'a>
+{
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]>
+    {
+        let mut exits = SmallVec::new();
+        let coordinate = self.idx_to_coordinate(idx);
+        let (x, y) = (coordinate.x as i32, coordinate.y as i32);
+        let width = self.map_size.x as i32;
+
+        for (delta_x, delta_y) in [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)]
+        {
+            if self.is_exit_valid(x + delta_x, y + delta_y)
+            {
+                let cost = if delta_x != 0 && delta_y != 0 { 1.45 } else { 1.0 };
+                exits.push((((y + delta_y) * width + x + delta_x) as usize, cost));
+            }
+        }
+
+        exits
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32
+    {
+        let width = self.map_size.x;
+        let point1 = Point::new(idx1 % width, idx1 / width);
+        let point2 = Point::new(idx2 % width, idx2 / width);
+
+        DistanceAlg::Pythagoras.distance2d(point1, point2)
+    }
+}
+
+/// One deck as it really is, for those who know the station by heart. Paths may end on the goal even when it can't
+/// be stood on, so a route can lead up to something to be worked on.
+pub struct StationDeck<'a>
+{
+    pub map: &'a Map,
+    pub z: usize,
+    pub goal: Coordinate,
+}
+
+impl<'a> StationDeck<'a>
+{
+    fn is_exit_valid(&self, x: i32, y: i32) -> bool
+    {
+        if x < 0 || y < 0 || x >= self.map.map_size.x as i32 || y >= self.map.map_size.y as i32
+        {
+            return false;
+        }
+
+        let coordinate = Coordinate::new(x as usize, y as usize, self.z);
+
+        coordinate == self.goal || self.map.get(coordinate).map(|tile| tile.can_path_through()).unwrap_or(false)
+    }
+
+    pub fn coordinate_to_idx(&self, coordinate: Coordinate) -> usize
+    {
+        coordinate.y * self.map.map_size.x + coordinate.x
+    }
+
+    pub fn idx_to_coordinate(&self, idx: usize) -> Coordinate
+    {
+        Coordinate::new(idx % self.map.map_size.x, idx / self.map.map_size.x, self.z)
+    }
+}
+
+impl<'a> Algorithm2D for StationDeck<'a>
+{
+    fn dimensions(&self) -> Point
+    {
+        Point::new(self.map.map_size.x, self.map.map_size.y)
+    }
+}
+
+impl<'a> BaseMap for StationDeck<'a>
+{
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]>
+    {
+        let mut exits = SmallVec::new();
+        let coordinate = self.idx_to_coordinate(idx);
+        let (x, y) = (coordinate.x as i32, coordinate.y as i32);
+        let width = self.map.map_size.x as i32;
+
+        for (delta_x, delta_y) in [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)]
+        {
+            if self.is_exit_valid(x + delta_x, y + delta_y)
+            {
+                let cost = if delta_x != 0 && delta_y != 0 { 1.45 } else { 1.0 };
+                exits.push((((y + delta_y) * width + x + delta_x) as usize, cost));
+            }
+        }
+
+        exits
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32
+    {
+        let width = self.map.map_size.x;
+        let point1 = Point::new(idx1 % width, idx1 / width);
+        let point2 = Point::new(idx2 % width, idx2 / width);
+
+        DistanceAlg::Pythagoras.distance2d(point1, point2)
+    }
+}
+
+/// Steps from one coordinate to another over the station as it really is, through doors that only need opening,
+/// not including the start. Gives nothing when the two are on different decks or no route links them.
+pub fn station_path(map: &Map, from: Coordinate, to: Coordinate) -> Option<Vec<Coordinate>>
+{
+    if from.z != to.z || from == to
+    {
+        return None;
+    }
+
+    let deck = StationDeck { map, z: from.z, goal: to };
+    let path = a_star_search(deck.coordinate_to_idx(from), deck.coordinate_to_idx(to), &deck);
+
+    match path.success
+    {
+        true => Some(path.steps.iter().skip(1).map(|idx| deck.idx_to_coordinate(*idx)).collect()),
+        false => None,
+    }
+}
+
+/// Steps from one coordinate to another over remembered tiles on the same deck, not including the start.
+/// Gives nothing when the two are on different decks or no known route links them.
+pub fn remembered_path(memory: &MapMemory, map_size: Coordinate, from: Coordinate, to: Coordinate) -> Option<Vec<Coordinate>>
+{
+    if from.z != to.z || from == to
+    {
+        return None;
+    }
+
+    let deck = RememberedDeck::new(memory, map_size, from.z);
+
+    if !deck.is_exit_valid(to.x as i32, to.y as i32)
+    {
+        return None;
+    }
+
+    let path = a_star_search(deck.coordinate_to_idx(from), deck.coordinate_to_idx(to), &deck);
+
+    match path.success
+    {
+        true => Some(path.steps.iter().skip(1).map(|idx| deck.idx_to_coordinate(*idx)).collect()),
+        false => None,
+    }
+}
+
+/// Next step towards the nearest unexplored part of the deck, found with a Dijkstra map seeded from every
+/// unrevealed tile next to a remembered passable one. Tiles in view that are still unrevealed hold nothing
+/// to see, so they are not worth walking to. Gives nothing once the deck is fully explored.
+pub fn exploration_step(memory: &MapMemory, map_size: Coordinate, visible_tiles: &HashSet<Coordinate>, from: Coordinate) -> Option<Coordinate>
+{
+    let deck = RememberedDeck::new(memory, map_size, from.z);
+    let mut unexplored: Vec<usize> = Vec::new();
+
+    for (coordinate, remembered) in memory.tiles.iter()
+    {
+        if coordinate.z != from.z || !remembered.tile.can_path_through()
+        {
+            continue;
+        }
+
+        for (delta_x, delta_y) in [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)]
+        {
+            let (x, y) = (coordinate.x as i32 + delta_x, coordinate.y as i32 + delta_y);
+
+            if x < 0 || y < 0 || x >= map_size.x as i32 || y >= map_size.y as i32
+            {
+                continue;
+            }
+
+            let neighbor = Coordinate::new(x as usize, y as usize, from.z);
+
+            if !memory.is_revealed(neighbor) && !visible_tiles.contains(&neighbor)
+            {
+                unexplored.push(deck.coordinate_to_idx(neighbor));
+            }
+        }
+    }
+
+    if unexplored.is_empty()
+    {
+        return None;
+    }
+
+    let dijkstra_map = DijkstraMap::new(map_size.x, map_size.y, &unexplored, &deck, (map_size.x * map_size.y) as f32);
+    let from_idx = deck.coordinate_to_idx(from);
+
+    if dijkstra_map.map[from_idx] == f32::MAX
+    {
+        return None;
+    }
+
+    DijkstraMap::find_lowest_exit(&dijkstra_map, from_idx, &deck).filter(|idx| dijkstra_map.map[*idx] < dijkstra_map.map[from_idx])
+                                                                   .map(|idx| deck.idx_to_coordinate(idx))
+}
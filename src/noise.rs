@@ -0,0 +1,150 @@
+use std::collections::{BinaryHeap, HashMap};
+
+use specs_derive::Component;
+use specs::prelude::*;
+
+use super::ai::Brain;
+use super::coordinate::Coordinate;
+use super::map::Map;
+use super::map_tile::Ladder;
+
+
+/// How loud things are, as the tiles of open air their sound carries across before dying away
+pub const FOOTSTEP_NOISE: i32 = 5;
+pub const SNEAKING_FOOTSTEP_NOISE: i32 = 1;
+pub const DOOR_NOISE: i32 = 8;
+pub const GUNSHOT_NOISE: i32 = 20;
+pub const BLAST_NOISE: i32 = 30;
+
+/// Loudness lost getting through a bulkhead or shut door, and through the deck plating to the deck above or below
+const WALL_DAMPING: i32 = 6;
+const DECK_DAMPING: i32 = 4;
+/// Pressure below which the air is too thin to carry sound, in kilopascals
+const MINIMUM_PRESSURE_FOR_SOUND: f32 = 10.0;
+/// Closest a sneaking player in dim light can be before being spotted
+const SNEAK_SPOT_RANGE: i32 = 2;
+
+//Sneaking
+/// Moving slowly and quietly, keeping to the shadows
+#[derive(Component)]
+pub struct Sneaking {}
+
+/// Whether someone at a spot is too well hidden to be made out by a watcher, as a sneaking player in dim light is
+/// from any further away than `SNEAK_SPOT_RANGE`
+pub fn hidden_in_shadow(map: &Map, sneaking: bool, at: Coordinate, watcher: Coordinate) -> bool
+{
+    let distance = (at.x as i32 - watcher.x as i32).abs().max((at.y as i32 - watcher.y as i32).abs());
+
+    sneaking && map.is_dim(at) && distance > SNEAK_SPOT_RANGE
+}
+
+/// How loud a noise from the origin still is at every tile it reaches. Sound spreads through the air, losing one
+/// for each tile crossed, goes through bulkheads and doors and between decks at a much higher cost, and does not
+/// cross vacuum at all.
+pub fn noise_spread(map: &Map, origin: Coordinate, loudness: i32) -> HashMap<Coordinate, i32>
+{
+    let mut heard: HashMap<Coordinate, i32> = HashMap::new();
+    // Loudest first, coordinates only break ties so the order never depends on anything but the map
+    let mut frontier: BinaryHeap<(i32, usize, usize, usize)> = BinaryHeap::new();
+
+    frontier.push((loudness, origin.z, origin.y, origin.x));
+
+    while let Some((left, z, y, x)) = frontier.pop()
+    {
+        let coordinate = Coordinate::new(x, y, z);
+
+        if left <= 0 || heard.contains_key(&coordinate)
+        {
+            continue;
+        }
+        heard.insert(coordinate, left);
+
+        for (neighbour, cost) in sound_exits(map, coordinate)
+        {
+            if left - cost > 0 && !heard.contains_key(&neighbour)
+            {
+                frontier.push((left - cost, neighbour.z, neighbour.y, neighbour.x));
+            }
+        }
+    }
+
+    heard
+}
+
+/// Where sound can go from a tile and how much loudness it loses on the way. Sound that has gone into a bulkhead
+/// only comes out into the air on the far side, it doesn't travel along inside it.
+fn sound_exits(map: &Map, coordinate: Coordinate) -> Vec<(Coordinate, i32)>
+{
+    let mut exits: Vec<(Coordinate, i32)> = Vec::new();
+    let tile = match map.get(coordinate)
+    {
+        Some(tile) => tile,
+        None => return exits,
+    };
+    let in_air = tile.holds_atmosphere();
+    let carries_sound = |coordinate: Coordinate| map.atmosphere(coordinate).pressure() >= MINIMUM_PRESSURE_FOR_SOUND;
+
+    for (delta_x, delta_y) in [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)]
+    {
+        let (x, y) = (coordinate.x as i32 + delta_x, coordinate.y as i32 + delta_y);
+
+        if x < 0 || y < 0 || x >= map.map_size.x as i32 || y >= map.map_size.y as i32
+        {
+            continue;
+        }
+
+        let neighbour = Coordinate::new(x as usize, y as usize, coordinate.z);
+
+        match map.get(neighbour)
+        {
+            Some(next) if !next.holds_atmosphere() && in_air => exits.push((neighbour, WALL_DAMPING)),
+            Some(next) if next.holds_atmosphere() && carries_sound(neighbour) => exits.push((neighbour, 1)),
+            _ => {}
+        }
+    }
+
+    if !in_air
+    {
+        return exits;
+    }
+
+    // Up and down through ladders and open floors carries well, anywhere else only through the plating
+    let deck_cost = |open: bool| if open { 1 } else { DECK_DAMPING };
+
+    if coordinate.z + 1 < map.map_size.z
+    {
+        let below = Coordinate::new(coordinate.x, coordinate.y, coordinate.z + 1);
+
+        if map.get(below).map(|below| below.holds_atmosphere()).unwrap_or(false) && carries_sound(below)
+        {
+            exits.push((below, deck_cost(tile.transparent_floor || tile.ladder == Some(Ladder::Down))));
+        }
+    }
+    if coordinate.z > 0
+    {
+        let above = Coordinate::new(coordinate.x, coordinate.y, coordinate.z - 1);
+
+        if let Some(above_tile) = map.get(above).filter(|above_tile| above_tile.holds_atmosphere() && carries_sound(above))
+        {
+            exits.push((above, deck_cost(above_tile.transparent_floor || tile.ladder == Some(Ladder::Up))));
+        }
+    }
+
+    exits
+}
+
+/// Makes a noise at the origin, which every NPC it still reaches hears and comes to look into
+pub fn make_noise(entity_system: &World, origin: Coordinate, loudness: i32)
+{
+    let spread = noise_spread(&entity_system.fetch::<Map>(), origin, loudness);
+    let coordinates = entity_system.read_storage::<Coordinate>();
+    let mut brains = entity_system.write_storage::<Brain>();
+
+    for (brain, coordinate) in (&mut brains, &coordinates).join()
+    {
+        if spread.contains_key(coordinate)
+        {
+            brain.heard = Some(origin);
+        }
+    }
+}
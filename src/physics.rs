@@ -0,0 +1,67 @@
+use specs_derive::Component;
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::map::Map;
+
+
+/// Drop in pressure to the next tile that drags anything loose along with the escaping air under gravity, and the
+/// much smaller drop that does it in zero-g, in kilopascals
+pub const DECOMPRESSION_PUSH: f32 = 30.0;
+pub const ZERO_G_DECOMPRESSION_PUSH: f32 = 10.0;
+/// Chance in one per turn an item resting in zero-g drifts off
+pub const FLOAT_CHANCE: i32 = 8;
+/// Suit oxygen a burst of the suit thrusters uses
+pub const THRUSTER_OXYGEN: i32 = 1;
+
+//Velocity
+/// Drifting through zero-g a tile a turn in one direction, until it hits something, grabs hold of a wall or reaches
+/// gravity again
+#[derive(Component, Clone, Copy)]
+pub struct Velocity
+{
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Whether there is a bulkhead, door or anything else solid next to the tile to grab hold of or push off from
+pub fn has_handhold(map: &Map, coordinate: Coordinate) -> bool
+{
+    [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)].iter().any(|(delta_x, delta_y)|
+    {
+        let (x, y) = (coordinate.x as i32 + delta_x, coordinate.y as i32 + delta_y);
+        x >= 0 && y >= 0 && map.get(Coordinate::new(x as usize, y as usize, coordinate.z)).map(|tile| !tile.passable).unwrap_or(false)
+    })
+}
+
+/// The tile one step along from a coordinate, if it is on the map
+pub fn step(map: &Map, coordinate: Coordinate, delta_x: i32, delta_y: i32) -> Option<Coordinate>
+{
+    let (x, y) = (coordinate.x as i32 + delta_x, coordinate.y as i32 + delta_y);
+
+    match x >= 0 && y >= 0 && (x as usize) < map.map_size.x && (y as usize) < map.map_size.y
+    {
+        true => Some(Coordinate::new(x as usize, y as usize, coordinate.z)),
+        false => None,
+    }
+}
+
+/// The way the air is rushing out of a tile and how hard, towards whichever neighbour has the lowest pressure
+pub fn decompression_pull(map: &Map, coordinate: Coordinate) -> Option<((i32, i32), f32)>
+{
+    let here = map.atmosphere(coordinate).pressure();
+
+    [(-1, 0), (1, 0), (0, -1), (0, 1)].iter()
+        .filter_map(|(delta_x, delta_y)|
+        {
+            let neighbour = step(map, coordinate, *delta_x, *delta_y)?;
+            let tile = map.get(neighbour)?;
+
+            match tile.holds_atmosphere()
+            {
+                true => Some(((*delta_x, *delta_y), here - map.atmosphere(neighbour).pressure())),
+                false => None,
+            }
+        })
+        .max_by(|(_first, drop), (_second, other_drop)| drop.total_cmp(other_drop))
+}
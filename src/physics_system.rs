@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+
+use rand::Rng;
+use rand::rngs::StdRng;
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::game_log::GameLog;
+use super::map::Map;
+use super::physics::*;
+use super::station_network::Turret;
+
+
+/// Moves whatever is loose for a turn. Air rushing out towards a breach drags things along, a tile under gravity but
+/// far enough to set them drifting in zero-g. Anything drifting carries on a tile a turn until it hits something or
+/// reaches gravity, and items left lying in zero-g float off now and then.
+pub struct PhysicsSystem {}
+
+impl<'a> System<'a> for PhysicsSystem
+{
+    type SystemData = (ReadExpect<'a, Map>,
+                       WriteExpect<'a, StdRng>,
+                       WriteExpect<'a, GameLog>,
+                       Entities<'a>,
+                       WriteStorage<'a, Coordinate>,
+                       WriteStorage<'a, Velocity>,
+                       WriteStorage<'a, Viewshed>,
+                       ReadStorage<'a, CombatStats>,
+                       ReadStorage<'a, Item>,
+                       ReadStorage<'a, Turret>,
+                       ReadStorage<'a, Player>);
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let (map, mut rng, mut log, entities, mut coordinates, mut velocities, mut viewsheds, combat_stats, items, turrets, players) = data;
+
+        // Bodies can't drift through each other, items can come to rest on anything
+        let mut occupied: HashSet<Coordinate> = (&combat_stats, &coordinates).join().map(|(_stats, coordinate)| *coordinate).collect();
+        let loose: Vec<(Entity, Coordinate)> = (&entities, &coordinates, !&turrets).join()
+                                                                                   .filter(|(entity, _coordinate, _turret)| combat_stats.get(*entity).is_some() || items.get(*entity).is_some())
+                                                                                   .map(|(entity, coordinate, _turret)| (entity, *coordinate))
+                                                                                   .collect();
+
+        for (entity, coordinate) in loose.iter()
+        {
+            let zero_g = !map.has_gravity(*coordinate);
+            let push = match zero_g
+            {
+                true => ZERO_G_DECOMPRESSION_PUSH,
+                false => DECOMPRESSION_PUSH,
+            };
+            let ((delta_x, delta_y), _drop) = match decompression_pull(&map, *coordinate).filter(|(_direction, drop)| *drop >= push)
+            {
+                Some(pull) => pull,
+                None => continue,
+            };
+
+            velocities.insert(*entity, Velocity { x: delta_x, y: delta_y }).expect("Unable to set velocity");
+
+            if players.get(*entity).is_some()
+            {
+                match zero_g
+                {
+                    true => log.add("The escaping air sends you tumbling!"),
+                    false => log.add("The escaping air drags you along!"),
+                }
+            }
+        }
+
+        for (entity, coordinate) in loose.iter()
+        {
+            if items.get(*entity).is_some() && !map.has_gravity(*coordinate) && velocities.get(*entity).is_none() && rng.gen_range(0..FLOAT_CHANCE) == 0
+            {
+                let (delta_x, delta_y) = [(-1, 0), (1, 0), (0, -1), (0, 1)][rng.gen_range(0..4)];
+                velocities.insert(*entity, Velocity { x: delta_x, y: delta_y }).expect("Unable to set velocity");
+            }
+        }
+
+        let drifting: Vec<(Entity, Velocity)> = (&entities, &velocities).join().map(|(entity, velocity)| (entity, *velocity)).collect();
+
+        for (entity, velocity) in drifting
+        {
+            let position = match coordinates.get(entity)
+            {
+                Some(position) => *position,
+                None =>
+                {
+                    velocities.remove(entity);
+                    continue;
+                }
+            };
+            let is_body = combat_stats.get(entity).is_some();
+            let next = step(&map, position, velocity.x, velocity.y).filter(|next|
+            {
+                map.get(*next).map(|tile| tile.passable).unwrap_or(false) && !(is_body && occupied.contains(next))
+            });
+
+            let next = match next
+            {
+                Some(next) => next,
+                None =>
+                {
+                    velocities.remove(entity);
+                    if players.get(entity).is_some() && !map.has_gravity(position)
+                    {
+                        log.add("You thump into something and come to a stop.");
+                    }
+                    continue;
+                }
+            };
+
+            if is_body
+            {
+                occupied.remove(&position);
+                occupied.insert(next);
+            }
+            if let Some(coordinate) = coordinates.get_mut(entity)
+            {
+                *coordinate = next;
+            }
+            if let Some(viewshed) = viewsheds.get_mut(entity)
+            {
+                viewshed.dirty = true;
+            }
+
+            // Anything drifting into gravity drops out of the air, so under gravity a push only carries a tile
+            if map.has_gravity(next)
+            {
+                velocities.remove(entity);
+            }
+        }
+    }
+}
@@ -0,0 +1,596 @@
+use std::cmp::{max, min};
+
+use specs::prelude::*;
+
+use super::character::player_attributes;
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::factions::{hold_player_to_account, is_hostile, FactionMember, Relations};
+use super::game_clock::GameClock;
+use super::game_log::GameLog;
+use super::key_bindings::Action;
+use super::life_support::{LifeSupport, MAX_CONDITION};
+use super::map::Map;
+use super::map_tile::{Door, Ladder};
+use super::noise::{make_noise, Sneaking, DOOR_NOISE, FOOTSTEP_NOISE, SNEAKING_FOOTSTEP_NOISE};
+use super::physics::{has_handhold, Velocity, THRUSTER_OXYGEN};
+use super::skills::{player_gain_experience, player_skill_bonus, player_skill_check, Skill, SUCCESS_EXPERIENCE};
+use super::status_effects::{StatusEffects, StatusKind};
+use super::survival::{Consumable, Needs};
+
+
+/// Chances in percent an untrained hand gets through a locked door, patches a hull breach or keeps their footing
+/// crossing a hazard
+const HACKING_BASE_CHANCE: i32 = 25;
+const REPAIR_BASE_CHANCE: i32 = 40;
+const EVA_BASE_CHANCE: i32 = 60;
+const PILOTING_BASE_CHANCE: i32 = 70;
+/// Pressure below which a tile is near enough vacuum to be a hazard, in kilopascals
+const HAZARD_PRESSURE: f32 = 30.0;
+/// Weight given to stopping bleeding when picking what to use, on top of the hit points restored
+const BLEEDING_TREATMENT_BENEFIT: f32 = 0.25;
+
+/// Carries out an action for the player, returning whether it used up the player's turn
+pub fn player_act(entity_system: &mut World, action: Action) -> bool
+{
+    if let Some((delta_x, delta_y)) = action.movement_delta()
+    {
+        return try_move_player(delta_x, delta_y, entity_system);
+    }
+
+    match action
+    {
+        Action::Wait =>
+        {
+            grab_hold(entity_system);
+            true
+        }
+        Action::PickUp => try_pick_up(entity_system),
+        Action::Descend => try_climb(entity_system, Ladder::Down),
+        Action::Ascend => try_climb(entity_system, Ladder::Up),
+        Action::Consume => try_consume(entity_system),
+        Action::Sneak => toggle_sneaking(entity_system),
+        _ => false,
+    }
+}
+
+pub fn try_move_player(delta_x: i32, delta_y: i32, entity_system: &mut World) -> bool
+{
+    let stunned = (&entity_system.read_storage::<Player>(), &entity_system.read_storage::<StatusEffects>()).join()
+                                                                                                          .any(|(_player, effects)| !effects.can_move());
+
+    // Being stunned still costs the turn, the player just staggers in place
+    if stunned
+    {
+        entity_system.fetch_mut::<GameLog>().add("You are too stunned to move.");
+        return true;
+    }
+
+    let target = match (&entity_system.read_storage::<Player>(), &entity_system.read_storage::<Coordinate>()).join().next()
+    {
+        Some((_player, coordinate)) => Coordinate::new((coordinate.x as i32 + delta_x) as usize, (coordinate.y as i32 + delta_y) as usize, coordinate.z),
+        None => return false,
+    };
+
+    // Walking into someone strikes them if they are an enemy, and anyone else is just in the way
+    let occupant = (&entity_system.entities(), &entity_system.read_storage::<CombatStats>(), &entity_system.read_storage::<Coordinate>()).join()
+                                                         .find(|(_entity, _stats, coordinate)| **coordinate == target)
+                                                         .map(|(entity, _stats, _coordinate)| entity);
+    if let Some(occupant) = occupant
+    {
+        return player_melee(entity_system, occupant);
+    }
+
+    let (tile, hazardous) =
+    {
+        let map = entity_system.fetch::<Map>();
+        let tile = map.get(target);
+        let hazardous = tile.map(|tile| tile.passable).unwrap_or(false) && (map.is_burning(target) || map.atmosphere(target).pressure() < HAZARD_PRESSURE);
+        (tile, hazardous)
+    };
+
+    // Walking into a door opens it, and walking into a breach tries to patch it, either way taking the turn
+    if let Some(tile) = tile
+    {
+        match tile.door
+        {
+            Some(Door::Closed) =>
+            {
+                entity_system.fetch_mut::<Map>().open_door(target);
+                entity_system.fetch_mut::<GameLog>().add("You open the door.");
+                make_noise(entity_system, target, DOOR_NOISE);
+                return true;
+            }
+            Some(Door::Locked) =>
+            {
+                match player_skill_check(entity_system, Skill::Hacking, HACKING_BASE_CHANCE)
+                {
+                    true =>
+                    {
+                        entity_system.fetch_mut::<Map>().open_door(target);
+                        entity_system.fetch_mut::<GameLog>().add("You bypass the lock and the door slides open.");
+                        make_noise(entity_system, target, DOOR_NOISE);
+                    }
+                    false => entity_system.fetch_mut::<GameLog>().add("The lock holds."),
+                }
+                return true;
+            }
+            _ => {}
+        }
+
+        if tile.open_to_space && !tile.passable
+        {
+            match player_skill_check(entity_system, Skill::Repair, REPAIR_BASE_CHANCE)
+            {
+                true =>
+                {
+                    entity_system.fetch_mut::<Map>().repair_tile(target);
+                    entity_system.fetch_mut::<GameLog>().add("You patch the hull breach.");
+                }
+                false => entity_system.fetch_mut::<GameLog>().add("Your patch fails to hold."),
+            }
+            return true;
+        }
+    }
+
+    // Walking into a worn life support unit sets about fixing it rather than stepping over it
+    let worn_unit = (&entity_system.entities(), &entity_system.read_storage::<LifeSupport>(), &entity_system.read_storage::<Coordinate>()).join()
+                                                         .find(|(_entity, unit, coordinate)| **coordinate == target && unit.condition < MAX_CONDITION)
+                                                         .map(|(entity, unit, _coordinate)| (entity, unit.kind.name()));
+    if let Some((unit, name)) = worn_unit
+    {
+        match player_skill_check(entity_system, Skill::Repair, REPAIR_BASE_CHANCE)
+        {
+            true =>
+            {
+                if let Some(unit) = entity_system.write_storage::<LifeSupport>().get_mut(unit)
+                {
+                    unit.condition = MAX_CONDITION;
+                }
+                entity_system.fetch_mut::<GameLog>().add(format!("You get the {} running smoothly again.", name));
+            }
+            false => entity_system.fetch_mut::<GameLog>().add(format!("You can't find the fault in the {}.", name)),
+        }
+        return true;
+    }
+
+    let zero_g =
+    {
+        let map = entity_system.fetch::<Map>();
+        let position = Coordinate::new((target.x as i32 - delta_x) as usize, (target.y as i32 - delta_y) as usize, target.z);
+        !map.has_gravity(position) || !map.has_gravity(target)
+    };
+    if zero_g
+    {
+        return push_off(entity_system, delta_x, delta_y);
+    }
+
+    if hazardous && !player_skill_check(entity_system, Skill::Eva, EVA_BASE_CHANCE)
+    {
+        entity_system.fetch_mut::<GameLog>().add("You lose your footing.");
+        return true;
+    }
+
+    let mut moved_to: Option<Coordinate> = None;
+    {
+        let mut coordinates = entity_system.write_storage::<Coordinate>();
+        let mut players = entity_system.write_storage::<Player>();
+        let mut viewsheds = entity_system.write_storage::<Viewshed>();
+        let map = entity_system.fetch::<Map>();
+
+        for (_player, coordinate, viewshed) in (&mut players, &mut coordinates, &mut viewsheds).join()
+        {
+            let target_coordinate = Coordinate { x: (coordinate.x as i32 + delta_x) as usize, y: (coordinate.y as i32 + delta_y) as usize, z : coordinate.z};
+            let passable = match map.get(target_coordinate)
+            {
+                Some(tile) => tile.passable,
+                None => true,
+            };
+
+            if passable
+            {
+                coordinate.x = min(map.map_size.x - 1 , max(0, target_coordinate.x));
+                coordinate.y = min(map.map_size.y - 1, max(0, target_coordinate.y));
+                viewshed.dirty = true;
+                moved_to = Some(*coordinate);
+            }
+        }
+    }
+
+    let destination = match moved_to
+    {
+        Some(destination) => destination,
+        None => return false,
+    };
+
+    // Creeping along takes an extra turn for each step but barely makes a sound
+    match player_is_sneaking(entity_system)
+    {
+        true =>
+        {
+            entity_system.write_resource::<GameClock>().advance();
+            make_noise(entity_system, destination, SNEAKING_FOOTSTEP_NOISE);
+        }
+        false => make_noise(entity_system, destination, FOOTSTEP_NOISE),
+    }
+
+    true
+}
+
+/// Sets the player drifting through zero-g in a direction, pushing off from the deck, a wall or anything else solid
+/// within reach, or firing the suit thrusters when there is nothing to push against, which takes a piloting roll.
+/// The physics system carries them along from there.
+fn push_off(entity_system: &mut World, delta_x: i32, delta_y: i32) -> bool
+{
+    let player = (&entity_system.entities(), &entity_system.read_storage::<Player>(), &entity_system.read_storage::<Coordinate>()).join()
+                                                                                                                                 .map(|(entity, _player, coordinate)| (entity, *coordinate))
+                                                                                                                                 .next();
+    let (player, position) = match player
+    {
+        Some(player) => player,
+        None => return false,
+    };
+    let (passable, handhold) =
+    {
+        let map = entity_system.fetch::<Map>();
+        let target = Coordinate::new((position.x as i32 + delta_x) as usize, (position.y as i32 + delta_y) as usize, position.z);
+        (map.get(target).map(|tile| tile.passable).unwrap_or(false), map.has_gravity(position) || has_handhold(&map, position))
+    };
+
+    if !passable
+    {
+        return false;
+    }
+
+    if !handhold
+    {
+        {
+            let mut needs = entity_system.write_storage::<Needs>();
+
+            match needs.get_mut(player).filter(|needs| needs.suit_oxygen >= THRUSTER_OXYGEN)
+            {
+                Some(needs) => needs.suit_oxygen -= THRUSTER_OXYGEN,
+                None =>
+                {
+                    entity_system.fetch_mut::<GameLog>().add("There is nothing to push off from and your thrusters are dry.");
+                    return true;
+                }
+            }
+        }
+
+        // A clumsy burst spends the oxygen all the same
+        if !player_skill_check(entity_system, Skill::Piloting, PILOTING_BASE_CHANCE)
+        {
+            entity_system.fetch_mut::<GameLog>().add("Your thrusters sputter and you tumble in place.");
+            return true;
+        }
+        entity_system.fetch_mut::<GameLog>().add("You fire your suit thrusters.");
+    }
+
+    entity_system.write_storage::<Velocity>().insert(player, Velocity { x: delta_x, y: delta_y }).expect("Unable to set velocity");
+    true
+}
+
+/// Stops the player drifting if there is anything within reach to grab hold of
+fn grab_hold(entity_system: &mut World)
+{
+    let player = (&entity_system.entities(), &entity_system.read_storage::<Player>(), &entity_system.read_storage::<Coordinate>(), &entity_system.read_storage::<Velocity>()).join()
+                                                                                                                                                                         .map(|(entity, _player, coordinate, _velocity)| (entity, *coordinate))
+                                                                                                                                                                         .next();
+    if let Some((player, position)) = player
+    {
+        if has_handhold(&entity_system.fetch::<Map>(), position)
+        {
+            entity_system.write_storage::<Velocity>().remove(player);
+            entity_system.fetch_mut::<GameLog>().add("You grab hold and stop drifting.");
+        }
+    }
+}
+
+fn player_is_sneaking(entity_system: &World) -> bool
+{
+    (&entity_system.read_storage::<Player>(), &entity_system.read_storage::<Sneaking>()).join().next().is_some()
+}
+
+/// Starts or stops sneaking, which takes a turn
+fn toggle_sneaking(entity_system: &mut World) -> bool
+{
+    let player = (&entity_system.entities(), &entity_system.read_storage::<Player>()).join().map(|(entity, _player)| entity).next();
+    let player = match player
+    {
+        Some(player) => player,
+        None => return false,
+    };
+
+    match player_is_sneaking(entity_system)
+    {
+        true =>
+        {
+            entity_system.write_storage::<Sneaking>().remove(player);
+            entity_system.fetch_mut::<GameLog>().add("You stop sneaking.");
+        }
+        false =>
+        {
+            entity_system.write_storage::<Sneaking>().insert(player, Sneaking {}).expect("Unable to start sneaking");
+            entity_system.fetch_mut::<GameLog>().add("You start moving slowly, keeping to the shadows.");
+        }
+    }
+
+    true
+}
+
+/// Picks up the first item lying on the player's tile
+pub fn try_pick_up(entity_system: &mut World) -> bool
+{
+    let entities = entity_system.entities();
+    let players = entity_system.read_storage::<Player>();
+    let items = entity_system.read_storage::<Item>();
+    let names = entity_system.read_storage::<Name>();
+    let mut coordinates = entity_system.write_storage::<Coordinate>();
+    let mut backpacks = entity_system.write_storage::<InBackpack>();
+    let mut log = entity_system.fetch_mut::<GameLog>();
+
+    let player = (&entities, &players, &coordinates).join().map(|(entity, _player, coordinate)| (entity, *coordinate)).next();
+
+    if let Some((player_entity, player_coordinate)) = player
+    {
+        let item = (&entities, &items, &coordinates).join()
+                                                     .find(|(_entity, _item, coordinate)| **coordinate == player_coordinate)
+                                                     .map(|(entity, _item, _coordinate)| entity);
+
+        match item
+        {
+            Some(item) =>
+            {
+                coordinates.remove(item);
+                backpacks.insert(item, InBackpack { owner: player_entity }).expect("Unable to pick up item");
+
+                let name = names.get(item).map(|name| name.name.clone()).unwrap_or_else(|| "something".to_string());
+                log.add(format!("You pick up the {}.", name));
+                return true;
+            }
+            None => log.add("There is nothing here to pick up."),
+        }
+    }
+
+    false
+}
+
+/// Eats, drinks, breathes or treats wounds with whichever carried consumable the player would get the most out of
+pub fn try_consume(entity_system: &mut World) -> bool
+{
+    let medicine_bonus = player_skill_bonus(entity_system, Skill::Medicine);
+
+    // Whether something was used, and whether it took medical skill to use it
+    let used: Option<bool> =
+    {
+        let entities = entity_system.entities();
+        let players = entity_system.read_storage::<Player>();
+        let backpacks = entity_system.read_storage::<InBackpack>();
+        let consumables = entity_system.read_storage::<Consumable>();
+        let names = entity_system.read_storage::<Name>();
+        let mut needs = entity_system.write_storage::<Needs>();
+        let mut combat_stats = entity_system.write_storage::<CombatStats>();
+        let mut status_effects = entity_system.write_storage::<StatusEffects>();
+        let mut log = entity_system.fetch_mut::<GameLog>();
+
+        let (player, player_needs, stats) = match (&entities, &players, &mut needs, &mut combat_stats).join().map(|(entity, _player, needs, stats)| (entity, needs, stats)).next()
+        {
+            Some(player) => player,
+            None => return false,
+        };
+        let bleeding = status_effects.get(player).map(|effects| effects.has(StatusKind::Bleeding)).unwrap_or(false);
+
+        let carried: Vec<(Entity, &Consumable)> = (&entities, &consumables, &backpacks).join()
+                                                                                      .filter(|(_entity, _consumable, backpack)| backpack.owner == player)
+                                                                                      .map(|(entity, consumable, _backpack)| (entity, consumable))
+                                                                                      .collect();
+
+        if carried.is_empty()
+        {
+            log.add("You have nothing to eat, drink, breathe or treat yourself with.");
+            return false;
+        }
+
+        // Keep the first of equally useful things so the choice never depends on anything but what is carried
+        let mut best: Option<(Entity, &Consumable, f32)> = None;
+        for (entity, consumable) in carried
+        {
+            let mut benefit = player_needs.benefit(consumable);
+            if consumable.healing > 0
+            {
+                benefit += consumable.healing.min(stats.max_hp - stats.hp).max(0) as f32 / stats.max_hp as f32;
+                benefit += if bleeding { BLEEDING_TREATMENT_BENEFIT } else { 0.0 };
+            }
+
+            if best.map(|(_entity, _consumable, best_benefit)| benefit > best_benefit).unwrap_or(true)
+            {
+                best = Some((entity, consumable, benefit));
+            }
+        }
+
+        match best
+        {
+            Some((entity, consumable, benefit)) if benefit > 0.0 =>
+            {
+                let name = names.get(entity).map(|name| name.name.clone()).unwrap_or_else(|| "something".to_string());
+                log.add(format!("You use the {}.", name));
+                player_needs.consume(consumable);
+
+                // A practised hand gets more out of the same medkit
+                if consumable.healing > 0
+                {
+                    let healed = (consumable.healing * (100 + medicine_bonus) / 100).min(stats.max_hp - stats.hp).max(0);
+                    stats.hp += healed;
+                    if let Some(effects) = status_effects.get_mut(player)
+                    {
+                        effects.remove(StatusKind::Bleeding);
+                    }
+                    log.add(format!("You treat your wounds and recover {} HP.", healed));
+                }
+
+                entities.delete(entity).expect("Unable to delete");
+                Some(consumable.healing > 0)
+            }
+            _ =>
+            {
+                log.add("You don't need anything you are carrying right now.");
+                None
+            }
+        }
+    };
+
+    match used
+    {
+        Some(treated) =>
+        {
+            if treated
+            {
+                player_gain_experience(entity_system, Skill::Medicine, SUCCESS_EXPERIENCE);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Hits the occupant of a tile the player walked into when the player's faction is at odds with theirs, returning
+/// whether that took the turn. Anyone the player has no quarrel with blocks the way instead.
+fn player_melee(entity_system: &mut World, occupant: Entity) -> bool
+{
+    let (hostile, name) =
+    {
+        let players = entity_system.read_storage::<Player>();
+        let members = entity_system.read_storage::<FactionMember>();
+        let names = entity_system.read_storage::<Name>();
+        let relations = entity_system.fetch::<Relations>();
+        let player_faction = (&players, &members).join().map(|(_player, member)| member.faction).next();
+        let hostile = match (player_faction, members.get(occupant))
+        {
+            (Some(player_faction), Some(member)) => is_hostile(&relations, (player_faction, true), (member.faction, false)),
+            _ => false,
+        };
+
+        (hostile, names.get(occupant).map(|name| name.name.clone()).unwrap_or_else(|| "something".to_string()))
+    };
+
+    if !hostile
+    {
+        entity_system.fetch_mut::<GameLog>().add(format!("The {} is in the way.", name));
+        return false;
+    }
+
+    let damage = player_attributes(entity_system).melee_damage();
+    SufferDamage::new_damage(&mut entity_system.write_storage::<SufferDamage>(), occupant, damage);
+    entity_system.fetch_mut::<GameLog>().add(format!("You hit the {} for {} damage.", name, damage));
+    hold_player_to_account(entity_system);
+
+    true
+}
+
+/// Climbs a ladder on the player's tile in the given direction
+pub fn try_climb(entity_system: &mut World, direction: Ladder) -> bool
+{
+    let players = entity_system.read_storage::<Player>();
+    let mut coordinates = entity_system.write_storage::<Coordinate>();
+    let mut viewsheds = entity_system.write_storage::<Viewshed>();
+    let map = entity_system.fetch::<Map>();
+    let mut log = entity_system.fetch_mut::<GameLog>();
+    let mut climbed = false;
+
+    for (_player, coordinate, viewshed) in (&players, &mut coordinates, &mut viewsheds).join()
+    {
+        match map.get(*coordinate)
+        {
+            Some(tile) if tile.ladder == Some(direction) =>
+            {
+                match direction
+                {
+                    Ladder::Down => coordinate.z += 1,
+                    Ladder::Up => coordinate.z -= 1,
+                }
+                viewshed.dirty = true;
+                climbed = true;
+                log.add(format!("You climb the ladder to deck {}.", coordinate.z + 1));
+            }
+            _ =>
+            {
+                match direction
+                {
+                    Ladder::Down => log.add("There is no way down here."),
+                    Ladder::Up => log.add("There is no way up here."),
+                }
+            }
+        }
+    }
+
+    climbed
+}
+
+/// Name of the first hostile the player can currently see, if any, going by how its faction regards the player
+pub fn visible_hostile(entity_system: &World) -> Option<String>
+{
+    let players = entity_system.read_storage::<Player>();
+    let viewsheds = entity_system.read_storage::<Viewshed>();
+    let members = entity_system.read_storage::<FactionMember>();
+    let coordinates = entity_system.read_storage::<Coordinate>();
+    let names = entity_system.read_storage::<Name>();
+    let relations = entity_system.fetch::<Relations>();
+
+    for (_player, viewshed) in (&players, &viewsheds).join()
+    {
+        for (member, coordinate, name, _not_player) in (&members, &coordinates, &names, !&players).join()
+        {
+            if relations.hostile_to_player(member.faction) && viewshed.visible_tiles.contains(coordinate)
+            {
+                return Some(name.name.clone());
+            }
+        }
+    }
+
+    None
+}
+
+/// Where the closest hostile the player can currently see stands, for aiming at by default
+pub fn nearest_visible_hostile(entity_system: &World) -> Option<Coordinate>
+{
+    let players = entity_system.read_storage::<Player>();
+    let viewsheds = entity_system.read_storage::<Viewshed>();
+    let members = entity_system.read_storage::<FactionMember>();
+    let coordinates = entity_system.read_storage::<Coordinate>();
+    let relations = entity_system.fetch::<Relations>();
+
+    let (_player, player_coordinate, viewshed) = (&players, &coordinates, &viewsheds).join().next()?;
+    let distance = |coordinate: &Coordinate| (coordinate.x as i32 - player_coordinate.x as i32).abs().max((coordinate.y as i32 - player_coordinate.y as i32).abs());
+
+    (&members, &coordinates, !&players).join()
+                                        .filter(|(member, _coordinate, _not_player)| relations.hostile_to_player(member.faction))
+                                        .map(|(_member, coordinate, _not_player)| *coordinate)
+                                        .filter(|coordinate| coordinate.z == player_coordinate.z && viewshed.visible_tiles.contains(coordinate))
+                                        .min_by_key(distance)
+}
+
+/// Every item lying where the player can currently see it
+pub fn visible_items(entity_system: &World) -> Vec<(Entity, String)>
+{
+    let entities = entity_system.entities();
+    let players = entity_system.read_storage::<Player>();
+    let viewsheds = entity_system.read_storage::<Viewshed>();
+    let items = entity_system.read_storage::<Item>();
+    let coordinates = entity_system.read_storage::<Coordinate>();
+    let names = entity_system.read_storage::<Name>();
+    let mut seen: Vec<(Entity, String)> = Vec::new();
+
+    for (_player, viewshed) in (&players, &viewsheds).join()
+    {
+        for (entity, _item, coordinate, name) in (&entities, &items, &coordinates, &names).join()
+        {
+            if viewshed.visible_tiles.contains(coordinate)
+            {
+                seen.push((entity, name.name.clone()));
+            }
+        }
+    }
+
+    seen
+}
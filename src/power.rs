@@ -0,0 +1,145 @@
+use specs_derive::Component;
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::map::Map;
+
+
+/// Which consumers keep their power first when a section can't supply them all, lowest first
+pub const LIFE_SUPPORT_PRIORITY: i32 = 0;
+pub const SECURITY_PRIORITY: i32 = 1;
+pub const DOOR_PRIORITY: i32 = 2;
+pub const TERMINAL_PRIORITY: i32 = 2;
+pub const LIGHTS_PRIORITY: i32 = 3;
+
+/// Power drawn each turn by a room's lights and by a door holding its maglock
+pub const LIGHTS_DRAW: i32 = 1;
+pub const MAGLOCK_DRAW: i32 = 1;
+
+//Generator
+/// Feeds power into the conduits it stands on or next to every turn
+#[derive(Component)]
+pub struct Generator
+{
+    pub output: i32,
+}
+
+//Battery
+/// Stores power its section has to spare and gives it back when the section runs short
+#[derive(Component)]
+pub struct Battery
+{
+    pub charge: i32,
+    pub capacity: i32,
+    /// Most it can take in or give out in a turn
+    pub rate: i32,
+}
+
+//PowerConsumer
+/// Something that only works while its section of the grid can spare it power
+#[derive(Component)]
+pub struct PowerConsumer
+{
+    pub draw: i32,
+    pub priority: i32,
+    pub powered: bool,
+}
+
+/// How much a section of the grid had and needed last turn
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SectionLoad
+{
+    pub supply: i32,
+    pub demand: i32,
+    pub stored: i32,
+}
+
+/// The station's conduits split into sections that are connected to each other, and how loaded each section was
+/// last turn
+#[derive(Default)]
+pub struct PowerGrid
+{
+    /// Section of every tile indexed by `Map::tile_index`, `None` where no conduit runs
+    pub sections: Vec<Option<usize>>,
+    pub loads: Vec<SectionLoad>,
+}
+
+impl PowerGrid
+{
+    /// Splits the conduits into sections, any two conduits touching on a side or a corner being in the same one
+    pub fn trace(map: &Map) -> PowerGrid
+    {
+        let size = map.map_size;
+        let mut sections: Vec<Option<usize>> = vec![None; size.x * size.y * size.z];
+        let mut count = 0;
+
+        for z in 0..size.z
+        {
+            for y in 0..size.y
+            {
+                for x in 0..size.x
+                {
+                    let start = Coordinate::new(x, y, z);
+
+                    if !map.has_conduit(start) || sections[map.tile_index(start)].is_some()
+                    {
+                        continue;
+                    }
+
+                    let mut frontier: Vec<Coordinate> = vec![start];
+                    sections[map.tile_index(start)] = Some(count);
+
+                    while let Some(current) = frontier.pop()
+                    {
+                        for neighbour in neighbours(map, current)
+                        {
+                            if map.has_conduit(neighbour) && sections[map.tile_index(neighbour)].is_none()
+                            {
+                                sections[map.tile_index(neighbour)] = Some(count);
+                                frontier.push(neighbour);
+                            }
+                        }
+                    }
+                    count += 1;
+                }
+            }
+        }
+
+        PowerGrid { sections, loads: vec![SectionLoad::default(); count] }
+    }
+
+    /// The section something on a tile draws from, through a conduit under it or under any tile next to it
+    pub fn section_at(&self, map: &Map, coordinate: Coordinate) -> Option<usize>
+    {
+        map.get(coordinate)?;
+
+        std::iter::once(coordinate).chain(neighbours(map, coordinate))
+                                   .find_map(|tile| self.sections.get(map.tile_index(tile)).copied().flatten())
+    }
+
+    pub fn load_at(&self, map: &Map, coordinate: Coordinate) -> Option<SectionLoad>
+    {
+        self.section_at(map, coordinate).and_then(|section| self.loads.get(section).copied())
+    }
+}
+
+/// The tiles around a coordinate on its deck
+fn neighbours(map: &Map, coordinate: Coordinate) -> impl Iterator<Item = Coordinate> + '_
+{
+    [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)].into_iter().filter_map(move |(delta_x, delta_y)|
+    {
+        let (x, y) = (coordinate.x as i32 + delta_x, coordinate.y as i32 + delta_y);
+
+        match x >= 0 && y >= 0 && x < map.map_size.x as i32 && y < map.map_size.y as i32
+        {
+            true => Some(Coordinate::new(x as usize, y as usize, coordinate.z)),
+            false => None,
+        }
+    })
+}
+
+/// Whether an entity that needs power has it, which anything that doesn't need power always does
+pub fn is_powered(consumers: &ReadStorage<PowerConsumer>, entity: Entity) -> bool
+{
+    consumers.get(entity).map(|consumer| consumer.powered).unwrap_or(true)
+}
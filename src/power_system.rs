@@ -0,0 +1,180 @@
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::map::Map;
+use super::map_tile::{Door, MapTile};
+use super::power::*;
+use super::station_network::{DeviceKind, StationNetwork};
+
+
+/// Something on the grid drawing power, an entity or a door or room lights on the station network
+#[derive(Clone, Copy)]
+enum Consumer
+{
+    Entity(Entity),
+    Device(usize),
+}
+
+/// Shares out what each section of the grid's generators and batteries can supply among its consumers, the most
+/// important first, and browns out the rest. Lights without power go dark and maglocks release. Whatever a section
+/// has to spare charges its batteries.
+pub struct PowerSystem {}
+
+impl<'a> System<'a> for PowerSystem
+{
+    type SystemData = (WriteExpect<'a, Map>,
+                       WriteExpect<'a, PowerGrid>,
+                       WriteExpect<'a, StationNetwork>,
+                       Entities<'a>,
+                       ReadStorage<'a, Coordinate>,
+                       ReadStorage<'a, Generator>,
+                       WriteStorage<'a, Battery>,
+                       WriteStorage<'a, PowerConsumer>);
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let (mut map, mut grid, mut network, entities, coordinates, generators, mut batteries, mut consumers) = data;
+
+        // Conduits only ever get cut, so the sections are only traced again when one is
+        if map.take_severed_conduits() || grid.sections.is_empty()
+        {
+            *grid = PowerGrid::trace(&map);
+        }
+
+        let section_count = grid.loads.len();
+        let mut supply = vec![0; section_count];
+        let mut from_batteries = vec![0; section_count];
+
+        for (generator, coordinate) in (&generators, &coordinates).join()
+        {
+            if let Some(section) = grid.section_at(&map, *coordinate)
+            {
+                supply[section] += generator.output;
+            }
+        }
+        for (battery, coordinate) in (&batteries, &coordinates).join()
+        {
+            if let Some(section) = grid.section_at(&map, *coordinate)
+            {
+                from_batteries[section] += battery.charge.min(battery.rate);
+            }
+        }
+
+        // Everything wanting power, as its section, priority, draw and what it is
+        let mut wanting: Vec<(Option<usize>, i32, i32, Consumer)> = Vec::new();
+
+        for (entity, consumer, coordinate) in (&entities, &consumers, &coordinates).join()
+        {
+            wanting.push((grid.section_at(&map, *coordinate), consumer.priority, consumer.draw, Consumer::Entity(entity)));
+        }
+        for (index, device) in network.devices.iter().enumerate()
+        {
+            match device.kind
+            {
+                DeviceKind::Door(coordinate) =>
+                {
+                    let locked = map.get(coordinate).and_then(|tile| tile.door) == Some(Door::Locked);
+                    wanting.push((grid.section_at(&map, coordinate), DOOR_PRIORITY, if locked { MAGLOCK_DRAW } else { 0 }, Consumer::Device(index)));
+                }
+                DeviceKind::Lights(room) =>
+                {
+                    wanting.push((grid.section_at(&map, room.center()), LIGHTS_PRIORITY, if device.online { LIGHTS_DRAW } else { 0 }, Consumer::Device(index)));
+                }
+                DeviceKind::Camera(_) | DeviceKind::Turret(_) => {}
+            }
+        }
+        wanting.sort_by_key(|(section, priority, _draw, _consumer)| (*section, *priority));
+
+        let mut left: Vec<i32> = (0..section_count).map(|section| supply[section] + from_batteries[section]).collect();
+        let mut demand = vec![0; section_count];
+        let mut powered: Vec<(Consumer, bool)> = Vec::new();
+
+        for (section, _priority, draw, consumer) in wanting
+        {
+            let has_power = match section
+            {
+                Some(section) =>
+                {
+                    demand[section] += draw;
+                    let has_power = supply[section] + from_batteries[section] > 0 && left[section] >= draw;
+
+                    if has_power
+                    {
+                        left[section] -= draw;
+                    }
+                    has_power
+                }
+                None => false,
+            };
+            powered.push((consumer, has_power));
+        }
+
+        // Batteries make up what the generators couldn't, or soak up what they had spare
+        let used: Vec<i32> = (0..section_count).map(|section| supply[section] + from_batteries[section] - left[section]).collect();
+        let mut shortfall: Vec<i32> = (0..section_count).map(|section| (used[section] - supply[section]).max(0)).collect();
+        let mut spare: Vec<i32> = (0..section_count).map(|section| (supply[section] - used[section]).max(0)).collect();
+        let mut stored = vec![0; section_count];
+
+        for (battery, coordinate) in (&mut batteries, &coordinates).join()
+        {
+            let section = match grid.section_at(&map, *coordinate)
+            {
+                Some(section) => section,
+                None => continue,
+            };
+            let drawn = shortfall[section].min(battery.charge).min(battery.rate);
+            let charged = spare[section].min(battery.capacity - battery.charge).min(battery.rate);
+
+            battery.charge += charged - drawn;
+            shortfall[section] -= drawn;
+            spare[section] -= charged;
+            stored[section] += battery.charge;
+        }
+
+        for section in 0..section_count
+        {
+            grid.loads[section] = SectionLoad { supply: supply[section] + from_batteries[section], demand: demand[section], stored: stored[section] };
+        }
+
+        for (consumer, has_power) in powered
+        {
+            match consumer
+            {
+                Consumer::Entity(entity) =>
+                {
+                    if let Some(consumer) = consumers.get_mut(entity)
+                    {
+                        consumer.powered = has_power;
+                    }
+                }
+                Consumer::Device(index) =>
+                {
+                    let device = &mut network.devices[index];
+                    device.powered = has_power;
+
+                    match device.kind
+                    {
+                        DeviceKind::Door(coordinate) if !has_power && map.get(coordinate).and_then(|tile| tile.door) == Some(Door::Locked) =>
+                        {
+                            map.set_tile(coordinate, MapTile::closed_door());
+                        }
+                        DeviceKind::Lights(room) if map.lights_on(&room) != (device.online && has_power) =>
+                        {
+                            map.set_lighting(&room, device.online && has_power);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        // Cameras and turrets are on the network and the grid both
+        for device in network.devices.iter_mut()
+        {
+            if let DeviceKind::Camera(entity) | DeviceKind::Turret(entity) = device.kind
+            {
+                device.powered = consumers.get(entity).map(|consumer| consumer.powered).unwrap_or(true);
+            }
+        }
+    }
+}
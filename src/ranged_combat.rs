@@ -0,0 +1,207 @@
+use rand::Rng;
+use rand::rngs::StdRng;
+use rltk::{line2d, LineAlg, Point};
+use specs::prelude::*;
+
+use super::character::player_attributes;
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::factions::hold_player_to_account;
+use super::noise::{make_noise, GUNSHOT_NOISE};
+use super::game_log::GameLog;
+use super::map::Map;
+use super::map_tile::MapTile;
+use super::skills::{player_gain_experience, player_skill_bonus, Skill, ATTEMPT_EXPERIENCE, SUCCESS_EXPERIENCE};
+use super::status_effects::{StatusEffects, StatusKind};
+
+
+/// Chance to hit a target standing right next to the shooter, in percent
+const POINT_BLANK_HIT_CHANCE: i32 = 95;
+/// Hit chance lost for every tile between shooter and target
+const HIT_CHANCE_LOST_PER_TILE: i32 = 5;
+/// Hit chance lost when the target is behind something solid
+const COVER_PENALTY: i32 = 30;
+const MINIMUM_HIT_CHANCE: i32 = 5;
+/// Turns a rivet wound keeps bleeding for
+const BLEEDING_TURNS: i32 = 5;
+/// Chance a shot that hits the hull tears a hole in it, in percent
+const BREACH_CHANCE: i32 = 20;
+
+/// Tiles a shot passes over on its way from the shooter towards the target and on past it until it has flown
+/// its full range, not including the shooter's own tile. The line is only traced, nothing stops it here.
+pub fn projectile_path(from: Coordinate, to: Coordinate, range: i32, map_size: Coordinate) -> Vec<Coordinate>
+{
+    if from == to
+    {
+        return Vec::new();
+    }
+
+    // Trace to the target first so the line always passes through it, then stretch it out to full range
+    // so a shot that misses carries on in the same direction
+    let (delta_x, delta_y) = (to.x as i32 - from.x as i32, to.y as i32 - from.y as i32);
+    let length = delta_x.abs().max(delta_y.abs());
+    let end = Point::new(from.x as i32 + delta_x * range / length, from.y as i32 + delta_y * range / length);
+    let mut points = line2d(LineAlg::Bresenham, Point::new(from.x, from.y), Point::new(to.x, to.y));
+
+    if range > length
+    {
+        points.extend(line2d(LineAlg::Bresenham, Point::new(to.x, to.y), end).into_iter().skip(1));
+    }
+
+    points.into_iter()
+          .skip(1)
+          .take(range.max(0) as usize)
+          .take_while(|point| point.x >= 0 && point.y >= 0 && (point.x as usize) < map_size.x && (point.y as usize) < map_size.y)
+          .map(|point| Coordinate::new(point.x as usize, point.y as usize, from.z))
+          .collect()
+}
+
+/// Whether a shot coming from the shooter has to pass something solid right in front of the target
+pub fn in_cover(map: &Map, from: Coordinate, target: Coordinate) -> bool
+{
+    let path = projectile_path(target, from, 1, map.map_size);
+
+    match path.first()
+    {
+        Some(in_front) if *in_front != from => map.get(*in_front).map(|tile| !tile.holds_atmosphere()).unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Percent chance a shot from the shooter hits whatever stands on the target tile, with the shooter's own bonus
+pub fn hit_chance(map: &Map, from: Coordinate, target: Coordinate, hit_bonus: i32) -> i32
+{
+    let distance = (target.x as i32 - from.x as i32).abs().max((target.y as i32 - from.y as i32).abs());
+    let cover = if in_cover(map, from, target) { COVER_PENALTY } else { 0 };
+
+    (POINT_BLANK_HIT_CHANCE - (distance - 1).max(0) * HIT_CHANCE_LOST_PER_TILE - cover + hit_bonus).max(MINIMUM_HIT_CHANCE)
+}
+
+/// The ranged weapon the player carries and a magazine with rounds left to load it with, if the player has both
+pub fn player_weapon(entity_system: &World) -> Option<(Entity, Entity)>
+{
+    let entities = entity_system.entities();
+    let players = entity_system.read_storage::<Player>();
+    let backpacks = entity_system.read_storage::<InBackpack>();
+    let weapons = entity_system.read_storage::<RangedWeapon>();
+    let ammunition = entity_system.read_storage::<Ammunition>();
+
+    let player = (&entities, &players).join().map(|(entity, _player)| entity).next()?;
+    let weapon = (&entities, &weapons, &backpacks).join().find(|(_entity, _weapon, backpack)| backpack.owner == player)?.0;
+    let magazine = (&entities, &ammunition, &backpacks).join()
+                                                        .find(|(_entity, ammunition, backpack)| backpack.owner == player && ammunition.rounds > 0)?.0;
+
+    Some((weapon, magazine))
+}
+
+/// Fires the player's weapon at the target tile, spending a round. The shot stops at the first solid tile or the
+/// first thing it hits, and can breach the hull. Gives back the tiles the shot flew over, empty if no shot was fired.
+pub fn player_fire(entity_system: &mut World, target: Coordinate) -> Vec<Coordinate>
+{
+    let (weapon, magazine) = match player_weapon(entity_system)
+    {
+        Some(weapon_and_rounds) => weapon_and_rounds,
+        None =>
+        {
+            entity_system.fetch_mut::<GameLog>().add("You have nothing to shoot with.");
+            return Vec::new();
+        }
+    };
+
+    let hit_bonus = player_attributes(entity_system).hit_bonus() + player_skill_bonus(entity_system, Skill::Firearms);
+    let (from, flight, hit) =
+    {
+        let entities = entity_system.entities();
+        let players = entity_system.read_storage::<Player>();
+        let coordinates = entity_system.read_storage::<Coordinate>();
+        let combat_stats = entity_system.read_storage::<CombatStats>();
+        let names = entity_system.read_storage::<Name>();
+        let weapons = entity_system.read_storage::<RangedWeapon>();
+        let mut ammunition = entity_system.write_storage::<Ammunition>();
+        let mut suffering = entity_system.write_storage::<SufferDamage>();
+        let mut status_effects = entity_system.write_storage::<StatusEffects>();
+        let breathers = entity_system.read_storage::<Breathes>();
+        let mut map = entity_system.fetch_mut::<Map>();
+        let mut rng = entity_system.fetch_mut::<StdRng>();
+        let mut log = entity_system.fetch_mut::<GameLog>();
+
+        let from = match (&players, &coordinates).join().map(|(_player, coordinate)| *coordinate).next()
+        {
+            Some(from) if from != target => from,
+            _ => return Vec::new(),
+        };
+        let weapon_stats = weapons.get(weapon).expect("Weapon without stats");
+
+        if let Some(ammunition) = ammunition.get_mut(magazine)
+        {
+            ammunition.rounds -= 1;
+
+            if ammunition.rounds <= 0
+            {
+                log.add("You use up your last round from that magazine.");
+                entities.delete(magazine).expect("Unable to delete");
+            }
+        }
+
+        let mut flight: Vec<Coordinate> = Vec::new();
+        let mut hit: Option<bool> = None;
+
+        for coordinate in projectile_path(from, target, weapon_stats.range, map.map_size)
+        {
+            flight.push(coordinate);
+
+            let tile = match map.get(coordinate)
+            {
+                Some(tile) => tile,
+                None => break,
+            };
+
+            if !tile.holds_atmosphere()
+            {
+                if map.is_hull(coordinate) && rng.gen_range(0..100) < BREACH_CHANCE
+                {
+                    map.set_tile(coordinate, MapTile::hull_breach());
+                    log.add("The shot punches through the hull!");
+                }
+                break;
+            }
+
+            let victim = (&entities, &coordinates, &combat_stats).join()
+                                                                  .find(|(_entity, victim_coordinate, _stats)| **victim_coordinate == coordinate)
+                                                                  .map(|(entity, _coordinate, _stats)| entity);
+
+            if let Some(victim) = victim
+            {
+                let name = names.get(victim).map(|name| name.name.clone()).unwrap_or_else(|| "target".to_string());
+
+                hit = Some(rng.gen_range(0..100) < hit_chance(&map, from, coordinate, hit_bonus));
+
+                if hit == Some(true)
+                {
+                    SufferDamage::new_damage(&mut suffering, victim, weapon_stats.damage);
+                    log.add(format!("You hit the {} for {} damage.", name, weapon_stats.damage));
+
+                    if breathers.get(victim).is_some()
+                    {
+                        StatusEffects::apply_to(&mut status_effects, victim, StatusKind::Bleeding, BLEEDING_TURNS);
+                    }
+                    break;
+                }
+                log.add(format!("The shot misses the {}.", name));
+            }
+        }
+
+        (from, flight, hit)
+    };
+
+    hold_player_to_account(entity_system);
+    make_noise(entity_system, from, GUNSHOT_NOISE);
+
+    // Shooting at something teaches more than shooting at nothing, and hitting it more still
+    if let Some(hit) = hit
+    {
+        player_gain_experience(entity_system, Skill::Firearms, if hit { SUCCESS_EXPERIENCE } else { ATTEMPT_EXPERIENCE });
+    }
+
+    flight
+}
@@ -0,0 +1,65 @@
+use specs::prelude::*;
+
+use super::ai::Brain;
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::factions::{Faction, FactionMember, Relations};
+use super::game_log::GameLog;
+use super::map::Map;
+use super::noise::{hidden_in_shadow, Sneaking};
+use super::station_network::{SecurityCamera, StationNetwork};
+
+
+/// Has every security camera that is online watch for the player, calling security guards on its deck to where
+/// the player was seen when security doesn't count the player a friend
+pub struct SecurityCameraSystem {}
+
+impl<'a> System<'a> for SecurityCameraSystem
+{
+    type SystemData = (ReadExpect<'a, Map>,
+                       ReadExpect<'a, StationNetwork>,
+                       ReadExpect<'a, Relations>,
+                       WriteExpect<'a, GameLog>,
+                       Entities<'a>,
+                       WriteStorage<'a, SecurityCamera>,
+                       ReadStorage<'a, Coordinate>,
+                       ReadStorage<'a, Viewshed>,
+                       ReadStorage<'a, Player>,
+                       ReadStorage<'a, Sneaking>,
+                       ReadStorage<'a, FactionMember>,
+                       WriteStorage<'a, Brain>);
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let (map, network, relations, mut log, entities, mut cameras, coordinates, viewsheds, players, sneaking, members, mut brains) = data;
+
+        let player = (&entities, &players, &coordinates).join().map(|(entity, _player, coordinate)| (entity, *coordinate)).next();
+        let (player, player_position) = match player
+        {
+            Some(player) => player,
+            None => return,
+        };
+        let wanted = !relations.friendly_to_player(Faction::Security);
+
+        for (camera_entity, camera, position, viewshed) in (&entities, &mut cameras, &coordinates, &viewsheds).join()
+        {
+            let sees_player = network.is_online(camera_entity) && position.z == player_position.z
+                              && viewshed.visible_tiles.contains(&player_position)
+                              && !hidden_in_shadow(&map, sneaking.get(player).is_some(), player_position, *position);
+
+            if sees_player && wanted && !camera.tracking_player
+            {
+                log.add("A security camera swivels to follow you.");
+
+                for (brain, member, coordinate) in (&mut brains, &members, &coordinates).join()
+                {
+                    if member.faction == Faction::Security && coordinate.z == player_position.z
+                    {
+                        brain.heard = Some(player_position);
+                    }
+                }
+            }
+            camera.tracking_player = sees_player;
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use rand::Rng;
+use rand::rngs::StdRng;
+use specs_derive::Component;
+use specs::prelude::*;
+
+use super::character::Attributes;
+use super::entity_components::Player;
+use super::game_log::GameLog;
+
+
+pub const MAX_SKILL_LEVEL: i32 = 10;
+/// Percent added to a success roll for each level of the skill used
+pub const BONUS_PER_LEVEL: i32 = 5;
+/// Experience for trying something with a skill, and for pulling it off
+pub const ATTEMPT_EXPERIENCE: i32 = 1;
+pub const SUCCESS_EXPERIENCE: i32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Skill
+{
+    Repair,
+    Hacking,
+    Medicine,
+    Firearms,
+    Eva,
+    Piloting,
+}
+
+impl Skill
+{
+    /// Every skill in the order the character sheet lists them
+    pub const ALL: [Skill; 6] = [Skill::Repair, Skill::Hacking, Skill::Medicine, Skill::Firearms, Skill::Eva, Skill::Piloting];
+
+    /// Name used in the backgrounds file
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Skill::Repair => "repair",
+            Skill::Hacking => "hacking",
+            Skill::Medicine => "medicine",
+            Skill::Firearms => "firearms",
+            Skill::Eva => "eva",
+            Skill::Piloting => "piloting",
+        }
+    }
+
+    pub fn title(&self) -> &'static str
+    {
+        match self
+        {
+            Skill::Repair => "Repair",
+            Skill::Hacking => "Hacking",
+            Skill::Medicine => "Medicine",
+            Skill::Firearms => "Firearms",
+            Skill::Eva => "EVA",
+            Skill::Piloting => "Piloting",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Skill>
+    {
+        Skill::ALL.iter().copied().find(|skill| skill.name() == name.trim())
+    }
+
+    fn index(&self) -> usize
+    {
+        Skill::ALL.iter().position(|skill| skill == self).unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SkillProgress
+{
+    pub level: i32,
+    /// Experience gathered towards the next level
+    pub experience: i32,
+}
+
+//Skills
+/// How practised a character is at each skill
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Skills
+{
+    pub progress: [SkillProgress; 6],
+}
+
+impl Skills
+{
+    pub fn level(&self, skill: Skill) -> i32
+    {
+        self.progress[skill.index()].level
+    }
+
+    pub fn set_level(&mut self, skill: Skill, level: i32)
+    {
+        self.progress[skill.index()] = SkillProgress { level: level.clamp(0, MAX_SKILL_LEVEL), experience: 0 };
+    }
+
+    pub fn progress(&self, skill: Skill) -> SkillProgress
+    {
+        self.progress[skill.index()]
+    }
+
+    /// Experience it takes to go from a level to the next
+    pub fn experience_to_next(level: i32) -> i32
+    {
+        10 * (level + 1)
+    }
+
+    /// Percent added to rolls made with the skill
+    pub fn bonus(&self, skill: Skill) -> i32
+    {
+        self.level(skill) * BONUS_PER_LEVEL
+    }
+
+    /// Chance in percent of pulling off something with a base chance of `base`, never quite certain either way
+    pub fn success_chance(&self, skill: Skill, base: i32) -> i32
+    {
+        (base + self.bonus(skill)).clamp(5, 95)
+    }
+
+    /// Adds experience to a skill, with quick minds learning faster. Gives back the new level if it went up.
+    pub fn gain(&mut self, skill: Skill, amount: i32, attributes: &Attributes) -> Option<i32>
+    {
+        let progress = &mut self.progress[skill.index()];
+
+        if progress.level >= MAX_SKILL_LEVEL
+        {
+            return None;
+        }
+
+        progress.experience += (amount + (attributes.intellect - 5) / 2).max(1);
+
+        let mut levelled_up = false;
+        while progress.level < MAX_SKILL_LEVEL && progress.experience >= Skills::experience_to_next(progress.level)
+        {
+            progress.experience -= Skills::experience_to_next(progress.level);
+            progress.level += 1;
+            levelled_up = true;
+        }
+
+        levelled_up.then_some(progress.level)
+    }
+}
+
+/// Gives the player experience in a skill, telling them when it goes up a level
+pub fn player_gain_experience(entity_system: &World, skill: Skill, amount: i32)
+{
+    let players = entity_system.read_storage::<Player>();
+    let attributes = entity_system.read_storage::<Attributes>();
+    let mut skills = entity_system.write_storage::<Skills>();
+
+    for (_player, skills, attributes) in (&players, &mut skills, attributes.maybe()).join()
+    {
+        if let Some(level) = skills.gain(skill, amount, &attributes.copied().unwrap_or_default())
+        {
+            entity_system.fetch_mut::<GameLog>().add(format!("Your {} skill rises to {}.", skill.title(), level));
+        }
+    }
+}
+
+/// Percent the player's level in a skill adds to rolls, nothing for a player without skills
+pub fn player_skill_bonus(entity_system: &World, skill: Skill) -> i32
+{
+    let players = entity_system.read_storage::<Player>();
+    let skills = entity_system.read_storage::<Skills>();
+
+    (&players, &skills).join().map(|(_player, skills)| skills.bonus(skill)).next().unwrap_or(0)
+}
+
+/// Rolls the player's skill against a base chance in percent and hands out experience for the attempt,
+/// more when it works. Gives back whether it worked.
+pub fn player_skill_check(entity_system: &World, skill: Skill, base: i32) -> bool
+{
+    let chance =
+    {
+        let players = entity_system.read_storage::<Player>();
+        let skills = entity_system.read_storage::<Skills>();
+
+        (&players, &skills).join().map(|(_player, skills)| skills.success_chance(skill, base)).next().unwrap_or_else(|| base.clamp(5, 95))
+    };
+    let succeeded = entity_system.fetch_mut::<StdRng>().gen_range(0..100) < chance;
+
+    player_gain_experience(entity_system, skill, if succeeded { SUCCESS_EXPERIENCE } else { ATTEMPT_EXPERIENCE });
+    succeeded
+}
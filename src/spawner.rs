@@ -0,0 +1,552 @@
+use rand::Rng;
+use rand::rngs::StdRng;
+use rltk::RGB;
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::map::Map;
+use super::map_memory::{MapMemory, SharedMemory};
+use super::rectangle::Rectangle;
+use super::character::{Character, CharacterBackground};
+use super::survival::{Consumable, Needs};
+use super::factions::{Faction, FactionMember};
+use super::ai::Brain;
+use super::station_network::{DeviceKind, SecurityCamera, SecurityMonitor, StationNetwork, Terminal, Turret, TERMINAL_LOGS};
+use super::power::{Battery, Generator, PowerConsumer, LIFE_SUPPORT_PRIORITY, SECURITY_PRIORITY, TERMINAL_PRIORITY};
+use super::life_support::{LifeSupport, LifeSupportKind, MAX_CONDITION};
+
+
+const MAX_ITEMS_PER_ROOM: usize = 2;
+/// Group crew and security pool their map memories in
+const CREW_MEMORY_GROUP: u32 = 0;
+/// Chance in one that a room has a fuel tank standing in it
+const FUEL_TANK_CHANCE: i32 = 6;
+
+/// Ids of the items a background can start with in its kit
+pub const KIT_ITEMS: [&str; 8] =
+[
+    "rivet_gun", "rivet_magazine", "breaching_charge", "extinguisher", "ration_pack", "water_pouch", "oxygen_canister", "medkit",
+];
+
+/// The player as made on the character creation screen, with the attributes and kit of their background
+pub fn player(entity_system: &mut World, coordinate: Coordinate, character: &Character) -> Entity
+{
+    let attributes = character.background.attributes;
+    let player = entity_system.create_entity()
+                 .with(Player{})
+                 .with(coordinate)
+                 .with(Renderable { glyph: rltk::to_cp437('@'),
+                                    foreground_color: RGB::named(rltk::YELLOW),
+                                    background_color: RGB::named(rltk::BLACK),
+                                    render_order: 0})
+                 .with(Name { name: character.name.clone() })
+                 .with(Viewshed { visible_tiles: Vec::new(), range: 18, dirty: true})
+                 .with(MapMemory::new())
+                 .with(CombatStats { max_hp: attributes.max_hp(), hp: attributes.max_hp() })
+                 .with(attributes)
+                 .with(character.background.skills.clone())
+                 .with(CharacterBackground { title: character.background.title.clone() })
+                 .with(FactionMember { faction: character.background.faction })
+                 .with(Breathes{})
+                 .with(Needs::default())
+                 .build();
+
+    for item_id in character.background.kit.iter()
+    {
+        if let Some(carried) = kit_item(entity_system, item_id, coordinate)
+        {
+            entity_system.write_storage::<Coordinate>().remove(carried);
+            entity_system.write_storage::<InBackpack>().insert(carried, InBackpack { owner: player }).expect("Unable to insert");
+        }
+    }
+
+    player
+}
+
+/// Makes one of the items in `KIT_ITEMS` by its id
+pub fn kit_item(entity_system: &mut World, item_id: &str, coordinate: Coordinate) -> Option<Entity>
+{
+    match item_id
+    {
+        "rivet_gun" => Some(rivet_gun(entity_system, coordinate)),
+        "rivet_magazine" => Some(rivet_magazine(entity_system, coordinate)),
+        "breaching_charge" => Some(breaching_charge(entity_system, coordinate)),
+        "extinguisher" => Some(extinguisher(entity_system, coordinate)),
+        "ration_pack" => Some(ration_pack(entity_system, coordinate)),
+        "water_pouch" => Some(water_pouch(entity_system, coordinate)),
+        "oxygen_canister" => Some(oxygen_canister(entity_system, coordinate)),
+        "medkit" => Some(medkit(entity_system, coordinate)),
+        _ => None,
+    }
+}
+
+/// Scatters a few items over the floor of a room, and sometimes someone or something from one of the factions
+pub fn fill_room(entity_system: &mut World, room: &Rectangle, rng: &mut StdRng)
+{
+    let item_count = rng.gen_range(0..=MAX_ITEMS_PER_ROOM);
+
+    for _ in 0..item_count
+    {
+        if let Some(coordinate) = random_floor_tile(entity_system, room, rng)
+        {
+            random_item(entity_system, coordinate, rng);
+        }
+    }
+
+    if rng.gen_range(0..FUEL_TANK_CHANCE) == 0
+    {
+        if let Some(coordinate) = random_floor_tile(entity_system, room, rng)
+        {
+            fuel_tank(entity_system, coordinate);
+        }
+    }
+
+    if rng.gen_range(0..3) == 0
+    {
+        if let Some(coordinate) = random_floor_tile(entity_system, room, rng)
+        {
+            match rng.gen_range(0..5)
+            {
+                0 => actor(entity_system, coordinate, "Rogue drone", rltk::to_cp437('d'), (6, 1), Faction::Drones, "drone"),
+                1 => actor(entity_system, coordinate, "Pirate", rltk::to_cp437('p'), (10, 3), Faction::Pirates, "pirate"),
+                2 => actor(entity_system, coordinate, "Crew member", rltk::to_cp437('c'), (8, 1), Faction::Crew, "crew"),
+                3 => actor(entity_system, coordinate, "Security guard", rltk::to_cp437('g'), (10, 3), Faction::Security, "security"),
+                _ => actor(entity_system, coordinate, "Scavenger", rltk::to_cp437('s'), (8, 2), Faction::Scavengers, "scavenger"),
+            };
+        }
+    }
+}
+
+/// Gives every NPC a patrol route from the room it stands in through two other rooms on its deck
+pub fn assign_patrol_routes(entity_system: &mut World, rooms: &[Rectangle], rng: &mut StdRng)
+{
+    let coordinates = entity_system.read_storage::<Coordinate>();
+    let mut brains = entity_system.write_storage::<Brain>();
+
+    for (brain, coordinate) in (&mut brains, &coordinates).join()
+    {
+        let deck_rooms: Vec<Coordinate> = rooms.iter().filter(|room| room.corner_one.z == coordinate.z).map(|room| room.center()).collect();
+
+        if deck_rooms.len() < 2
+        {
+            continue;
+        }
+
+        let home = deck_rooms.iter().min_by_key(|centre| (centre.x as i32 - coordinate.x as i32).abs() + (centre.y as i32 - coordinate.y as i32).abs()).copied();
+
+        brain.patrol_route = home.into_iter()
+                                 .chain((0..2).map(|_| deck_rooms[rng.gen_range(0..deck_rooms.len())]))
+                                 .collect();
+    }
+}
+
+/// Wires the station network, with every door and every room's lights on it, and places terminals, security
+/// cameras and sentry turrets about the rooms, with a security monitor in one room on each deck. The crew's starting
+/// room always has a terminal and never a turret.
+pub fn wire_station_network(entity_system: &mut World, rooms: &[Rectangle], rng: &mut StdRng)
+{
+    let doors: Vec<Coordinate> =
+    {
+        let map = entity_system.fetch::<Map>();
+        let mut doors: Vec<Coordinate> = Vec::new();
+
+        for z in 0..map.map_size.z
+        {
+            for y in 0..map.map_size.y
+            {
+                for x in 0..map.map_size.x
+                {
+                    if map.tiles[x][y][z].map(|tile| tile.door.is_some()).unwrap_or(false)
+                    {
+                        doors.push(Coordinate::new(x, y, z));
+                    }
+                }
+            }
+        }
+        doors
+    };
+
+    {
+        let mut network = entity_system.fetch_mut::<StationNetwork>();
+        let map = entity_system.fetch::<Map>();
+
+        for door in doors
+        {
+            network.connect(door.z, DeviceKind::Door(door));
+        }
+        for room in rooms.iter()
+        {
+            network.connect(room.corner_one.z, DeviceKind::Lights(*room));
+
+            // Lights that failed before the player arrived stay switched off until someone switches them on
+            if let Some(lights) = network.devices.last_mut()
+            {
+                lights.online = map.lights_on(room);
+            }
+        }
+    }
+
+    for (room_number, room) in rooms.iter().enumerate()
+    {
+        if room_number == 0 || rng.gen_range(0..3) == 0
+        {
+            if let Some(coordinate) = random_floor_tile(entity_system, room, rng)
+            {
+                let logs = (0..rng.gen_range(1..=3)).map(|_| rng.gen_range(0..TERMINAL_LOGS.len())).collect();
+                terminal(entity_system, coordinate, logs);
+            }
+        }
+        if rng.gen_range(0..4) == 0
+        {
+            if let Some(coordinate) = random_floor_tile(entity_system, room, rng)
+            {
+                let camera = security_camera(entity_system, coordinate);
+                entity_system.fetch_mut::<StationNetwork>().connect(coordinate.z, DeviceKind::Camera(camera));
+            }
+        }
+        if room_number != 0 && rng.gen_range(0..8) == 0
+        {
+            if let Some(coordinate) = random_floor_tile(entity_system, room, rng)
+            {
+                let turret = turret(entity_system, coordinate);
+                entity_system.fetch_mut::<StationNetwork>().connect(coordinate.z, DeviceKind::Turret(turret));
+            }
+        }
+    }
+
+    let decks = entity_system.fetch::<Map>().map_size.z;
+    for deck in 0..decks
+    {
+        let deck_rooms: Vec<&Rectangle> = rooms.iter().filter(|room| room.corner_one.z == deck).collect();
+
+        if deck_rooms.is_empty()
+        {
+            continue;
+        }
+
+        let room = deck_rooms[rng.gen_range(0..deck_rooms.len())];
+        if let Some(coordinate) = random_floor_tile(entity_system, room, rng)
+        {
+            security_monitor(entity_system, coordinate);
+        }
+    }
+}
+
+fn terminal(entity_system: &mut World, coordinate: Coordinate, logs: Vec<usize>) -> Entity
+{
+    entity_system.create_entity()
+                 .with(coordinate)
+                 .with(Renderable { glyph: rltk::to_cp437('≡'),
+                                    foreground_color: RGB::named(rltk::CYAN),
+                                    background_color: RGB::named(rltk::BLACK),
+                                    render_order: 2})
+                 .with(Name { name: "Terminal".to_string() })
+                 .with(Terminal { logs, access: false })
+                 .with(PowerConsumer { draw: 1, priority: TERMINAL_PRIORITY, powered: true })
+                 .build()
+}
+
+fn security_camera(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    entity_system.create_entity()
+                 .with(coordinate)
+                 .with(Renderable { glyph: rltk::to_cp437('°'),
+                                    foreground_color: Faction::Security.color(),
+                                    background_color: RGB::named(rltk::BLACK),
+                                    render_order: 2})
+                 .with(Name { name: "Security camera".to_string() })
+                 .with(Viewshed { visible_tiles: Vec::new(), range: 8, dirty: true })
+                 .with(MapMemory::new())
+                 .with(SecurityCamera { tracking_player: false })
+                 .with(PowerConsumer { draw: 1, priority: SECURITY_PRIORITY, powered: true })
+                 .build()
+}
+
+fn security_monitor(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    entity_system.create_entity()
+                 .with(coordinate)
+                 .with(Renderable { glyph: rltk::to_cp437('■'),
+                                    foreground_color: Faction::Security.color(),
+                                    background_color: RGB::named(rltk::BLACK),
+                                    render_order: 2})
+                 .with(Name { name: "Security monitor".to_string() })
+                 .with(SecurityMonitor {})
+                 .with(PowerConsumer { draw: 1, priority: TERMINAL_PRIORITY, powered: true })
+                 .build()
+}
+
+/// A sentry gun loyal to station security, which doesn't breathe, move or think
+fn turret(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    entity_system.create_entity()
+                 .with(coordinate)
+                 .with(Renderable { glyph: rltk::to_cp437('T'),
+                                    foreground_color: Faction::Security.color(),
+                                    background_color: RGB::named(rltk::BLACK),
+                                    render_order: 1})
+                 .with(Name { name: "Sentry turret".to_string() })
+                 .with(FactionMember { faction: Faction::Security })
+                 .with(CombatStats { max_hp: 12, hp: 12 })
+                 .with(Viewshed { visible_tiles: Vec::new(), range: 8, dirty: true })
+                 .with(Turret { range: 6, damage: 2 })
+                 .with(PowerConsumer { draw: 2, priority: SECURITY_PRIORITY, powered: true })
+                 .build()
+}
+
+/// Puts a generator in the first room of every deck and a battery in a random room of it
+pub fn install_power(entity_system: &mut World, rooms: &[Rectangle], rng: &mut StdRng)
+{
+    let decks = entity_system.fetch::<Map>().map_size.z;
+    for deck in 0..decks
+    {
+        let deck_rooms: Vec<&Rectangle> = rooms.iter().filter(|room| room.corner_one.z == deck).collect();
+
+        if deck_rooms.is_empty()
+        {
+            continue;
+        }
+
+        if let Some(coordinate) = random_floor_tile(entity_system, deck_rooms[0], rng)
+        {
+            generator(entity_system, coordinate);
+        }
+        let room = deck_rooms[rng.gen_range(0..deck_rooms.len())];
+        if let Some(coordinate) = random_floor_tile(entity_system, room, rng)
+        {
+            battery(entity_system, coordinate);
+        }
+    }
+}
+
+/// Gives every deck an environmental control room running a scrubber, an oxygen generator and a heater big enough
+/// for the corridors, and some other rooms a smaller unit of their own. Units start anywhere from worn to like new.
+pub fn install_life_support(entity_system: &mut World, rooms: &[Rectangle], rng: &mut StdRng)
+{
+    const LIFE_SUPPORT_KINDS: [LifeSupportKind; 3] = [LifeSupportKind::Scrubber, LifeSupportKind::OxygenGenerator, LifeSupportKind::Heater];
+
+    let decks = entity_system.fetch::<Map>().map_size.z;
+    for deck in 0..decks
+    {
+        let deck_rooms: Vec<&Rectangle> = rooms.iter().filter(|room| room.corner_one.z == deck).collect();
+
+        if deck_rooms.is_empty()
+        {
+            continue;
+        }
+
+        let control_room = rng.gen_range(0..deck_rooms.len());
+        for (room_number, room) in deck_rooms.iter().enumerate()
+        {
+            let kinds: Vec<(LifeSupportKind, bool)> = match room_number == control_room
+            {
+                true => LIFE_SUPPORT_KINDS.iter().map(|kind| (*kind, true)).collect(),
+                false if rng.gen_range(0..3) == 0 => vec![(LIFE_SUPPORT_KINDS[rng.gen_range(0..LIFE_SUPPORT_KINDS.len())], false)],
+                false => Vec::new(),
+            };
+
+            for (kind, primary) in kinds
+            {
+                if let Some(coordinate) = random_floor_tile(entity_system, room, rng)
+                {
+                    let condition = rng.gen_range(MAX_CONDITION / 2..=MAX_CONDITION);
+                    life_support(entity_system, coordinate, kind, primary, condition);
+                }
+            }
+        }
+    }
+}
+
+/// A life support unit, a primary one serving a whole deck putting out several times what a room's own does
+fn life_support(entity_system: &mut World, coordinate: Coordinate, kind: LifeSupportKind, primary: bool, condition: i32) -> Entity
+{
+    let (name, glyph, color, output) = match kind
+    {
+        LifeSupportKind::Scrubber => ("Scrubber", '≈', RGB::named(rltk::LIGHT_BLUE), 3.0),
+        LifeSupportKind::OxygenGenerator => ("Oxygen generator", 'Ω', RGB::named(rltk::WHITE), 2.0),
+        LifeSupportKind::Heater => ("Heater", '§', RGB::named(rltk::ORANGE_RED), 12.0),
+    };
+
+    entity_system.create_entity()
+                 .with(coordinate)
+                 .with(Renderable { glyph: rltk::to_cp437(glyph),
+                                    foreground_color: color,
+                                    background_color: RGB::named(rltk::BLACK),
+                                    render_order: 2})
+                 .with(Name { name: name.to_string() })
+                 .with(LifeSupport { kind, output: if primary { output * 4.0 } else { output }, condition })
+                 .with(PowerConsumer { draw: if primary { 4 } else { 2 }, priority: LIFE_SUPPORT_PRIORITY, powered: true })
+                 .build()
+}
+
+fn generator(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    entity_system.create_entity()
+                 .with(coordinate)
+                 .with(Renderable { glyph: rltk::to_cp437('☼'),
+                                    foreground_color: RGB::named(rltk::ORANGE),
+                                    background_color: RGB::named(rltk::BLACK),
+                                    render_order: 2})
+                 .with(Name { name: "Generator".to_string() })
+                 .with(Generator { output: 80 })
+                 .build()
+}
+
+fn battery(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    entity_system.create_entity()
+                 .with(coordinate)
+                 .with(Renderable { glyph: rltk::to_cp437('▬'),
+                                    foreground_color: RGB::named(rltk::GREEN),
+                                    background_color: RGB::named(rltk::BLACK),
+                                    render_order: 2})
+                 .with(Name { name: "Battery bank".to_string() })
+                 .with(Battery { charge: 100, capacity: 200, rate: 10 })
+                 .build()
+}
+
+/// A random spot inside the room, as long as it can be stood on
+fn random_floor_tile(entity_system: &World, room: &Rectangle, rng: &mut StdRng) -> Option<Coordinate>
+{
+    let coordinate = Coordinate::new(rng.gen_range(room.corner_one.x + 1..=room.corner_two.x),
+                                     rng.gen_range(room.corner_one.y + 1..=room.corner_two.y),
+                                     room.corner_one.z);
+
+    match entity_system.fetch::<Map>().get(coordinate)
+    {
+        Some(tile) if tile.passable => Some(coordinate),
+        _ => None,
+    }
+}
+
+/// A tank of fuel that blows up once shot, caught in a blast or burnt through
+fn fuel_tank(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    entity_system.create_entity()
+                 .with(coordinate)
+                 .with(Renderable { glyph: rltk::to_cp437('Φ'),
+                                    foreground_color: RGB::named(rltk::ORANGE_RED),
+                                    background_color: RGB::named(rltk::BLACK),
+                                    render_order: 1})
+                 .with(Name { name: "Fuel tank".to_string() })
+                 .with(CombatStats { max_hp: 4, hp: 4 })
+                 .with(Explosive { radius: 3, damage: 12 })
+                 .build()
+}
+
+/// Someone or something belonging to a faction, which breathes unless it is a drone. It has `hp` hit points, hits
+/// for `damage` and decides what to do by the behaviour profile with the given id.
+fn actor(entity_system: &mut World, coordinate: Coordinate, name: &str, glyph: rltk::FontCharType, (hp, damage): (i32, i32), faction: Faction, profile: &str) -> Entity
+{
+    let actor = entity_system.create_entity()
+                             .with(coordinate)
+                             .with(Renderable { glyph,
+                                                foreground_color: faction.color(),
+                                                background_color: RGB::named(rltk::BLACK),
+                                                render_order: 1})
+                             .with(Name { name: name.to_string() })
+                             .with(FactionMember { faction })
+                             .with(CombatStats { max_hp: hp, hp })
+                             .with(MeleeAttack { damage })
+                             .with(Viewshed { visible_tiles: Vec::new(), range: 8, dirty: true })
+                             .with(MapMemory::new())
+                             .with(Brain::new(profile))
+                             .build();
+
+    if faction != Faction::Drones
+    {
+        entity_system.write_storage::<Breathes>().insert(actor, Breathes{}).expect("Unable to insert");
+    }
+    if faction == Faction::Crew || faction == Faction::Security
+    {
+        entity_system.write_storage::<SharedMemory>().insert(actor, SharedMemory { group: CREW_MEMORY_GROUP }).expect("Unable to insert");
+    }
+
+    actor
+}
+
+fn random_item(entity_system: &mut World, coordinate: Coordinate, rng: &mut StdRng) -> Entity
+{
+    match rng.gen_range(0..8)
+    {
+        0 => ration_pack(entity_system, coordinate),
+        1 => medkit(entity_system, coordinate),
+        2 => oxygen_canister(entity_system, coordinate),
+        3 => rivet_magazine(entity_system, coordinate),
+        4 => breaching_charge(entity_system, coordinate),
+        5 => extinguisher(entity_system, coordinate),
+        6 => water_pouch(entity_system, coordinate),
+        _ => rivet_gun(entity_system, coordinate),
+    }
+}
+
+fn breaching_charge(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    let charge = item(entity_system, coordinate, "Breaching charge", rltk::to_cp437('*'), RGB::named(rltk::ORANGE_RED));
+    entity_system.write_storage::<Explosive>().insert(charge, Explosive { radius: 3, damage: 10 }).expect("Unable to insert");
+    charge
+}
+
+fn ration_pack(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    let ration_pack = item(entity_system, coordinate, "Ration pack", rltk::to_cp437('%'), RGB::named(rltk::ORANGE));
+    entity_system.write_storage::<Flammable>().insert(ration_pack, Flammable{}).expect("Unable to insert");
+    entity_system.write_storage::<Consumable>().insert(ration_pack, Consumable { food: 600, water: 50, ..Default::default() }).expect("Unable to insert");
+    ration_pack
+}
+
+fn medkit(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    let medkit = item(entity_system, coordinate, "Medkit", rltk::to_cp437('+'), RGB::named(rltk::RED));
+    entity_system.write_storage::<Consumable>().insert(medkit, Consumable { healing: 6, ..Default::default() }).expect("Unable to insert");
+    medkit
+}
+
+fn water_pouch(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    let water_pouch = item(entity_system, coordinate, "Water pouch", rltk::to_cp437('!'), RGB::named(rltk::BLUE));
+    entity_system.write_storage::<Consumable>().insert(water_pouch, Consumable { water: 400, ..Default::default() }).expect("Unable to insert");
+    water_pouch
+}
+
+fn oxygen_canister(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    let canister = item(entity_system, coordinate, "Oxygen canister", rltk::to_cp437('!'), RGB::named(rltk::CYAN));
+    entity_system.write_storage::<Consumable>().insert(canister, Consumable { oxygen: 40, ..Default::default() }).expect("Unable to insert");
+    canister
+}
+
+fn extinguisher(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    let extinguisher = item(entity_system, coordinate, "Fire extinguisher", rltk::to_cp437('&'), RGB::named(rltk::RED));
+    entity_system.write_storage::<Extinguisher>().insert(extinguisher, Extinguisher { charges: 5 }).expect("Unable to insert");
+    extinguisher
+}
+
+fn rivet_gun(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    let gun = item(entity_system, coordinate, "Rivet gun", rltk::to_cp437('{'), RGB::named(rltk::LIGHT_GRAY));
+    entity_system.write_storage::<RangedWeapon>().insert(gun, RangedWeapon { range: 10, damage: 4 }).expect("Unable to insert");
+    gun
+}
+
+fn rivet_magazine(entity_system: &mut World, coordinate: Coordinate) -> Entity
+{
+    let magazine = item(entity_system, coordinate, "Rivet magazine", rltk::to_cp437('='), RGB::named(rltk::LIGHT_GRAY));
+    entity_system.write_storage::<Ammunition>().insert(magazine, Ammunition { rounds: 8 }).expect("Unable to insert");
+    magazine
+}
+
+fn item(entity_system: &mut World, coordinate: Coordinate, name: &str, glyph: rltk::FontCharType, color: RGB) -> Entity
+{
+    entity_system.create_entity()
+                 .with(coordinate)
+                 .with(Renderable { glyph,
+                                    foreground_color: color,
+                                    background_color: RGB::named(rltk::BLACK),
+                                    render_order: 2})
+                 .with(Name { name: name.to_string() })
+                 .with(Item{})
+                 .build()
+}
@@ -0,0 +1,351 @@
+use specs_derive::Component;
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::entity_components::CombatStats;
+use super::map::Map;
+use super::map_tile::{Door, MapTile};
+use super::power::{is_powered, PowerConsumer, PowerGrid};
+use super::rectangle::Rectangle;
+use super::skills::{player_skill_check, Skill};
+
+
+/// Chance in percent an untrained hand gets past a terminal's login
+const TERMINAL_HACKING_BASE_CHANCE: i32 = 30;
+/// Most devices of a kind `list` shows, nearest the terminal first
+const MAX_LISTED_DEVICES: usize = 12;
+
+/// Log entries terminals are loaded with, as a title and the text shown when it is read
+pub const TERMINAL_LOGS: [(&str, &str); 8] =
+[
+    ("Maintenance rota", "Scrubber filters on every deck are three cycles overdue. Again."),
+    ("Security memo", "Sentry turrets answer to any terminal once logged in. Keep your passwords to yourselves."),
+    ("Incident report", "Hull breach near the cargo ring. Sealed with foam, needs a proper patch."),
+    ("Personal note", "If anyone finds my ration stash behind the medbay panels, it is NOT communal."),
+    ("Docking log", "Unregistered shuttle latched on at the aft port. No answer on any channel."),
+    ("Drone fault", "Maintenance drones ignoring recall orders. Do not approach, do not try to reason with them."),
+    ("Power advisory", "Lighting in the outer corridors cut to half to save power until further notice."),
+    ("Duty roster", "Camera coverage on the lower decks is patchy. Report anything you see in person."),
+];
+
+/// What a device on the station network is, and what it works
+#[derive(Clone, Copy)]
+pub enum DeviceKind
+{
+    Door(Coordinate),
+    /// The lighting of a whole room
+    Lights(Rectangle),
+    Camera(Entity),
+    Turret(Entity),
+}
+
+impl DeviceKind
+{
+    /// Start of the labels devices of this kind are listed under
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            DeviceKind::Door(_) => "door",
+            DeviceKind::Lights(_) => "lights",
+            DeviceKind::Camera(_) => "camera",
+            DeviceKind::Turret(_) => "turret",
+        }
+    }
+}
+
+/// Something a terminal can reach over the network
+#[derive(Clone)]
+pub struct NetworkDevice
+{
+    /// What it is called on the network, such as `door-3`
+    pub label: String,
+    pub deck: usize,
+    pub kind: DeviceKind,
+    /// Whether it is switched on, cameras and turrets only work while online and lights only shine while on
+    pub online: bool,
+    /// Whether its section of the power grid is supplying it
+    pub powered: bool,
+}
+
+/// Every device wired into the station network. A terminal reaches every device on its own deck.
+#[derive(Default)]
+pub struct StationNetwork
+{
+    pub devices: Vec<NetworkDevice>,
+}
+
+impl StationNetwork
+{
+    /// Adds a device, labelling it after its kind and how many of that kind are on the deck already
+    pub fn connect(&mut self, deck: usize, kind: DeviceKind)
+    {
+        let number = self.devices.iter().filter(|device| device.deck == deck && device.kind.name() == kind.name()).count() + 1;
+        self.devices.push(NetworkDevice { label: format!("{}-{}", kind.name(), number), deck, kind, online: true, powered: true });
+    }
+
+    fn find(&self, deck: usize, label: &str) -> Option<usize>
+    {
+        self.devices.iter().position(|device| device.deck == deck && device.label == label)
+    }
+
+    /// Every camera on a deck that is online, in the order the network lists them
+    pub fn online_cameras(&self, deck: usize) -> Vec<(Entity, &str)>
+    {
+        self.devices.iter()
+                    .filter(|device| device.deck == deck && device.online && device.powered)
+                    .filter_map(|device| match device.kind
+                    {
+                        DeviceKind::Camera(camera) => Some((camera, device.label.as_str())),
+                        _ => None,
+                    })
+                    .collect()
+    }
+
+    /// Whether a camera or turret is switched on and powered, which anything not on the network always is
+    pub fn is_online(&self, entity: Entity) -> bool
+    {
+        self.devices.iter()
+                    .find(|device| matches!(device.kind, DeviceKind::Camera(wired) | DeviceKind::Turret(wired) if wired == entity))
+                    .map(|device| device.online && device.powered)
+                    .unwrap_or(true)
+    }
+}
+
+//Terminal
+/// A computer terminal wired into the station network, which needs hacking before it will work any device
+#[derive(Component)]
+pub struct Terminal
+{
+    /// Indices into `TERMINAL_LOGS` of the entries stored on it
+    pub logs: Vec<usize>,
+    /// Whether the player has logged in
+    pub access: bool,
+}
+
+//SecurityCamera
+/// A camera watching over a room from the wall, which reports the player to security unless they are on good terms
+#[derive(Component)]
+pub struct SecurityCamera
+{
+    /// Whether it had the player in sight last turn, so each sighting raises the alarm once
+    pub tracking_player: bool,
+}
+
+//SecurityMonitor
+/// A bank of screens in a security station showing the feed of any camera online on its deck
+#[derive(Component)]
+pub struct SecurityMonitor {}
+
+//Turret
+/// A sentry gun that shoots at the nearest enemy of its faction in view for as long as it is online
+#[derive(Component)]
+pub struct Turret
+{
+    pub range: i32,
+    pub damage: i32,
+}
+
+/// Runs a command typed at a terminal, giving back the lines it prints and whether it took a turn
+pub fn run_command(entity_system: &mut World, terminal: Entity, command: &str) -> (Vec<String>, bool)
+{
+    let deck = match entity_system.read_storage::<Coordinate>().get(terminal)
+    {
+        Some(coordinate) => coordinate.z,
+        None => return (vec!["No carrier.".to_string()], false),
+    };
+    if !is_powered(&entity_system.read_storage::<PowerConsumer>(), terminal)
+    {
+        return (vec!["The screen is dark.".to_string()], false);
+    }
+    let access = entity_system.read_storage::<Terminal>().get(terminal).map(|terminal| terminal.access).unwrap_or(false);
+    let words: Vec<&str> = command.split_whitespace().collect();
+
+    match words.as_slice()
+    {
+        [] => (Vec::new(), false),
+        ["help"] => (vec!["help  list [door|lights|camera|turret]  logs  read <n>  hack  power".to_string(),
+                          "open <door>  lock <door>  lights <lights> on|off".to_string(),
+                          "enable <device>  disable <device>  exit".to_string()], false),
+        ["list"] =>
+        {
+            let network = entity_system.fetch::<StationNetwork>();
+            let lines = ["door", "lights", "camera", "turret"].iter()
+                .map(|kind| (kind, network.devices.iter().filter(|device| device.deck == deck && device.kind.name() == *kind).count()))
+                .map(|(kind, count)| format!("{:<10}{} on this deck", kind, count))
+                .collect();
+            (lines, false)
+        }
+        ["list", kind] =>
+        {
+            let network = entity_system.fetch::<StationNetwork>();
+            let map = entity_system.fetch::<Map>();
+            let coordinates = entity_system.read_storage::<Coordinate>();
+            let here = coordinates.get(terminal).copied().unwrap_or_else(|| Coordinate::new(0, 0, deck));
+            let location = |device: &NetworkDevice| match device.kind
+            {
+                DeviceKind::Door(coordinate) => Some(coordinate),
+                DeviceKind::Lights(room) => Some(room.center()),
+                DeviceKind::Camera(entity) | DeviceKind::Turret(entity) => coordinates.get(entity).copied(),
+            };
+            let mut devices: Vec<(i32, &NetworkDevice)> = network.devices.iter()
+                .filter(|device| device.deck == deck && device.kind.name() == *kind)
+                .map(|device| (location(device).map(|at| (at.x as i32 - here.x as i32).abs().max((at.y as i32 - here.y as i32).abs())).unwrap_or(i32::MAX), device))
+                .collect();
+            devices.sort_by_key(|(distance, _device)| *distance);
+
+            let mut lines: Vec<String> = devices.iter()
+                                                .take(MAX_LISTED_DEVICES)
+                                                .map(|(_distance, device)| format!("{:<10}{}", device.label, device_status(&map, device)))
+                                                .collect();
+            match devices.len()
+            {
+                0 => lines.push(format!("No {} devices on this deck.", kind)),
+                count if count > MAX_LISTED_DEVICES => lines.push(format!("...and {} further away.", count - MAX_LISTED_DEVICES)),
+                _ => {}
+            }
+            (lines, false)
+        }
+        ["power"] =>
+        {
+            let grid = entity_system.fetch::<PowerGrid>();
+            let map = entity_system.fetch::<Map>();
+            let load = entity_system.read_storage::<Coordinate>().get(terminal).and_then(|coordinate| grid.load_at(&map, *coordinate));
+
+            match load
+            {
+                Some(load) => (vec![format!("Section supply {}, demand {}.", load.supply, load.demand),
+                                    format!("Batteries hold {}.", load.stored),
+                                    match load.demand > load.supply
+                                    {
+                                        true => "Warning: demand exceeds supply, low priority systems shed.".to_string(),
+                                        false => "Load nominal.".to_string(),
+                                    }], false),
+                None => (vec!["No grid readings.".to_string()], false),
+            }
+        }
+        ["logs"] =>
+        {
+            let logs = entity_system.read_storage::<Terminal>().get(terminal).map(|terminal| terminal.logs.clone()).unwrap_or_default();
+            let lines = match logs.is_empty()
+            {
+                true => vec!["No log entries.".to_string()],
+                false => logs.iter().enumerate().map(|(number, log)| format!("{}. {}", number + 1, TERMINAL_LOGS[*log].0)).collect(),
+            };
+            (lines, false)
+        }
+        ["read", number] =>
+        {
+            let log = number.parse::<usize>().ok()
+                            .and_then(|number| entity_system.read_storage::<Terminal>().get(terminal)?.logs.get(number.checked_sub(1)?).copied());
+            match log
+            {
+                Some(log) => (vec![TERMINAL_LOGS[log].0.to_string(), TERMINAL_LOGS[log].1.to_string()], false),
+                None => (vec![format!("No log entry {}.", number)], false),
+            }
+        }
+        ["hack"] if access => (vec!["Already logged in.".to_string()], false),
+        ["hack"] =>
+        {
+            match player_skill_check(entity_system, Skill::Hacking, TERMINAL_HACKING_BASE_CHANCE)
+            {
+                true =>
+                {
+                    if let Some(terminal) = entity_system.write_storage::<Terminal>().get_mut(terminal)
+                    {
+                        terminal.access = true;
+                    }
+                    (vec!["Password accepted. Access granted.".to_string()], true)
+                }
+                false => (vec!["Access denied.".to_string()], true),
+            }
+        }
+        ["open" | "lock" | "lights" | "enable" | "disable", ..] if !access => (vec!["Access denied. Type hack to log in.".to_string()], false),
+        [verb @ ("open" | "lock" | "enable" | "disable"), label] => (vec![work_device(entity_system, deck, label, verb)], true),
+        ["lights", label, setting @ ("on" | "off")] => (vec![work_device(entity_system, deck, label, setting)], true),
+        _ => (vec![format!("Unknown command '{}'. Type help for commands.", command.trim())], false),
+    }
+}
+
+/// How a device is doing, as shown by `list`
+fn device_status(map: &Map, device: &NetworkDevice) -> &'static str
+{
+    match device.kind
+    {
+        DeviceKind::Door(coordinate) => match map.get(coordinate).and_then(|tile| tile.door)
+        {
+            Some(Door::Open) => "open",
+            Some(Door::Closed) => "closed",
+            Some(Door::Locked) => "locked",
+            None => "no response",
+        },
+        DeviceKind::Lights(_) | DeviceKind::Camera(_) | DeviceKind::Turret(_) if !device.powered => "no power",
+        DeviceKind::Lights(room) => match map.lights_on(&room)
+        {
+            true => "on",
+            false => "off",
+        },
+        DeviceKind::Camera(_) | DeviceKind::Turret(_) => match device.online
+        {
+            true => "online",
+            false => "offline",
+        },
+    }
+}
+
+/// Carries out an order for one device on the deck's network, giving back the terminal's reply
+fn work_device(entity_system: &mut World, deck: usize, label: &str, order: &str) -> String
+{
+    let coordinates = entity_system.read_storage::<Coordinate>();
+    let combat_stats = entity_system.read_storage::<CombatStats>();
+    let mut network = entity_system.fetch_mut::<StationNetwork>();
+    let mut map = entity_system.fetch_mut::<Map>();
+    let index = match network.find(deck, label)
+    {
+        Some(index) => index,
+        None => return format!("No device '{}' on this deck.", label),
+    };
+    let device = &mut network.devices[index];
+
+    match (device.kind, order)
+    {
+        (DeviceKind::Door(coordinate), "lock") if !device.powered && map.get(coordinate).and_then(|tile| tile.door).is_some() =>
+        {
+            format!("{} has no power to hold a maglock.", device.label)
+        }
+        (DeviceKind::Door(coordinate), "open" | "lock") if map.get(coordinate).and_then(|tile| tile.door).is_none() =>
+        {
+            format!("{} is not responding.", device.label)
+        }
+        (DeviceKind::Door(coordinate), "open") =>
+        {
+            map.open_door(coordinate);
+            format!("{} opened.", device.label)
+        }
+        (DeviceKind::Door(coordinate), "lock") if (&coordinates, &combat_stats).join().any(|(standing, _stats)| *standing == coordinate) =>
+        {
+            format!("{} is obstructed.", device.label)
+        }
+        (DeviceKind::Door(coordinate), "lock") =>
+        {
+            map.set_tile(coordinate, MapTile::locked_door());
+            format!("{} shut and locked.", device.label)
+        }
+        (DeviceKind::Lights(room), "on" | "off") =>
+        {
+            device.online = order == "on";
+            map.set_lighting(&room, device.online && device.powered);
+            match device.powered
+            {
+                true => format!("{} switched {}.", device.label, order),
+                false => format!("{} switched {}, but has no power.", device.label, order),
+            }
+        }
+        (DeviceKind::Camera(_) | DeviceKind::Turret(_), "enable" | "disable") =>
+        {
+            device.online = order == "enable";
+            format!("{} {}d.", device.label, order)
+        }
+        _ => format!("{} can't {}.", device.label, order),
+    }
+}
@@ -0,0 +1,55 @@
+use specs::prelude::*;
+
+use super::entity_components::*;
+use super::game_log::GameLog;
+use super::status_effects::StatusEffects;
+
+
+/// Runs every status effect for a turn: deals each effect's damage, counts effects down and redraws the view of
+/// anything whose sight range has just changed
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem
+{
+    type SystemData = (WriteExpect<'a, GameLog>,
+                       Entities<'a>,
+                       ReadStorage<'a, Player>,
+                       WriteStorage<'a, StatusEffects>,
+                       WriteStorage<'a, SufferDamage>,
+                       WriteStorage<'a, Viewshed>);
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let (mut log, entities, players, mut status_effects, mut suffering, mut viewsheds) = data;
+
+        for (entity, effects) in (&entities, &mut status_effects).join()
+        {
+            let vision_before = effects.vision_range(100);
+            let damage = effects.damage_this_turn();
+
+            if damage > 0
+            {
+                SufferDamage::new_damage(&mut suffering, entity, damage);
+
+                if players.get(entity).is_some()
+                {
+                    let causes: Vec<&str> = effects.effects.iter()
+                                                           .filter(|effect| effect.kind.damage_per_turn(effect.stacks, effect.turns_left) > 0)
+                                                           .map(|effect| effect.kind.name())
+                                                           .collect();
+                    log.add(format!("You take {} damage ({}).", damage, causes.join(", ")));
+                }
+            }
+
+            effects.tick();
+
+            if effects.vision_range(100) != vision_before
+            {
+                if let Some(viewshed) = viewsheds.get_mut(entity)
+                {
+                    viewshed.dirty = true;
+                }
+            }
+        }
+    }
+}
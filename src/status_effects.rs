@@ -0,0 +1,205 @@
+use specs_derive::Component;
+use specs::prelude::*;
+
+
+/// How a status effect combines with one already running of the same kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stacking
+{
+    /// Each application adds a stack, up to a limit, and resets the duration
+    Intensity { max_stacks: i32 },
+    /// Each application adds its duration to what is left
+    Duration,
+    /// Each application only tops the duration back up
+    Refresh,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StatusKind
+{
+    Bleeding,
+    Burning,
+    Hypoxia,
+    Stunned,
+    Irradiated,
+    Poisoned,
+    Hypothermia,
+    Heatstroke,
+}
+
+impl StatusKind
+{
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            StatusKind::Bleeding => "Bleeding",
+            StatusKind::Burning => "Burning",
+            StatusKind::Hypoxia => "Hypoxia",
+            StatusKind::Stunned => "Stunned",
+            StatusKind::Irradiated => "Irradiated",
+            StatusKind::Poisoned => "Poisoned",
+            StatusKind::Hypothermia => "Hypothermia",
+            StatusKind::Heatstroke => "Heatstroke",
+        }
+    }
+
+    pub fn stacking(&self) -> Stacking
+    {
+        match self
+        {
+            StatusKind::Bleeding => Stacking::Intensity { max_stacks: 5 },
+            StatusKind::Burning => Stacking::Refresh,
+            StatusKind::Hypoxia => Stacking::Refresh,
+            StatusKind::Stunned => Stacking::Duration,
+            StatusKind::Irradiated => Stacking::Intensity { max_stacks: 10 },
+            StatusKind::Poisoned => Stacking::Intensity { max_stacks: 3 },
+            StatusKind::Hypothermia => Stacking::Intensity { max_stacks: 10 },
+            StatusKind::Heatstroke => Stacking::Intensity { max_stacks: 10 },
+        }
+    }
+
+    /// Damage the effect does each turn at the given number of stacks, on the given turn of the effect
+    pub fn damage_per_turn(&self, stacks: i32, turns_left: i32) -> i32
+    {
+        match self
+        {
+            StatusKind::Bleeding => stacks,
+            StatusKind::Burning => 2,
+            StatusKind::Hypoxia => 1,
+            StatusKind::Stunned => 0,
+            // Radiation sickness is slow, it only bites every few turns
+            StatusKind::Irradiated => if turns_left % 4 == 0 { stacks } else { 0 },
+            StatusKind::Poisoned => stacks,
+            // Cold and heat only start to hurt after a few turns out in them, and hurt more the longer it goes on
+            StatusKind::Hypothermia | StatusKind::Heatstroke => stacks / 4,
+        }
+    }
+
+    pub fn color(&self) -> rltk::RGB
+    {
+        match self
+        {
+            StatusKind::Bleeding => rltk::RGB::named(rltk::RED),
+            StatusKind::Burning => rltk::RGB::named(rltk::ORANGE),
+            StatusKind::Hypoxia => rltk::RGB::named(rltk::LIGHT_BLUE),
+            StatusKind::Stunned => rltk::RGB::named(rltk::YELLOW),
+            StatusKind::Irradiated => rltk::RGB::named(rltk::GREEN),
+            StatusKind::Poisoned => rltk::RGB::named(rltk::PURPLE),
+            StatusKind::Hypothermia => rltk::RGB::named(rltk::CYAN),
+            StatusKind::Heatstroke => rltk::RGB::named(rltk::ORANGE_RED),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct StatusEffect
+{
+    pub kind: StatusKind,
+    pub turns_left: i32,
+    pub stacks: i32,
+}
+
+//StatusEffects
+/// Every status effect running on an entity
+#[derive(Component, Default, Clone)]
+pub struct StatusEffects
+{
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects
+{
+    /// Adds an effect for a number of turns, combining it with any running effect of the same kind by its stacking rule
+    pub fn apply(&mut self, kind: StatusKind, turns: i32)
+    {
+        match self.effects.iter_mut().find(|effect| effect.kind == kind)
+        {
+            Some(effect) => match kind.stacking()
+            {
+                Stacking::Intensity { max_stacks } =>
+                {
+                    effect.stacks = (effect.stacks + 1).min(max_stacks);
+                    effect.turns_left = effect.turns_left.max(turns);
+                }
+                Stacking::Duration => effect.turns_left += turns,
+                Stacking::Refresh => effect.turns_left = effect.turns_left.max(turns),
+            },
+            None => self.effects.push(StatusEffect { kind, turns_left: turns, stacks: 1 }),
+        }
+    }
+
+    /// Adds an effect to an entity, giving it a set of status effects first if it has none
+    pub fn apply_to(store: &mut WriteStorage<StatusEffects>, entity: Entity, kind: StatusKind, turns: i32)
+    {
+        if let Some(effects) = store.get_mut(entity)
+        {
+            effects.apply(kind, turns);
+        }
+        else
+        {
+            let mut effects = StatusEffects::default();
+            effects.apply(kind, turns);
+            store.insert(entity, effects).expect("Unable to insert status effects");
+        }
+    }
+
+    pub fn remove(&mut self, kind: StatusKind)
+    {
+        self.effects.retain(|effect| effect.kind != kind);
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool
+    {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    pub fn stacks(&self, kind: StatusKind) -> i32
+    {
+        self.effects.iter().find(|effect| effect.kind == kind).map(|effect| effect.stacks).unwrap_or(0)
+    }
+
+    /// Whether the entity can act on its own this turn
+    pub fn can_move(&self) -> bool
+    {
+        !self.has(StatusKind::Stunned)
+    }
+
+    /// How far the entity can see with a normal sight range of `range`, starved of oxygen it sees half as far
+    pub fn vision_range(&self, range: i32) -> i32
+    {
+        match self.has(StatusKind::Hypoxia)
+        {
+            true => (range / 2).max(1),
+            false => range,
+        }
+    }
+
+    /// Damage actually taken from a hit of `amount`, radiation sickness leaves the body less able to take punishment
+    pub fn damage_taken(&self, amount: i32) -> i32
+    {
+        amount + self.stacks(StatusKind::Irradiated) / 3
+    }
+
+    /// Total damage the effects do this turn
+    pub fn damage_this_turn(&self) -> i32
+    {
+        self.effects.iter().map(|effect| effect.kind.damage_per_turn(effect.stacks, effect.turns_left)).sum()
+    }
+
+    /// Counts every effect down a turn and drops those that have run out
+    pub fn tick(&mut self)
+    {
+        for effect in self.effects.iter_mut()
+        {
+            effect.turns_left -= 1;
+        }
+        self.effects.retain(|effect| effect.turns_left > 0);
+    }
+}
+
+/// How far an entity with or without status effects sees, given its normal range
+pub fn vision_range(effects: Option<&StatusEffects>, range: i32) -> i32
+{
+    effects.map(|effects| effects.vision_range(range)).unwrap_or(range)
+}
@@ -0,0 +1,157 @@
+use specs_derive::Component;
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+
+
+/// Turns of breathing a full suit tank holds
+pub const MAX_SUIT_OXYGEN: i32 = 60;
+/// Turns a full stomach lasts
+pub const MAX_FOOD: i32 = 1200;
+/// Turns until a fully watered body runs dry
+pub const MAX_WATER: i32 = 800;
+/// Turns of activity before a rested body gives out
+pub const MAX_REST: i32 = 1000;
+
+/// How badly a need is met, from nothing wrong to run out completely
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NeedLevel
+{
+    Fine,
+    Low,
+    Critical,
+    Depleted,
+}
+
+impl NeedLevel
+{
+    /// Level of a need with `left` out of `max` remaining
+    pub fn of(left: i32, max: i32) -> NeedLevel
+    {
+        match left
+        {
+            left if left <= 0 => NeedLevel::Depleted,
+            left if left * 5 <= max => NeedLevel::Critical,
+            left if left * 2 <= max => NeedLevel::Low,
+            _ => NeedLevel::Fine,
+        }
+    }
+
+    pub fn color(&self) -> rltk::RGB
+    {
+        match self
+        {
+            NeedLevel::Fine => rltk::RGB::named(rltk::GREEN),
+            NeedLevel::Low => rltk::RGB::named(rltk::YELLOW),
+            NeedLevel::Critical => rltk::RGB::named(rltk::ORANGE),
+            NeedLevel::Depleted => rltk::RGB::named(rltk::RED),
+        }
+    }
+}
+
+//Needs
+/// What keeps a body going, each counting down as turns pass
+#[derive(Component, Clone, Debug)]
+pub struct Needs
+{
+    /// Breathing left in the suit tank, drawn on only when the air around is too thin
+    pub suit_oxygen: i32,
+    pub food: i32,
+    pub water: i32,
+    pub rest: i32,
+    /// Where the body stood last turn, a turn spent in the same place counts as resting
+    pub last_position: Option<Coordinate>,
+}
+
+impl Default for Needs
+{
+    fn default() -> Needs
+    {
+        Needs { suit_oxygen: MAX_SUIT_OXYGEN, food: MAX_FOOD, water: MAX_WATER, rest: MAX_REST, last_position: None }
+    }
+}
+
+impl Needs
+{
+    pub fn oxygen_level(&self) -> NeedLevel
+    {
+        NeedLevel::of(self.suit_oxygen, MAX_SUIT_OXYGEN)
+    }
+
+    pub fn food_level(&self) -> NeedLevel
+    {
+        NeedLevel::of(self.food, MAX_FOOD)
+    }
+
+    pub fn water_level(&self) -> NeedLevel
+    {
+        NeedLevel::of(self.water, MAX_WATER)
+    }
+
+    pub fn rest_level(&self) -> NeedLevel
+    {
+        NeedLevel::of(self.rest, MAX_REST)
+    }
+
+    pub fn food_label(&self) -> &'static str
+    {
+        match self.food_level()
+        {
+            NeedLevel::Fine => "Fed",
+            NeedLevel::Low => "Hungry",
+            NeedLevel::Critical => "Weak",
+            NeedLevel::Depleted => "Starving",
+        }
+    }
+
+    pub fn water_label(&self) -> &'static str
+    {
+        match self.water_level()
+        {
+            NeedLevel::Fine => "Hydrated",
+            NeedLevel::Low => "Thirsty",
+            NeedLevel::Critical => "Parched",
+            NeedLevel::Depleted => "Dehydrated",
+        }
+    }
+
+    pub fn rest_label(&self) -> &'static str
+    {
+        match self.rest_level()
+        {
+            NeedLevel::Fine => "Rested",
+            NeedLevel::Low => "Tired",
+            NeedLevel::Critical => "Exhausted",
+            NeedLevel::Depleted => "Collapsing",
+        }
+    }
+
+    /// How much of a consumable would actually go to use, as a share of the needs it tops up
+    pub fn benefit(&self, consumable: &Consumable) -> f32
+    {
+        let topped_up = |left: i32, max: i32, amount: i32| amount.min(max - left).max(0) as f32 / max as f32;
+
+        topped_up(self.food, MAX_FOOD, consumable.food)
+        + topped_up(self.water, MAX_WATER, consumable.water)
+        + topped_up(self.suit_oxygen, MAX_SUIT_OXYGEN, consumable.oxygen)
+    }
+
+    pub fn consume(&mut self, consumable: &Consumable)
+    {
+        self.food = (self.food + consumable.food).min(MAX_FOOD);
+        self.water = (self.water + consumable.water).min(MAX_WATER);
+        self.suit_oxygen = (self.suit_oxygen + consumable.oxygen).min(MAX_SUIT_OXYGEN);
+    }
+}
+
+//Consumable
+/// Something that is used up to eat, drink or refill a suit tank, in turns of each need it covers, or to treat wounds
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Consumable
+{
+    pub food: i32,
+    pub water: i32,
+    pub oxygen: i32,
+    /// Hit points it restores in untrained hands, it also stops bleeding
+    pub healing: i32,
+}
@@ -0,0 +1,185 @@
+use rand::Rng;
+use rand::rngs::StdRng;
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::game_log::GameLog;
+use super::map::Map;
+use super::status_effects::{StatusEffects, StatusKind};
+use super::survival::{NeedLevel, Needs, MAX_REST};
+
+
+/// Oxygen partial pressure below which anything that breathes starts to suffocate, in kilopascals
+pub const HYPOXIA_OXYGEN: f32 = 16.0;
+/// Turns hypoxia lingers after getting back to good air
+const HYPOXIA_TURNS: i32 = 2;
+/// Rest won back for each turn spent standing still
+const REST_RECOVERY: i32 = 3;
+/// Percent chance per turn an exhausted body staggers and loses its next turn
+const STAGGER_CHANCE: i32 = 10;
+/// Turns spent out cold after collapsing from exhaustion, and the rest that gives back
+const COLLAPSE_TURNS: i32 = 8;
+const COLLAPSE_REST: i32 = 100;
+
+/// Runs breathing, eating, drinking and tiredness for a turn. Anything that breathes draws on the air of its tile,
+/// or its suit tank when that air is too thin, and suffocates when neither will do. Bodies with needs get hungrier,
+/// thirstier and more tired, and suffer worse for each the further it has run down.
+pub struct SurvivalSystem {}
+
+impl<'a> System<'a> for SurvivalSystem
+{
+    type SystemData = (ReadExpect<'a, Map>,
+                       WriteExpect<'a, StdRng>,
+                       WriteExpect<'a, GameLog>,
+                       Entities<'a>,
+                       ReadStorage<'a, Coordinate>,
+                       ReadStorage<'a, Breathes>,
+                       ReadStorage<'a, Player>,
+                       WriteStorage<'a, Needs>,
+                       WriteStorage<'a, StatusEffects>,
+                       WriteStorage<'a, SufferDamage>,
+                       WriteStorage<'a, Viewshed>);
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let (map, mut rng, mut log, entities, coordinates, breathers, players, mut needs, mut status_effects, mut suffering, mut viewsheds) = data;
+
+        for (entity, _breathes, coordinate) in (&entities, &breathers, &coordinates).join()
+        {
+            if map.atmosphere(*coordinate).is_breathable(HYPOXIA_OXYGEN)
+            {
+                continue;
+            }
+
+            let is_player = players.get(entity).is_some();
+
+            if let Some(needs) = needs.get_mut(entity).filter(|needs| needs.suit_oxygen > 0)
+            {
+                let level_before = needs.oxygen_level();
+                needs.suit_oxygen -= 1;
+
+                if is_player && needs.oxygen_level() != level_before
+                {
+                    match needs.oxygen_level()
+                    {
+                        NeedLevel::Depleted => log.add("Your suit tank is empty!"),
+                        NeedLevel::Critical => log.add("Your suit tank is running low."),
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+
+            let already_hypoxic = status_effects.get(entity).map(|effects| effects.has(StatusKind::Hypoxia)).unwrap_or(false);
+            StatusEffects::apply_to(&mut status_effects, entity, StatusKind::Hypoxia, HYPOXIA_TURNS);
+
+            if !already_hypoxic
+            {
+                if let Some(viewshed) = viewsheds.get_mut(entity)
+                {
+                    viewshed.dirty = true;
+                }
+                if is_player
+                {
+                    match map.atmosphere(*coordinate).oxygen >= HYPOXIA_OXYGEN
+                    {
+                        true => log.add("The air is thick and stale, your head pounds and your vision starts to tunnel."),
+                        false => log.add("The air is too thin, your vision starts to tunnel."),
+                    }
+                }
+            }
+        }
+
+        for (entity, needs, coordinate) in (&entities, &mut needs, &coordinates).join()
+        {
+            let is_player = players.get(entity).is_some();
+            let labels_before = [needs.food_label(), needs.water_label(), needs.rest_label()];
+
+            needs.food = (needs.food - 1).max(0);
+            needs.water = (needs.water - 1).max(0);
+            needs.rest = match needs.last_position == Some(*coordinate)
+            {
+                true => (needs.rest + REST_RECOVERY).min(MAX_REST),
+                false => (needs.rest - 1).max(0),
+            };
+            needs.last_position = Some(*coordinate);
+
+            // Running low only hurts now and then, running out hurts every turn
+            let mut damage = 0;
+            let mut causes: Vec<&str> = Vec::new();
+
+            let food_damage = match needs.food_level()
+            {
+                NeedLevel::Critical if needs.food % 20 == 0 => 1,
+                NeedLevel::Depleted => 1,
+                _ => 0,
+            };
+            let water_damage = match needs.water_level()
+            {
+                NeedLevel::Critical if needs.water % 10 == 0 => 1,
+                NeedLevel::Depleted => 2,
+                _ => 0,
+            };
+
+            if food_damage > 0
+            {
+                damage += food_damage;
+                causes.push(needs.food_label());
+            }
+            if water_damage > 0
+            {
+                damage += water_damage;
+                causes.push(needs.water_label());
+            }
+
+            match needs.rest_level()
+            {
+                NeedLevel::Critical if rng.gen_range(0..100) < STAGGER_CHANCE =>
+                {
+                    StatusEffects::apply_to(&mut status_effects, entity, StatusKind::Stunned, 2);
+                    if is_player
+                    {
+                        log.add("You stagger with exhaustion.");
+                    }
+                }
+                NeedLevel::Depleted =>
+                {
+                    StatusEffects::apply_to(&mut status_effects, entity, StatusKind::Stunned, COLLAPSE_TURNS);
+                    needs.rest = COLLAPSE_REST;
+                    damage += 2;
+                    causes.push("Collapsing");
+                    if is_player
+                    {
+                        log.add("You collapse from exhaustion.");
+                    }
+                }
+                _ => {}
+            }
+
+            if is_player
+            {
+                let levels = [needs.food_level(), needs.water_level(), needs.rest_level()];
+                let labels = [needs.food_label(), needs.water_label(), needs.rest_label()];
+
+                for ((label, label_before), level) in labels.iter().zip(labels_before.iter()).zip(levels.iter())
+                {
+                    if label != label_before && *level != NeedLevel::Fine
+                    {
+                        log.add(format!("You are {}.", label.to_lowercase()));
+                    }
+                }
+            }
+
+            if damage > 0
+            {
+                SufferDamage::new_damage(&mut suffering, entity, damage);
+
+                if is_player
+                {
+                    log.add(format!("You take {} damage ({}).", damage, causes.join(", ")));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use specs::prelude::*;
+
+use super::ai_system::AiSystem;
+use super::atmosphere_system::AtmosphereSystem;
+use super::fire_system::FireSystem;
+use super::game_clock::GameClock;
+use super::physics_system::PhysicsSystem;
+use super::hazard_system::HazardSystem;
+use super::life_support_system::LifeSupportSystem;
+use super::memory_sharing_system::MemorySharingSystem;
+use super::power_system::PowerSystem;
+use super::status_effect_system::StatusEffectSystem;
+use super::survival_system::SurvivalSystem;
+use super::turret_system::TurretSystem;
+use super::security_camera_system::SecurityCameraSystem;
+use super::visibility_system::VisibilitySystem;
+
+
+/// Turns simulated at most per run, so a long stall never freezes a frame
+const MAX_CATCH_UP_TURNS: u64 = 10;
+
+/// Runs the systems that only act as game time passes, once for every turn that has gone by since it last ran,
+/// always in the same order so a replay unfolds exactly as the game did
+#[derive(Default)]
+pub struct TurnScheduler
+{
+    simulated_turn: u64,
+}
+
+impl TurnScheduler
+{
+    pub fn run(&mut self, entity_system: &mut World)
+    {
+        let turn = entity_system.fetch::<GameClock>().turn;
+        let turns_behind = turn.saturating_sub(self.simulated_turn);
+
+        for _ in 0..turns_behind.min(MAX_CATCH_UP_TURNS)
+        {
+            TurnScheduler::observe(entity_system);
+
+            let mut power_system = PowerSystem{};
+            let mut ai_system = AiSystem{};
+            let mut turret_system = TurretSystem{};
+            let mut security_camera_system = SecurityCameraSystem{};
+            let mut life_support_system = LifeSupportSystem{};
+            let mut atmosphere_system = AtmosphereSystem{};
+            let mut physics_system = PhysicsSystem{};
+            let mut fire_system = FireSystem{};
+            let mut hazard_system = HazardSystem{};
+            let mut survival_system = SurvivalSystem{};
+            let mut status_effect_system = StatusEffectSystem{};
+
+            power_system.run_now(entity_system);
+            ai_system.run_now(entity_system);
+            turret_system.run_now(entity_system);
+            security_camera_system.run_now(entity_system);
+            life_support_system.run_now(entity_system);
+            atmosphere_system.run_now(entity_system);
+            physics_system.run_now(entity_system);
+            fire_system.run_now(entity_system);
+            hazard_system.run_now(entity_system);
+            survival_system.run_now(entity_system);
+            status_effect_system.run_now(entity_system);
+            entity_system.maintain();
+
+            self.simulated_turn += 1;
+        }
+    }
+
+    /// Updates what everyone can see and pools what crews have seen, so the turn is acted on from fresh sightings
+    pub fn observe(entity_system: &mut World)
+    {
+        let mut visibility_system = VisibilitySystem{};
+        let mut memory_sharing_system = MemorySharingSystem{};
+
+        visibility_system.run_now(entity_system);
+        memory_sharing_system.run_now(entity_system);
+    }
+}
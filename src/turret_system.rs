@@ -0,0 +1,75 @@
+use specs::prelude::*;
+
+use super::coordinate::Coordinate;
+use super::entity_components::*;
+use super::factions::{is_hostile, FactionMember, Relations};
+use super::game_log::GameLog;
+use super::map::Map;
+use super::noise::{hidden_in_shadow, Sneaking};
+use super::station_network::{StationNetwork, Turret};
+
+
+/// Has every sentry turret that is online shoot at the nearest enemy it can see within range
+pub struct TurretSystem {}
+
+impl<'a> System<'a> for TurretSystem
+{
+    type SystemData = (ReadExpect<'a, Map>,
+                       ReadExpect<'a, StationNetwork>,
+                       ReadExpect<'a, Relations>,
+                       WriteExpect<'a, GameLog>,
+                       Entities<'a>,
+                       ReadStorage<'a, Turret>,
+                       ReadStorage<'a, Coordinate>,
+                       ReadStorage<'a, Viewshed>,
+                       ReadStorage<'a, FactionMember>,
+                       ReadStorage<'a, CombatStats>,
+                       ReadStorage<'a, Player>,
+                       ReadStorage<'a, Sneaking>,
+                       ReadStorage<'a, Name>,
+                       WriteStorage<'a, SufferDamage>);
+
+    fn run(&mut self, data : Self::SystemData)
+    {
+        let (map, network, relations, mut log, entities, turrets, coordinates, viewsheds, members, combat_stats, players, sneaking, names, mut suffering) = data;
+
+        let player_view: Vec<Coordinate> = (&players, &viewsheds).join().flat_map(|(_player, viewshed)| viewshed.visible_tiles.iter().copied()).collect();
+
+        for (turret_entity, turret, position, viewshed, member, stats) in (&entities, &turrets, &coordinates, &viewsheds, &members, &combat_stats).join()
+        {
+            if stats.hp <= 0 || !network.is_online(turret_entity)
+            {
+                continue;
+            }
+
+            let in_range = |coordinate: &Coordinate| (coordinate.x as i32 - position.x as i32).abs().max((coordinate.y as i32 - position.y as i32).abs()) <= turret.range;
+            let target = (&entities, &coordinates, &members, &combat_stats).join()
+                .filter(|(other, coordinate, other_member, other_stats)|
+                {
+                    *other != turret_entity && other_stats.hp > 0 && coordinate.z == position.z && in_range(coordinate)
+                    && viewshed.visible_tiles.contains(coordinate)
+                    && !hidden_in_shadow(&map, sneaking.get(*other).is_some(), **coordinate, *position)
+                    && is_hostile(&relations, (member.faction, false), (other_member.faction, players.get(*other).is_some()))
+                })
+                .min_by_key(|(_other, coordinate, _member, _stats)| (coordinate.x as i32 - position.x as i32).abs().max((coordinate.y as i32 - position.y as i32).abs()))
+                .map(|(other, _coordinate, _member, _stats)| other);
+
+            if let Some(target) = target
+            {
+                let name = names.get(turret_entity).map(|name| name.name.as_str()).unwrap_or("turret");
+
+                SufferDamage::new_damage(&mut suffering, target, turret.damage);
+
+                if players.get(target).is_some()
+                {
+                    log.add(format!("The {} shoots you for {} damage.", name, turret.damage));
+                }
+                else if player_view.contains(position)
+                {
+                    let target_name = names.get(target).map(|name| name.name.as_str()).unwrap_or("figure");
+                    log.add(format!("The {} shoots the {}.", name, target_name));
+                }
+            }
+        }
+    }
+}
@@ -1,45 +1,141 @@
-use rltk::{field_of_view, Point};
+use std::collections::HashMap;
+
+use rltk::{field_of_view, Point, DistanceAlg};
 
 use super::map::*;
 use specs::prelude::*;
 
-use super::{entity_components::*, coordinate::Coordinate};
+use super::{entity_components::*, coordinate::Coordinate, map_memory::MapMemory, game_clock::GameClock};
+use super::status_effects::{vision_range, StatusEffects};
 
 pub struct VisibilitySystem {}
 
 impl<'a> System<'a> for VisibilitySystem
 {
     type SystemData = (WriteExpect<'a, Map>,
+                       ReadExpect<'a, GameClock>,
                        Entities<'a>,
                        WriteStorage<'a, Viewshed>,
-                       WriteStorage<'a, Coordinate>,
-                       ReadStorage<'a, Player>);
+                       ReadStorage<'a, Coordinate>,
+                       WriteStorage<'a, MapMemory>,
+                       ReadStorage<'a, StatusEffects>);
 
     fn run(&mut self, data : Self::SystemData)
     {
-        let (mut map, entities, mut viewshed, coordinate, player) = data;
+        let (mut map, game_clock, entities, mut viewsheds, coordinates, mut memories, status_effects) = data;
+        let changed_tiles = map.take_changed_tiles();
+        let map = &*map;
+        let turn = game_clock.turn;
 
-        for (entity, viewshed, coordinate) in (&entities, &mut viewshed, &coordinate).join()
+        (&mut viewsheds, &coordinates, (&mut memories).maybe(), status_effects.maybe()).par_join().for_each(|(viewshed, coordinate, memory, effects)|
         {
+            // Status effects such as hypoxia can narrow how far the viewer sees
+            let range = vision_range(effects, viewshed.range);
+
+            if !viewshed.dirty && changed_tiles.iter().any(|changed_tile| is_within_view_range(*coordinate, range, *changed_tile))
+            {
+                viewshed.dirty = true;
+            }
+
             if viewshed.dirty
             {
-                map.reset_visibility(coordinate.z);
-                viewshed.visible_tiles.clear();
-                viewshed.visible_tiles = field_of_view(Point::new(coordinate.x, coordinate.y), viewshed.range, &*map);
-                viewshed.visible_tiles.retain(|p| p.x >= 0 && p.x < map.map_size.x as i32 && p.y >= 0 && p.y < map.map_size.y as i32);
-            
-                let player_entity : Option<&Player> = player.get(entity);
-
-                if let Some(_player_entity) = player_entity
+                viewshed.visible_tiles = compute_visible_tiles(map, *coordinate, range);
+
+                if let Some(memory) = memory
                 {
                     for visible_tile in viewshed.visible_tiles.iter()
                     {
-                        map.set_tile_visibility(Coordinate { x:visible_tile.x as usize, y: visible_tile.y as usize, z: coordinate.z }, true);
-                        map.set_tile_revealed(Coordinate { x:visible_tile.x as usize, y: visible_tile.y as usize, z: coordinate.z }, true);
+                        if let Some(tile) = map.get(*visible_tile)
+                        {
+                            memory.remember_tile(*visible_tile, tile, turn);
+                        }
                     }
                 }
                 viewshed.dirty = false;
             }
+        });
+
+        // Other entities move without dirtying this viewshed, so sightings are refreshed every run
+        let mut entities_by_tile: HashMap<Coordinate, Vec<Entity>> = HashMap::new();
+
+        for (entity, coordinate) in (&entities, &coordinates).join()
+        {
+            entities_by_tile.entry(*coordinate).or_default().push(entity);
         }
+
+        (&entities, &viewsheds, &mut memories).par_join().for_each(|(viewer, viewshed, memory)|
+        {
+            for visible_tile in viewshed.visible_tiles.iter()
+            {
+                if let Some(seen_entities) = entities_by_tile.get(visible_tile)
+                {
+                    for seen_entity in seen_entities.iter().filter(|seen_entity| **seen_entity != viewer)
+                    {
+                        memory.remember_entity(*seen_entity, *visible_tile, turn);
+                    }
+                }
+            }
+        });
     }
-}
\ No newline at end of file
+}
+
+/// Whether a tile is close enough to a viewer that a change to it could alter what the viewer sees
+pub fn is_within_view_range(origin: Coordinate, range: i32, target: Coordinate) -> bool
+{
+    let flat_distance = DistanceAlg::Pythagoras.distance2d(Point::new(origin.x, origin.y), Point::new(target.x, target.y));
+    let deck_distance = (origin.z as i32 - target.z as i32).abs() as f32 * DECK_HEIGHT;
+
+    flat_distance + deck_distance <= range as f32 + 1.0
+}
+
+/// Runs field of view on the viewer's deck, then follows every visible transparent floor
+/// down and every transparent ceiling up for as long as the viewer's range allows
+pub fn compute_visible_tiles(map: &Map, origin: Coordinate, range: i32) -> Vec<Coordinate>
+{
+    let origin_point = Point::new(origin.x, origin.y);
+    let deck = DeckView::new(map, origin.z);
+    let mut visible_tiles: Vec<Coordinate> = Vec::new();
+
+    for point in field_of_view(origin_point, range, &deck).iter()
+    {
+        if point.x < 0 || point.x >= map.map_size.x as i32 || point.y < 0 || point.y >= map.map_size.y as i32
+        {
+            continue;
+        }
+
+        let deck_tile = Coordinate::new(point.x as usize, point.y as usize, origin.z);
+        let flat_distance = DistanceAlg::Pythagoras.distance2d(origin_point, *point);
+        visible_tiles.push(deck_tile);
+
+        // Looking down through floors
+        let mut current = deck_tile;
+        while let Some(below) = map.below_transparent_floor(current)
+        {
+            if flat_distance + (below.z - origin.z) as f32 * DECK_HEIGHT > range as f32
+            {
+                break;
+            }
+            visible_tiles.push(below);
+            current = below;
+        }
+
+        // Looking up through ceilings, which are the floors of the deck above
+        let mut current = deck_tile;
+        while current.z > 0
+        {
+            let above = Coordinate::new(current.x, current.y, current.z - 1);
+
+            if map.below_transparent_floor(above).is_none() || flat_distance + (origin.z - above.z) as f32 * DECK_HEIGHT > range as f32
+            {
+                break;
+            }
+            visible_tiles.push(above);
+            current = above;
+        }
+    }
+
+    visible_tiles
+}
+
+/// Distance in tiles that one deck of height counts for against a viewshed's range
+pub const DECK_HEIGHT: f32 = 3.0;